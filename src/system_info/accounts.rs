@@ -0,0 +1,512 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{BufReader, ErrorKind, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const PASSWD_PATH: &str = "etc/passwd";
+const GROUP_PATH: &str = "etc/group";
+const SHADOW_PATH: &str = "etc/shadow";
+const SUDOERS_PATH: &str = "etc/sudoers";
+const SUDOERS_DIR: &str = "etc/sudoers.d";
+const UTMP_PATH: &str = "var/run/utmp";
+const WTMP_PATH: &str = "var/log/wtmp";
+const LASTLOG_PATH: &str = "var/log/lastlog";
+
+/// Groups that grant sudo rights in the default sudoers of common
+/// distributions, assumed when sudoers cannot be read.
+const SUDO_GROUPS: [&str; 3] = ["sudo", "wheel", "admin"];
+const NOLOGIN_SHELLS: [&str; 3] = ["nologin", "false", "sync"];
+
+/// Size of glibc `struct utmp`; `ut_tv` is 32-bit even on 64-bit ABIs.
+const UTMP_RECORD_SIZE: usize = 384;
+const UTMP_USER_PROCESS: i16 = 7;
+/// `struct lastlog`: 32-bit time, 32-byte line and 256-byte host.
+const LASTLOG_RECORD_SIZE: u64 = 292;
+
+/// Local users, groups and logins.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct AccountsInfo {
+    pub users: Vec<User>,
+    pub groups: Vec<Group>,
+    /// Current login sessions from utmp.
+    pub sessions: Vec<Session>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct User {
+    pub name: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub gecos: String,
+    pub home: String,
+    pub shell: String,
+    /// False for `nologin`, `false` and similar shells.
+    pub login_shell: bool,
+    /// Primary and supplementary group names.
+    pub groups: Vec<String>,
+    /// Member of a group granted sudo rights.
+    pub sudo: bool,
+    /// Aging fields from `/etc/shadow`, only present if it was readable.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub password: Option<PasswordAging>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub last_login: Option<Login>,
+}
+
+/// `/etc/shadow` metadata. Dates are days since the Unix epoch. The password
+/// hash itself is never stored.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct PasswordAging {
+    pub state: PasswordState,
+    /// 0 means the password must be changed at the next login.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub last_change: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub min_days: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_days: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub warn_days: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub inactive_days: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub expire_date: Option<u64>,
+}
+
+/// Derived from the first character of the password field.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PasswordState {
+    Set,
+    /// `!` or `*`: password login disabled.
+    Locked,
+    /// No password required.
+    Empty,
+    #[default]
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Group {
+    pub name: String,
+    pub gid: u32,
+    /// Supplementary members listed in `/etc/group`.
+    pub members: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Session {
+    pub user: String,
+    /// Terminal, e.g. `pts/0` or `tty1`.
+    pub line: String,
+    /// Remote host for network logins.
+    pub host: String,
+    pub pid: u32,
+    /// Login time in seconds since the Unix epoch.
+    pub login_time: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Login {
+    /// Seconds since the Unix epoch.
+    pub time: u64,
+    pub line: String,
+    pub host: String,
+}
+
+impl AccountsInfo {
+    pub fn new() -> Result<Self> {
+        read_accounts(Path::new("/"))
+    }
+}
+
+fn read_accounts(root: &Path) -> Result<AccountsInfo> {
+    let passwd = fs::read_to_string(root.join(PASSWD_PATH))
+        .with_context(|| format!("Failed to read /{}", PASSWD_PATH))?;
+    let groups = fs::read_to_string(root.join(GROUP_PATH))
+        .map(|content| parse_group(&content))
+        .unwrap_or_default();
+    // Only root can read shadow; without it the aging fields are left out.
+    let shadow = fs::read_to_string(root.join(SHADOW_PATH))
+        .map(|content| parse_shadow(&content))
+        .unwrap_or_default();
+    let sudo_groups = read_sudo_groups(root);
+    let wtmp_logins = read_last_logins(&root.join(WTMP_PATH)).unwrap_or_default();
+
+    let mut users = parse_passwd(&passwd);
+    for user in &mut users {
+        let primary = groups.iter().find(|group| group.gid == user.gid);
+        user.groups = primary
+            .into_iter()
+            .chain(
+                groups
+                    .iter()
+                    .filter(|group| group.members.contains(&user.name)),
+            )
+            .map(|group| group.name.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        user.sudo = user.groups.iter().any(|group| sudo_groups.contains(group));
+        user.password = shadow.get(&user.name).cloned();
+        user.last_login = wtmp_logins
+            .get(&user.name)
+            .cloned()
+            .or_else(|| read_lastlog(&root.join(LASTLOG_PATH), user.uid));
+    }
+
+    Ok(AccountsInfo {
+        users,
+        groups,
+        sessions: fs::read(root.join(UTMP_PATH))
+            .map(|data| parse_utmp(&data))
+            .unwrap_or_default(),
+    })
+}
+
+fn parse_passwd(content: &str) -> Vec<User> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 7 {
+                return None;
+            }
+            let shell = fields[6].to_string();
+            let login_shell = !shell.is_empty()
+                && !NOLOGIN_SHELLS
+                    .iter()
+                    .any(|nologin| shell.rsplit('/').next() == Some(*nologin));
+            Some(User {
+                name: fields[0].to_string(),
+                uid: fields[2].parse().ok()?,
+                gid: fields[3].parse().ok()?,
+                gecos: fields[4].to_string(),
+                home: fields[5].to_string(),
+                shell,
+                login_shell,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+fn parse_group(content: &str) -> Vec<Group> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 4 {
+                return None;
+            }
+            Some(Group {
+                name: fields[0].to_string(),
+                gid: fields[2].parse().ok()?,
+                members: fields[3]
+                    .split(',')
+                    .filter(|member| !member.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// Parses `/etc/shadow` into aging fields by user name, dropping the hash.
+fn parse_shadow(content: &str) -> BTreeMap<String, PasswordAging> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 8 {
+                return None;
+            }
+            let number = |index: usize| fields[index].parse().ok();
+            let state = match fields[1].chars().next() {
+                None => PasswordState::Empty,
+                Some('!') | Some('*') => PasswordState::Locked,
+                Some(_) => PasswordState::Set,
+            };
+            Some((
+                fields[0].to_string(),
+                PasswordAging {
+                    state,
+                    last_change: number(2),
+                    min_days: number(3),
+                    max_days: number(4),
+                    warn_days: number(5),
+                    inactive_days: number(6),
+                    expire_date: number(7),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Returns the groups granted sudo rights by `%group` entries in sudoers and
+/// sudoers.d. Only when sudoers cannot be read (it is usually readable by
+/// root only) are the distribution defaults assumed instead.
+fn read_sudo_groups(root: &Path) -> BTreeSet<String> {
+    let mut groups: BTreeSet<String> = match fs::read_to_string(root.join(SUDOERS_PATH)) {
+        Ok(content) => parse_sudoers_groups(&content).into_iter().collect(),
+        Err(_) => SUDO_GROUPS.iter().map(|g| g.to_string()).collect(),
+    };
+    if let Ok(entries) = fs::read_dir(root.join(SUDOERS_DIR)) {
+        for content in entries
+            .flatten()
+            .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        {
+            groups.extend(parse_sudoers_groups(&content));
+        }
+    }
+    groups
+}
+
+fn parse_sudoers_groups(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix('%'))
+        .filter_map(|rule| rule.split_whitespace().next())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses utmp records, keeping user logins.
+fn parse_utmp(data: &[u8]) -> Vec<Session> {
+    data.chunks_exact(UTMP_RECORD_SIZE)
+        .filter_map(parse_utmp_record)
+        .collect()
+}
+
+fn parse_utmp_record(record: &[u8]) -> Option<Session> {
+    if i16::from_ne_bytes([record[0], record[1]]) != UTMP_USER_PROCESS {
+        return None;
+    }
+    let i32_at = |offset: usize| i32::from_ne_bytes(record[offset..offset + 4].try_into().unwrap());
+    let session = Session {
+        pid: i32_at(4) as u32,
+        line: c_string(&record[8..40]),
+        user: c_string(&record[44..76]),
+        host: c_string(&record[76..332]),
+        login_time: i32_at(340) as u32 as u64,
+    };
+    (!session.user.is_empty()).then_some(session)
+}
+
+/// Latest login per user from wtmp. The file grows without bound between
+/// rotations, so it is read one record at a time.
+fn read_last_logins(path: &Path) -> Result<BTreeMap<String, Login>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut record = [0u8; UTMP_RECORD_SIZE];
+    let mut logins: BTreeMap<String, Login> = BTreeMap::new();
+    loop {
+        match reader.read_exact(&mut record) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", path.display()))
+            }
+        }
+        let Some(session) = parse_utmp_record(&record) else {
+            continue;
+        };
+        if logins
+            .get(&session.user)
+            .is_some_and(|existing| existing.time > session.login_time)
+        {
+            continue;
+        }
+        logins.insert(
+            session.user,
+            Login {
+                time: session.login_time,
+                line: session.line,
+                host: session.host,
+            },
+        );
+    }
+    Ok(logins)
+}
+
+/// Reads the lastlog record of `uid`. The file is sparse and indexed by UID.
+fn read_lastlog(path: &Path, uid: u32) -> Option<Login> {
+    let mut file = File::open(path).ok()?;
+    file.seek(SeekFrom::Start(uid as u64 * LASTLOG_RECORD_SIZE))
+        .ok()?;
+    let mut record = [0u8; LASTLOG_RECORD_SIZE as usize];
+    file.read_exact(&mut record).ok()?;
+    parse_lastlog_record(&record)
+}
+
+fn parse_lastlog_record(record: &[u8]) -> Option<Login> {
+    let time = u32::from_ne_bytes(record[..4].try_into().ok()?) as u64;
+    (time != 0).then(|| Login {
+        time,
+        line: c_string(&record[4..36]),
+        host: c_string(&record[36..292]),
+    })
+}
+
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::test_util::TempDir;
+
+    fn utmp_record(kind: i16, pid: i32, line: &str, user: &str, host: &str, time: i32) -> Vec<u8> {
+        let mut record = vec![0u8; UTMP_RECORD_SIZE];
+        record[..2].copy_from_slice(&kind.to_ne_bytes());
+        record[4..8].copy_from_slice(&pid.to_ne_bytes());
+        record[8..8 + line.len()].copy_from_slice(line.as_bytes());
+        record[44..44 + user.len()].copy_from_slice(user.as_bytes());
+        record[76..76 + host.len()].copy_from_slice(host.as_bytes());
+        record[340..344].copy_from_slice(&time.to_ne_bytes());
+        record
+    }
+
+    #[test]
+    fn test_read_accounts() -> Result<()> {
+        let root = TempDir::new("accounts");
+        fs::create_dir_all(root.join("etc/sudoers.d"))?;
+        fs::create_dir_all(root.join("var/run"))?;
+        fs::create_dir_all(root.join("var/log"))?;
+
+        fs::write(
+            root.join(PASSWD_PATH),
+            "root:x:0:0:root:/root:/bin/bash\n\
+             daemon:x:1:1:daemon:/usr/sbin:/usr/sbin/nologin\n\
+             alice:x:1000:1000:Alice,,,:/home/alice:/bin/zsh\n\
+             bob:x:1001:1001::/home/bob:/bin/bash\n",
+        )?;
+        fs::write(
+            root.join(GROUP_PATH),
+            "root:x:0:\ndaemon:x:1:\nsudo:x:27:alice\nalice:x:1000:\nbob:x:1001:\nops:x:2000:bob\n",
+        )?;
+        fs::write(
+            root.join(SHADOW_PATH),
+            "root:*:19000:0:99999:7:::\n\
+             alice:$y$j9T$salt$hash:19500:0:90:7:14::\n\
+             bob::19501:0:99999:7::20000:\n",
+        )?;
+        fs::write(
+            root.join("etc/sudoers.d/ops"),
+            "%ops ALL=(ALL) NOPASSWD: ALL\n",
+        )?;
+
+        let utmp = [
+            utmp_record(2, 0, "~", "reboot", "6.1.0", 1700000000),
+            utmp_record(
+                UTMP_USER_PROCESS,
+                4242,
+                "pts/0",
+                "alice",
+                "10.0.0.5",
+                1700000100,
+            ),
+        ]
+        .concat();
+        fs::write(root.join(UTMP_PATH), &utmp)?;
+        let wtmp = [
+            utmp_record(UTMP_USER_PROCESS, 100, "tty1", "alice", "", 1690000000),
+            utmp_record(
+                UTMP_USER_PROCESS,
+                4242,
+                "pts/0",
+                "alice",
+                "10.0.0.5",
+                1700000100,
+            ),
+            utmp_record(8, 4242, "pts/0", "", "", 1700000200),
+        ]
+        .concat();
+        fs::write(root.join(WTMP_PATH), &wtmp)?;
+        let mut lastlog = vec![0u8; 1002 * LASTLOG_RECORD_SIZE as usize];
+        let bob = 1001 * LASTLOG_RECORD_SIZE as usize;
+        lastlog[bob..bob + 4].copy_from_slice(&1680000000u32.to_ne_bytes());
+        lastlog[bob + 4..bob + 8].copy_from_slice(b"tty2");
+        fs::write(root.join(LASTLOG_PATH), &lastlog)?;
+
+        let accounts = read_accounts(&root)?;
+        assert_eq!(accounts.users.len(), 4);
+        assert_eq!(accounts.groups.len(), 6);
+
+        let user = |name: &str| accounts.users.iter().find(|u| u.name == name).unwrap();
+        assert!(!user("daemon").login_shell);
+        assert!(user("root").login_shell);
+
+        let alice = user("alice");
+        assert_eq!(alice.groups, vec!["alice", "sudo"]);
+        assert!(alice.sudo);
+        let password = alice.password.as_ref().unwrap();
+        assert_eq!(password.state, PasswordState::Set);
+        assert_eq!(password.max_days, Some(90));
+        assert_eq!(password.inactive_days, Some(14));
+        assert_eq!(password.expire_date, None);
+        assert_eq!(alice.last_login.as_ref().unwrap().time, 1700000100);
+        assert_eq!(alice.last_login.as_ref().unwrap().host, "10.0.0.5");
+
+        let bob = user("bob");
+        assert!(bob.sudo);
+        assert_eq!(bob.password.as_ref().unwrap().state, PasswordState::Empty);
+        assert_eq!(bob.password.as_ref().unwrap().expire_date, Some(20000));
+        assert_eq!(bob.last_login.as_ref().unwrap().line, "tty2");
+        assert_eq!(
+            user("root").password.as_ref().unwrap().state,
+            PasswordState::Locked
+        );
+        assert!(user("daemon").password.is_none());
+
+        assert_eq!(
+            accounts.sessions,
+            vec![Session {
+                user: "alice".to_string(),
+                line: "pts/0".to_string(),
+                host: "10.0.0.5".to_string(),
+                pid: 4242,
+                login_time: 1700000100,
+            }]
+        );
+
+        // No hash may leak into the serialized form.
+        assert!(!serde_json::to_string(&accounts)?.contains("$y$"));
+
+        // A readable sudoers replaces the distribution defaults, so being in
+        // `sudo` no longer counts when only `wheel` is granted.
+        fs::write(
+            root.join(SUDOERS_PATH),
+            "root ALL=(ALL:ALL) ALL
+# %sudo ALL=(ALL:ALL) ALL
+%wheel ALL=(ALL) ALL
+@includedir /etc/sudoers.d
+",
+        )?;
+        let accounts = read_accounts(&root)?;
+        let user = |name: &str| accounts.users.iter().find(|u| u.name == name).unwrap();
+        assert!(!user("alice").sudo);
+        assert!(user("bob").sudo);
+
+        // A truncated trailing record is ignored.
+        let mut truncated = wtmp.clone();
+        truncated.extend_from_slice(&[0u8; 100]);
+        fs::write(root.join(WTMP_PATH), &truncated)?;
+        let logins = read_last_logins(&root.join(WTMP_PATH))?;
+        assert_eq!(logins["alice"].time, 1700000100);
+        Ok(())
+    }
+
+    #[test]
+    fn test_accounts_info_new() -> Result<()> {
+        let accounts = AccountsInfo::new()?;
+        assert!(accounts.users.iter().any(|user| user.uid == 0));
+        Ok(())
+    }
+}
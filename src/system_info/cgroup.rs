@@ -0,0 +1,506 @@
+use super::filesystem::{read_mount_table, MountEntry, MountFilter, MOUNTINFO_PATH};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PROC_SELF_CGROUP_PATH: &str = "/proc/self/cgroup";
+
+/// cgroup v1 reports "no limit" as `LONG_MAX` rounded down to the page size.
+const V1_UNLIMITED: u64 = 1 << 62;
+
+/// How cgroup hierarchies are mounted.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CgroupMode {
+    /// Only legacy per-controller hierarchies.
+    V1,
+    /// Only the unified hierarchy.
+    V2,
+    /// Controllers in v1 hierarchies plus a controller-less unified
+    /// hierarchy, usually at `/sys/fs/cgroup/unified`.
+    Hybrid,
+    /// No cgroup filesystem mounted.
+    #[default]
+    Other,
+}
+
+/// Resource limits that apply to the current process through its cgroup.
+///
+/// Limits are the tightest found between the process's cgroup and the root
+/// of the hierarchy, since a parent's limit also constrains its children.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct CgroupInfo {
+    pub mode: CgroupMode,
+    /// Path of the current cgroup from `/proc/self/cgroup`: the unified
+    /// hierarchy on v2, the `cpu` hierarchy on v1.
+    pub path: String,
+    /// Directory of the current cgroup in the unified hierarchy, e.g. for
+    /// [`read_cgroup_pressure`](super::pressure::read_cgroup_pressure).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub unified_dir: Option<String>,
+    /// CPU bandwidth limit as a number of CPUs, i.e. quota / period.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cpu_limit: Option<f64>,
+    /// Quota and period in microseconds of the level setting `cpu_limit`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cpu_quota_us: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cpu_period_us: Option<u64>,
+    /// CPUs and memory nodes the process may use, e.g. `0-3,8`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cpuset_cpus: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cpuset_mems: Option<String>,
+    /// Hard memory limit in bytes (`memory.max`, v1 `memory.limit_in_bytes`).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub memory_max: Option<u64>,
+    /// Throttling threshold in bytes (`memory.high`, v2 only).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub memory_high: Option<u64>,
+    /// Swap limit in bytes. On v1 derived from `memory.memsw.limit_in_bytes`
+    /// minus the memory limit.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub swap_max: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub pids_max: Option<u64>,
+    /// Default IO weight of the cgroup (`io.weight`, v1 `blkio.weight`).
+    /// Weights are relative to siblings, so they are not walked up.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub io_weight: Option<u64>,
+    /// Per-device IO weights keyed by `major:minor`.
+    #[serde(default)]
+    pub io_device_weights: BTreeMap<String, u64>,
+}
+
+/// A mounted hierarchy together with the process's position in it.
+#[derive(Debug)]
+struct Hierarchy {
+    unified: bool,
+    controllers: Vec<String>,
+    mount_point: PathBuf,
+    /// Directory of the current cgroup below `mount_point`.
+    dir: PathBuf,
+    path: String,
+}
+
+impl Hierarchy {
+    /// Directories from the current cgroup up to the hierarchy root.
+    fn ancestors(&self) -> impl Iterator<Item = &Path> {
+        self.dir
+            .ancestors()
+            .take_while(move |dir| dir.starts_with(&self.mount_point))
+    }
+
+    fn read(&self, dir: &Path, file: &str) -> Option<String> {
+        fs::read_to_string(dir.join(file))
+            .ok()
+            .map(|content| content.trim().to_string())
+    }
+
+    /// Smallest value of `file` over all levels, ignoring "max" and `-1`.
+    fn tightest(&self, file: &str) -> Option<u64> {
+        self.ancestors()
+            .filter_map(|dir| parse_limit(&self.read(dir, file)?))
+            .min()
+    }
+}
+
+impl CgroupInfo {
+    pub fn new() -> Result<Self> {
+        let mounts = read_mount_table(MOUNTINFO_PATH, MountFilter::new())?;
+        let proc_cgroup = fs::read_to_string(PROC_SELF_CGROUP_PATH)
+            .with_context(|| format!("Failed to read {}", PROC_SELF_CGROUP_PATH))?;
+        Self::from_mounts(&mounts, &proc_cgroup, Path::new("/"))
+    }
+
+    /// Number of CPUs the process can use: the CPU limit or, if lower, the
+    /// size of the cpuset.
+    pub fn available_cpus(&self) -> Option<f64> {
+        let cpuset = self
+            .cpuset_cpus
+            .as_deref()
+            .and_then(count_cpu_list)
+            .map(|count| count as f64);
+        match (self.cpu_limit, cpuset) {
+            (Some(limit), Some(cpuset)) => Some(limit.min(cpuset)),
+            (limit, cpuset) => limit.or(cpuset),
+        }
+    }
+
+    /// Builds the info from mount entries and `/proc/self/cgroup`, with
+    /// mount points resolved below `sysroot`.
+    fn from_mounts(mounts: &[MountEntry], proc_cgroup: &str, sysroot: &Path) -> Result<Self> {
+        let memberships: Vec<(&str, &str)> = proc_cgroup
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(3, ':');
+                let (_, controllers, path) = (parts.next()?, parts.next()?, parts.next()?);
+                Some((controllers, path))
+            })
+            .collect();
+        if memberships.is_empty() {
+            bail!("No cgroup memberships in {}", PROC_SELF_CGROUP_PATH);
+        }
+
+        let hierarchies: Vec<Hierarchy> = mounts
+            .iter()
+            .filter_map(|mount| {
+                let unified = match mount.fstype.as_str() {
+                    "cgroup2" => true,
+                    "cgroup" => false,
+                    _ => return None,
+                };
+                // v1 memberships list the hierarchy's controllers, e.g.
+                // `cpu,cpuacct` or `name=systemd`, which also appear in the
+                // mount's super options.
+                let (controllers, path) = memberships.iter().find(|(controllers, _)| {
+                    if unified {
+                        controllers.is_empty()
+                    } else {
+                        !controllers.is_empty()
+                            && controllers.split(',').all(|controller| {
+                                mount.super_options.iter().any(|o| o == controller)
+                            })
+                    }
+                })?;
+                let controllers: Vec<String> = controllers
+                    .split(',')
+                    .filter(|controller| !controller.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                let mount_point = sysroot.join(mount.target.trim_start_matches('/'));
+                Some(Hierarchy {
+                    unified,
+                    dir: cgroup_dir(&mount_point, &mount.root, path),
+                    mount_point,
+                    controllers,
+                    path: path.to_string(),
+                })
+            })
+            .collect();
+
+        // Named hierarchies such as `name=systemd` carry no controllers.
+        let has_v1 = hierarchies
+            .iter()
+            .any(|h| h.controllers.iter().any(|c| !c.starts_with("name=")));
+        let unified = hierarchies.iter().find(|h| h.unified);
+        let mode = match (has_v1, unified.is_some()) {
+            (true, true) => CgroupMode::Hybrid,
+            (true, false) => CgroupMode::V1,
+            (false, true) => CgroupMode::V2,
+            (false, false) => CgroupMode::Other,
+        };
+        // Controllers live in their v1 hierarchies unless everything is
+        // unified; the unified hierarchy of hybrid mode has no controllers.
+        let controller = |name: &str| {
+            if mode == CgroupMode::V2 {
+                unified
+            } else {
+                hierarchies
+                    .iter()
+                    .find(|h| h.controllers.iter().any(|c| c == name))
+            }
+        };
+
+        let mut info = CgroupInfo {
+            mode,
+            path: controller("cpu")
+                .map(|h| h.path.clone())
+                .unwrap_or_else(|| memberships[0].1.to_string()),
+            unified_dir: unified.map(|h| h.dir.display().to_string()),
+            ..Default::default()
+        };
+
+        if let Some(cpu) = controller("cpu") {
+            let limits = cpu.ancestors().filter_map(|dir| {
+                if cpu.unified {
+                    parse_cpu_max(&cpu.read(dir, "cpu.max")?)
+                } else {
+                    let quota = cpu.read(dir, "cpu.cfs_quota_us")?.parse::<i64>().ok()?;
+                    let period = cpu.read(dir, "cpu.cfs_period_us")?.parse().ok()?;
+                    (quota > 0 && period > 0).then_some((quota as u64, period))
+                }
+            });
+            if let Some((quota, period)) = limits.min_by(|(q1, p1), (q2, p2)| {
+                (*q1 as f64 / *p1 as f64).total_cmp(&(*q2 as f64 / *p2 as f64))
+            }) {
+                info.cpu_limit = Some(quota as f64 / period as f64);
+                info.cpu_quota_us = Some(quota);
+                info.cpu_period_us = Some(period);
+            }
+        }
+
+        if let Some(cpuset) = controller("cpuset") {
+            let (cpus, mems) = if cpuset.unified {
+                ("cpuset.cpus.effective", "cpuset.mems.effective")
+            } else {
+                ("cpuset.effective_cpus", "cpuset.effective_mems")
+            };
+            info.cpuset_cpus = cpuset.read(&cpuset.dir, cpus).filter(|s| !s.is_empty());
+            info.cpuset_mems = cpuset.read(&cpuset.dir, mems).filter(|s| !s.is_empty());
+        }
+
+        if let Some(memory) = controller("memory") {
+            if memory.unified {
+                info.memory_max = memory.tightest("memory.max");
+                info.memory_high = memory.tightest("memory.high");
+                info.swap_max = memory.tightest("memory.swap.max");
+            } else {
+                info.memory_max = memory.tightest("memory.limit_in_bytes");
+                info.swap_max = memory
+                    .tightest("memory.memsw.limit_in_bytes")
+                    .map(|memsw| memsw.saturating_sub(info.memory_max.unwrap_or(0)));
+            }
+        }
+
+        if let Some(pids) = controller("pids") {
+            info.pids_max = pids.tightest("pids.max");
+        }
+
+        let io = controller("io").or_else(|| controller("blkio"));
+        if let Some(io) = io {
+            let (weight_file, device_file) = if io.unified {
+                ("io.weight", None)
+            } else {
+                ("blkio.weight", Some("blkio.weight_device"))
+            };
+            if let Some(content) = io.read(&io.dir, weight_file) {
+                let (weight, devices) = parse_io_weights(&content);
+                info.io_weight = weight;
+                info.io_device_weights = devices;
+            }
+            if let Some(content) = device_file.and_then(|file| io.read(&io.dir, file)) {
+                info.io_device_weights.extend(parse_io_weights(&content).1);
+            }
+        }
+
+        Ok(info)
+    }
+}
+
+/// Resolves the cgroup directory below a mount point. The mount may expose
+/// only a subtree (`root`), as with Docker on v1 or cgroup namespaces, in
+/// which case the mount point itself is the process's cgroup.
+fn cgroup_dir(mount_point: &Path, mount_root: &str, path: &str) -> PathBuf {
+    let relative = if mount_root == "/" {
+        path
+    } else {
+        path.strip_prefix(mount_root).unwrap_or("")
+    };
+    let relative = relative.trim_start_matches('/');
+    let dir = mount_point.join(relative);
+    if !relative.is_empty() && dir.is_dir() {
+        dir
+    } else {
+        mount_point.to_path_buf()
+    }
+}
+
+/// Parses a limit value; "max" and negative values mean unlimited.
+fn parse_limit(value: &str) -> Option<u64> {
+    value
+        .parse::<u64>()
+        .ok()
+        .filter(|limit| *limit < V1_UNLIMITED)
+}
+
+/// Parses `cpu.max`, e.g. `150000 100000` or `max 100000`.
+fn parse_cpu_max(content: &str) -> Option<(u64, u64)> {
+    let (quota, period) = content.split_once(' ')?;
+    Some((quota.parse().ok()?, period.trim().parse().ok()?))
+}
+
+/// Parses `io.weight` (`default 100` plus `8:0 200` lines) or v1
+/// `blkio.weight` (a single number) and `blkio.weight_device`.
+fn parse_io_weights(content: &str) -> (Option<u64>, BTreeMap<String, u64>) {
+    let mut default = None;
+    let mut devices = BTreeMap::new();
+    for line in content.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [weight] => default = weight.parse().ok(),
+            ["default", weight] => default = weight.parse().ok(),
+            [device, weight] => {
+                if let Ok(weight) = weight.parse() {
+                    devices.insert(device.to_string(), weight);
+                }
+            }
+            _ => {}
+        }
+    }
+    (default, devices)
+}
+
+/// Counts the CPUs in a list such as `0-3,8,10-11`.
+fn count_cpu_list(list: &str) -> Option<usize> {
+    list.split(',')
+        .filter(|range| !range.is_empty())
+        .map(|range| match range.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?);
+                end.checked_sub(start).map(|span| span + 1)
+            }
+            None => range.parse::<usize>().ok().map(|_| 1),
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::test_util::TempDir;
+
+    fn write(root: &Path, path: &str, content: &str) -> Result<()> {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    fn mounts(root: &Path, mountinfo: &str) -> Result<Vec<MountEntry>> {
+        let path = root.join("mountinfo");
+        fs::write(&path, mountinfo)?;
+        read_mount_table(&path, MountFilter::new())
+    }
+
+    #[test]
+    fn test_cgroup_v2_limits() -> Result<()> {
+        let root = TempDir::new("cgroup_v2");
+        let pod = "sys/fs/cgroup/kubepods/pod1";
+        let ctr = "sys/fs/cgroup/kubepods/pod1/ctr";
+        write(&root, "sys/fs/cgroup/kubepods/cpu.max", "400000 100000\n")?;
+        write(&root, &format!("{}/cpu.max", pod), "150000 100000\n")?;
+        write(&root, &format!("{}/memory.max", pod), "536870912\n")?;
+        write(&root, &format!("{}/pids.max", pod), "100\n")?;
+        write(&root, &format!("{}/cpu.max", ctr), "max 100000\n")?;
+        write(&root, &format!("{}/memory.max", ctr), "max\n")?;
+        write(&root, &format!("{}/memory.high", ctr), "268435456\n")?;
+        write(&root, &format!("{}/memory.swap.max", ctr), "0\n")?;
+        write(&root, &format!("{}/pids.max", ctr), "max\n")?;
+        write(&root, &format!("{}/cpuset.cpus.effective", ctr), "0-3\n")?;
+        write(&root, &format!("{}/cpuset.mems.effective", ctr), "0\n")?;
+        write(
+            &root,
+            &format!("{}/io.weight", ctr),
+            "default 100\n8:0 200\n",
+        )?;
+
+        let mounts = mounts(
+            &root,
+            "30 24 0:26 / /sys/fs/cgroup rw,nosuid shared:4 - cgroup2 cgroup2 rw,nsdelegate\n",
+        )?;
+        let info = CgroupInfo::from_mounts(&mounts, "0::/kubepods/pod1/ctr\n", &root)?;
+        assert_eq!(info.mode, CgroupMode::V2);
+        assert_eq!(info.path, "/kubepods/pod1/ctr");
+        assert_eq!(info.unified_dir, Some(root.join(ctr).display().to_string()));
+        assert_eq!(info.cpu_limit, Some(1.5));
+        assert_eq!(
+            (info.cpu_quota_us, info.cpu_period_us),
+            (Some(150000), Some(100000))
+        );
+        assert_eq!(info.cpuset_cpus.as_deref(), Some("0-3"));
+        assert_eq!(info.cpuset_mems.as_deref(), Some("0"));
+        assert_eq!(info.memory_max, Some(536870912));
+        assert_eq!(info.memory_high, Some(268435456));
+        assert_eq!(info.swap_max, Some(0));
+        assert_eq!(info.pids_max, Some(100));
+        assert_eq!(info.io_weight, Some(100));
+        assert_eq!(info.io_device_weights.get("8:0"), Some(&200));
+        assert_eq!(info.available_cpus(), Some(1.5));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cgroup_hybrid_limits() -> Result<()> {
+        let root = TempDir::new("cgroup_hybrid");
+        let cpu = "sys/fs/cgroup/cpu,cpuacct/system.slice/app.service";
+        write(&root, &format!("{}/cpu.cfs_quota_us", cpu), "-1\n")?;
+        write(&root, &format!("{}/cpu.cfs_period_us", cpu), "100000\n")?;
+        write(
+            &root,
+            "sys/fs/cgroup/cpu,cpuacct/system.slice/cpu.cfs_quota_us",
+            "200000\n",
+        )?;
+        write(
+            &root,
+            "sys/fs/cgroup/cpu,cpuacct/system.slice/cpu.cfs_period_us",
+            "100000\n",
+        )?;
+        // Docker-style mount exposing only the container's subtree.
+        write(
+            &root,
+            "sys/fs/cgroup/memory/memory.limit_in_bytes",
+            "1073741824\n",
+        )?;
+        write(
+            &root,
+            "sys/fs/cgroup/memory/memory.memsw.limit_in_bytes",
+            "2147483648\n",
+        )?;
+        write(
+            &root,
+            "sys/fs/cgroup/cpuset/cpuset.effective_cpus",
+            "0-1,4\n",
+        )?;
+        write(&root, "sys/fs/cgroup/pids/pids.max", "max\n")?;
+        write(&root, "sys/fs/cgroup/blkio/blkio.weight", "500\n")?;
+        write(
+            &root,
+            "sys/fs/cgroup/blkio/blkio.weight_device",
+            "8:16 300\n",
+        )?;
+        fs::create_dir_all(root.join("sys/fs/cgroup/unified/system.slice/app.service"))?;
+
+        let mounts = mounts(
+            &root,
+            "33 32 0:29 / /sys/fs/cgroup/cpu,cpuacct rw - cgroup cgroup rw,cpu,cpuacct\n\
+             36 32 0:32 /docker/abc /sys/fs/cgroup/memory rw - cgroup cgroup rw,memory\n\
+             35 32 0:31 / /sys/fs/cgroup/cpuset rw - cgroup cgroup rw,cpuset\n\
+             40 32 0:36 / /sys/fs/cgroup/pids rw - cgroup cgroup rw,pids\n\
+             39 32 0:35 / /sys/fs/cgroup/blkio rw - cgroup cgroup rw,blkio\n\
+             41 32 0:37 / /sys/fs/cgroup/systemd rw - cgroup cgroup rw,xattr,name=systemd\n\
+             42 32 0:38 / /sys/fs/cgroup/unified rw - cgroup2 cgroup2 rw\n",
+        )?;
+        let proc_cgroup = "9:pids:/\n8:blkio:/\n6:cpuset:/\n4:memory:/docker/abc\n\
+                           2:cpuacct,cpu:/system.slice/app.service\n\
+                           1:name=systemd:/system.slice/app.service\n\
+                           0::/system.slice/app.service\n";
+        let info = CgroupInfo::from_mounts(&mounts, proc_cgroup, &root)?;
+        assert_eq!(info.mode, CgroupMode::Hybrid);
+        assert_eq!(info.path, "/system.slice/app.service");
+        assert_eq!(info.cpu_limit, Some(2.0));
+        assert_eq!(info.memory_max, Some(1073741824));
+        assert_eq!(info.memory_high, None);
+        assert_eq!(info.swap_max, Some(1073741824));
+        assert_eq!(info.pids_max, None);
+        assert_eq!(info.cpuset_cpus.as_deref(), Some("0-1,4"));
+        assert_eq!(info.io_weight, Some(500));
+        assert_eq!(info.io_device_weights.get("8:16"), Some(&300));
+        assert_eq!(info.available_cpus(), Some(2.0));
+        assert!(info
+            .unified_dir
+            .unwrap()
+            .ends_with("unified/system.slice/app.service"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cgroup_helpers() {
+        assert_eq!(count_cpu_list("0-3,8,10-11"), Some(7));
+        assert_eq!(count_cpu_list("3-1"), None);
+        assert_eq!(parse_limit("max"), None);
+        assert_eq!(parse_limit("9223372036854771712"), None);
+        assert_eq!(parse_limit("-1"), None);
+        assert_eq!(parse_cpu_max("max 100000"), None);
+    }
+
+    #[test]
+    fn test_cgroup_info_new() -> Result<()> {
+        let info = CgroupInfo::new()?;
+        assert_ne!(info.mode, CgroupMode::Other);
+        assert!(info.path.starts_with('/'));
+        Ok(())
+    }
+}
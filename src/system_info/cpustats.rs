@@ -0,0 +1,321 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+pub const PROC_STAT_PATH: &str = "/proc/stat";
+
+/// Cumulative time a CPU spent in each mode, in jiffies (`USER_HZ`).
+///
+/// The kernel also counts `guest` in `user` and `guest_nice` in `nice`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct CpuTimes {
+    /// `cpu` for the sum over all CPUs, otherwise `cpu0`, `cpu1`, ...
+    pub name: String,
+    pub user: u64,
+    pub nice: u64,
+    pub system: u64,
+    pub idle: u64,
+    pub iowait: u64,
+    pub irq: u64,
+    pub softirq: u64,
+    /// Time a hypervisor ran something else while this virtual CPU was
+    /// runnable.
+    pub steal: u64,
+    pub guest: u64,
+    pub guest_nice: u64,
+}
+
+/// Counters from `/proc/stat`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct CpuStats {
+    /// The aggregate `cpu` line followed by the per-CPU lines.
+    pub cpus: Vec<CpuTimes>,
+    pub context_switches: u64,
+    pub interrupts: u64,
+    /// Processes and threads created since boot.
+    pub forks: u64,
+    pub procs_running: u32,
+    pub procs_blocked: u32,
+}
+
+/// [`CpuStats`] taken at a single point in time.
+#[derive(Debug, Clone)]
+pub struct CpuStatsSample {
+    pub taken_at: Instant,
+    pub stats: CpuStats,
+}
+
+/// Share of time per mode between two samples, in percent. The modes add up
+/// to 100: `user` and `nice` exclude the guest time reported separately.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct CpuUtilization {
+    pub name: String,
+    pub user_percent: f64,
+    pub nice_percent: f64,
+    pub system_percent: f64,
+    pub idle_percent: f64,
+    pub iowait_percent: f64,
+    pub irq_percent: f64,
+    pub softirq_percent: f64,
+    pub steal_percent: f64,
+    pub guest_percent: f64,
+    pub guest_nice_percent: f64,
+    /// Everything except `idle` and `iowait`.
+    pub busy_percent: f64,
+}
+
+/// Rates derived from two [`CpuStatsSample`]s.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct CpuRates {
+    /// Utilization of the aggregate `cpu` line followed by each CPU.
+    pub cpus: Vec<CpuUtilization>,
+    pub context_switches_per_sec: f64,
+    pub interrupts_per_sec: f64,
+    pub forks_per_sec: f64,
+}
+
+pub fn sample_cpu_stats() -> Result<CpuStatsSample> {
+    Ok(CpuStatsSample {
+        taken_at: Instant::now(),
+        stats: read_cpu_stats(PROC_STAT_PATH)?,
+    })
+}
+
+pub fn read_cpu_stats<P: AsRef<Path>>(path: P) -> Result<CpuStats> {
+    let path = path.as_ref();
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_cpu_stats(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn parse_cpu_stats(content: &str) -> Result<CpuStats> {
+    let mut stats = CpuStats::default();
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(key) = fields.next() else {
+            continue;
+        };
+        let value = || -> Result<u64> {
+            line.split_whitespace()
+                .nth(1)
+                .context("Missing value")?
+                .parse()
+                .with_context(|| format!("Invalid line: {}", line))
+        };
+        match key {
+            _ if key.starts_with("cpu") => stats.cpus.push(parse_cpu_line(key, fields, line)?),
+            "ctxt" => stats.context_switches = value()?,
+            // The first field is the total, followed by per-IRQ counts.
+            "intr" => stats.interrupts = value()?,
+            "processes" => stats.forks = value()?,
+            "procs_running" => stats.procs_running = value()? as u32,
+            "procs_blocked" => stats.procs_blocked = value()? as u32,
+            _ => {}
+        }
+    }
+    if stats.cpus.is_empty() {
+        bail!("No cpu lines found");
+    }
+    Ok(stats)
+}
+
+fn parse_cpu_line<'a>(
+    name: &str,
+    fields: impl Iterator<Item = &'a str>,
+    line: &str,
+) -> Result<CpuTimes> {
+    let values = fields
+        .map(str::parse::<u64>)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Invalid cpu line: {}", line))?;
+    if values.len() < 4 {
+        bail!("Truncated cpu line: {}", line);
+    }
+    // Columns after idle were added over time (iowait, irq and softirq in
+    // 2.6, steal in 2.6.11, guest in 2.6.24, guest_nice in 2.6.33).
+    let value = |index: usize| values.get(index).copied().unwrap_or(0);
+
+    Ok(CpuTimes {
+        name: name.to_string(),
+        user: value(0),
+        nice: value(1),
+        system: value(2),
+        idle: value(3),
+        iowait: value(4),
+        irq: value(5),
+        softirq: value(6),
+        steal: value(7),
+        guest: value(8),
+        guest_nice: value(9),
+    })
+}
+
+/// Computes utilization per CPU and system-wide event rates between two
+/// samples.
+///
+/// CPUs missing from either sample (hotplug) are skipped, and counters that
+/// went backwards yield zero.
+pub fn compute_cpu_rates(previous: &CpuStatsSample, current: &CpuStatsSample) -> CpuRates {
+    let secs = current
+        .taken_at
+        .saturating_duration_since(previous.taken_at)
+        .as_secs_f64();
+    let per_sec = |prev: u64, curr: u64| {
+        if secs > 0.0 {
+            curr.saturating_sub(prev) as f64 / secs
+        } else {
+            0.0
+        }
+    };
+    let (prev, curr) = (&previous.stats, &current.stats);
+
+    CpuRates {
+        cpus: curr
+            .cpus
+            .iter()
+            .filter_map(|curr_cpu| {
+                let prev_cpu = prev.cpus.iter().find(|cpu| cpu.name == curr_cpu.name)?;
+                Some(utilization_between(prev_cpu, curr_cpu))
+            })
+            .collect(),
+        context_switches_per_sec: per_sec(prev.context_switches, curr.context_switches),
+        interrupts_per_sec: per_sec(prev.interrupts, curr.interrupts),
+        forks_per_sec: per_sec(prev.forks, curr.forks),
+    }
+}
+
+fn utilization_between(prev: &CpuTimes, curr: &CpuTimes) -> CpuUtilization {
+    let delta = |prev: u64, curr: u64| curr.saturating_sub(prev);
+    let guest = delta(prev.guest, curr.guest);
+    let guest_nice = delta(prev.guest_nice, curr.guest_nice);
+    let user = delta(prev.user, curr.user).saturating_sub(guest);
+    let nice = delta(prev.nice, curr.nice).saturating_sub(guest_nice);
+    let system = delta(prev.system, curr.system);
+    let idle = delta(prev.idle, curr.idle);
+    let iowait = delta(prev.iowait, curr.iowait);
+    let irq = delta(prev.irq, curr.irq);
+    let softirq = delta(prev.softirq, curr.softirq);
+    let steal = delta(prev.steal, curr.steal);
+
+    let total = user + nice + system + idle + iowait + irq + softirq + steal + guest + guest_nice;
+    let percent = |jiffies: u64| {
+        if total > 0 {
+            jiffies as f64 * 100.0 / total as f64
+        } else {
+            0.0
+        }
+    };
+
+    CpuUtilization {
+        name: curr.name.clone(),
+        user_percent: percent(user),
+        nice_percent: percent(nice),
+        system_percent: percent(system),
+        idle_percent: percent(idle),
+        iowait_percent: percent(iowait),
+        irq_percent: percent(irq),
+        softirq_percent: percent(softirq),
+        steal_percent: percent(steal),
+        guest_percent: percent(guest),
+        guest_nice_percent: percent(guest_nice),
+        busy_percent: percent(total - idle - iowait),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const STAT: &str = "cpu  1000 100 500 8000 200 10 40 150 0 0\n\
+                        cpu0 500 50 250 4000 100 5 20 75 0 0\n\
+                        cpu1 500 50 250 4000 100 5 20 75 0 0\n\
+                        intr 123456 10 0 0\n\
+                        ctxt 987654\n\
+                        btime 1700000000\n\
+                        processes 4242\n\
+                        procs_running 3\n\
+                        procs_blocked 1\n\
+                        softirq 1 2 3\n";
+
+    #[test]
+    fn test_parse_cpu_stats() -> Result<()> {
+        let stats = parse_cpu_stats(STAT)?;
+        assert_eq!(stats.cpus.len(), 3);
+        assert_eq!(stats.cpus[0].name, "cpu");
+        assert_eq!(stats.cpus[0].steal, 150);
+        assert_eq!(stats.cpus[2].name, "cpu1");
+        assert_eq!(stats.interrupts, 123456);
+        assert_eq!(stats.context_switches, 987654);
+        assert_eq!(stats.forks, 4242);
+        assert_eq!((stats.procs_running, stats.procs_blocked), (3, 1));
+
+        // Pre-2.6.11 kernels have no steal or guest columns.
+        let old = parse_cpu_stats("cpu 1 2 3 4 5 6 7\n")?;
+        assert_eq!(old.cpus[0].softirq, 7);
+        assert_eq!(old.cpus[0].steal, 0);
+        assert!(parse_cpu_stats("ctxt 1\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_cpu_rates() -> Result<()> {
+        let prev = parse_cpu_stats(STAT)?;
+        let mut curr = prev.clone();
+        // 1000 jiffies on cpu0: 300 user of which 100 guest, 100 system,
+        // 400 idle, 200 steal.
+        let cpu0 = &mut curr.cpus[1];
+        cpu0.user += 300;
+        cpu0.guest += 100;
+        cpu0.system += 100;
+        cpu0.idle += 400;
+        cpu0.steal += 200;
+        curr.cpus[2].idle += 1000;
+        curr.cpus.truncate(2);
+        curr.context_switches += 5000;
+        curr.interrupts += 2000;
+        curr.forks += 10;
+
+        let start = Instant::now();
+        let previous = CpuStatsSample {
+            taken_at: start,
+            stats: prev,
+        };
+        let current = CpuStatsSample {
+            taken_at: start + Duration::from_secs(2),
+            stats: curr,
+        };
+
+        let rates = compute_cpu_rates(&previous, &current);
+        assert_eq!(rates.context_switches_per_sec, 2500.0);
+        assert_eq!(rates.interrupts_per_sec, 1000.0);
+        assert_eq!(rates.forks_per_sec, 5.0);
+
+        assert_eq!(rates.cpus.len(), 2);
+        let cpu0 = &rates.cpus[1];
+        assert_eq!(cpu0.name, "cpu0");
+        assert_eq!(cpu0.user_percent, 20.0);
+        assert_eq!(cpu0.guest_percent, 10.0);
+        assert_eq!(cpu0.system_percent, 10.0);
+        assert_eq!(cpu0.idle_percent, 40.0);
+        assert_eq!(cpu0.steal_percent, 20.0);
+        assert_eq!(cpu0.busy_percent, 60.0);
+
+        // The aggregate line did not change: no division by zero.
+        assert_eq!(rates.cpus[0].busy_percent, 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_cpu_stats() -> Result<()> {
+        let first = sample_cpu_stats()?;
+        std::thread::sleep(Duration::from_millis(50));
+        let second = sample_cpu_stats()?;
+        let rates = compute_cpu_rates(&first, &second);
+        assert_eq!(rates.cpus.len(), second.stats.cpus.len());
+        assert!(rates.cpus.iter().all(|cpu| cpu.busy_percent <= 100.0));
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+pub const DISKSTATS_PATH: &str = "/proc/diskstats";
+
+/// `/proc/diskstats` always counts in 512-byte sectors, whatever the device's
+/// logical block size.
+const SECTOR_SIZE: u64 = 512;
+
+/// Cumulative I/O counters of a block device, see the kernel's
+/// `Documentation/admin-guide/iostats.rst`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct DiskStats {
+    pub major: u32,
+    pub minor: u32,
+    pub name: String,
+    pub reads_completed: u64,
+    pub reads_merged: u64,
+    pub sectors_read: u64,
+    pub read_time_ms: u64,
+    pub writes_completed: u64,
+    pub writes_merged: u64,
+    pub sectors_written: u64,
+    pub write_time_ms: u64,
+    pub in_flight: u64,
+    pub io_time_ms: u64,
+    pub weighted_io_time_ms: u64,
+    /// Discard fields, available since Linux 4.18.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub discards_completed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub discards_merged: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sectors_discarded: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub discard_time_ms: Option<u64>,
+    /// Flush fields, available since Linux 5.5.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub flushes_completed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub flush_time_ms: Option<u64>,
+}
+
+/// A set of counters taken at a single point in time.
+#[derive(Debug, Clone)]
+pub struct DiskStatsSample {
+    pub taken_at: Instant,
+    pub devices: HashMap<String, DiskStats>,
+}
+
+/// Per-device rates derived from two [`DiskStatsSample`]s.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct DiskIoRates {
+    pub name: String,
+    pub read_iops: f64,
+    pub write_iops: f64,
+    pub read_bytes_per_sec: f64,
+    pub write_bytes_per_sec: f64,
+    /// Percentage of the interval during which the device had I/O in flight.
+    pub utilization_percent: f64,
+    pub avg_read_latency_ms: f64,
+    pub avg_write_latency_ms: f64,
+    pub avg_queue_size: f64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub discard_iops: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub flush_iops: Option<f64>,
+}
+
+pub fn sample_diskstats() -> Result<DiskStatsSample> {
+    let devices = read_diskstats(DISKSTATS_PATH)?
+        .into_iter()
+        .map(|stats| (stats.name.clone(), stats))
+        .collect();
+
+    Ok(DiskStatsSample {
+        taken_at: Instant::now(),
+        devices,
+    })
+}
+
+pub fn read_diskstats<P: AsRef<Path>>(path: P) -> Result<Vec<DiskStats>> {
+    let path = path.as_ref();
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_diskstats_line)
+        .collect()
+}
+
+fn parse_diskstats_line(line: &str) -> Result<DiskStats> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 14 {
+        bail!("Truncated diskstats line: {}", line);
+    }
+
+    let counters = fields[3..]
+        .iter()
+        .map(|field| field.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Invalid diskstats line: {}", line))?;
+    let optional = |index: usize| counters.get(index).copied();
+
+    Ok(DiskStats {
+        major: fields[0].parse()?,
+        minor: fields[1].parse()?,
+        name: fields[2].to_string(),
+        reads_completed: counters[0],
+        reads_merged: counters[1],
+        sectors_read: counters[2],
+        read_time_ms: counters[3],
+        writes_completed: counters[4],
+        writes_merged: counters[5],
+        sectors_written: counters[6],
+        write_time_ms: counters[7],
+        in_flight: counters[8],
+        io_time_ms: counters[9],
+        weighted_io_time_ms: counters[10],
+        discards_completed: optional(11),
+        discards_merged: optional(12),
+        sectors_discarded: optional(13),
+        discard_time_ms: optional(14),
+        flushes_completed: optional(15),
+        flush_time_ms: optional(16),
+    })
+}
+
+/// Computes per-device rates between two samples.
+///
+/// Devices missing from either sample are skipped. Counters that went
+/// backwards (device re-attached, driver reload) yield zero rather than a
+/// bogus spike.
+pub fn compute_io_rates(previous: &DiskStatsSample, current: &DiskStatsSample) -> Vec<DiskIoRates> {
+    let elapsed = current
+        .taken_at
+        .saturating_duration_since(previous.taken_at);
+    let mut rates: Vec<DiskIoRates> = current
+        .devices
+        .values()
+        .filter_map(|curr| {
+            let prev = previous.devices.get(&curr.name)?;
+            Some(io_rates_between(prev, curr, elapsed))
+        })
+        .collect();
+
+    rates.sort_by(|a, b| a.name.cmp(&b.name));
+    rates
+}
+
+fn io_rates_between(prev: &DiskStats, curr: &DiskStats, elapsed: Duration) -> DiskIoRates {
+    let secs = elapsed.as_secs_f64();
+    let per_sec = |delta: u64| if secs > 0.0 { delta as f64 / secs } else { 0.0 };
+    let ratio = |num: u64, den: u64| {
+        if den > 0 {
+            num as f64 / den as f64
+        } else {
+            0.0
+        }
+    };
+
+    let reads = curr.reads_completed.saturating_sub(prev.reads_completed);
+    let writes = curr.writes_completed.saturating_sub(prev.writes_completed);
+    let read_time = curr.read_time_ms.saturating_sub(prev.read_time_ms);
+    let write_time = curr.write_time_ms.saturating_sub(prev.write_time_ms);
+    let io_time = curr.io_time_ms.saturating_sub(prev.io_time_ms);
+    let weighted = curr
+        .weighted_io_time_ms
+        .saturating_sub(prev.weighted_io_time_ms);
+    let elapsed_ms = elapsed.as_millis() as u64;
+
+    let optional_delta = |curr: Option<u64>, prev: Option<u64>| match (curr, prev) {
+        (Some(curr), Some(prev)) => Some(per_sec(curr.saturating_sub(prev))),
+        _ => None,
+    };
+
+    DiskIoRates {
+        name: curr.name.clone(),
+        read_iops: per_sec(reads),
+        write_iops: per_sec(writes),
+        read_bytes_per_sec: per_sec(
+            curr.sectors_read.saturating_sub(prev.sectors_read) * SECTOR_SIZE,
+        ),
+        write_bytes_per_sec: per_sec(
+            curr.sectors_written.saturating_sub(prev.sectors_written) * SECTOR_SIZE,
+        ),
+        utilization_percent: (ratio(io_time, elapsed_ms) * 100.0).min(100.0),
+        avg_read_latency_ms: ratio(read_time, reads),
+        avg_write_latency_ms: ratio(write_time, writes),
+        avg_queue_size: ratio(weighted, elapsed_ms),
+        discard_iops: optional_delta(curr.discards_completed, prev.discards_completed),
+        flush_iops: optional_delta(curr.flushes_completed, prev.flushes_completed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE_4_14: &str = "   8       0 sda 1000 10 80000 500 2000 20 160000 1500 0 1800 2000";
+    const LINE_5_5: &str =
+        " 259       0 nvme0n1 1000 10 80000 500 2000 20 160000 1500 3 1800 2000 50 0 4096 10 200 40";
+
+    #[test]
+    fn test_parse_diskstats_line() -> Result<()> {
+        let old = parse_diskstats_line(LINE_4_14)?;
+        assert_eq!(old.name, "sda");
+        assert_eq!((old.major, old.minor), (8, 0));
+        assert_eq!(old.sectors_written, 160000);
+        assert_eq!(old.weighted_io_time_ms, 2000);
+        assert_eq!(old.discards_completed, None);
+        assert_eq!(old.flushes_completed, None);
+
+        let new = parse_diskstats_line(LINE_5_5)?;
+        assert_eq!(new.name, "nvme0n1");
+        assert_eq!(new.in_flight, 3);
+        assert_eq!(new.sectors_discarded, Some(4096));
+        assert_eq!(new.flushes_completed, Some(200));
+        assert_eq!(new.flush_time_ms, Some(40));
+
+        assert!(parse_diskstats_line("8 0 sda 1 2 3").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_io_rates() -> Result<()> {
+        let prev = parse_diskstats_line(LINE_5_5)?;
+        let mut curr = prev.clone();
+        curr.reads_completed += 200;
+        curr.sectors_read += 2048;
+        curr.read_time_ms += 100;
+        curr.writes_completed += 100;
+        curr.write_time_ms += 300;
+        curr.io_time_ms += 500;
+        curr.weighted_io_time_ms += 1000;
+        curr.flushes_completed = curr.flushes_completed.map(|v| v + 10);
+
+        let start = Instant::now();
+        let previous = DiskStatsSample {
+            taken_at: start,
+            devices: HashMap::from([(prev.name.clone(), prev)]),
+        };
+        let current = DiskStatsSample {
+            taken_at: start + Duration::from_secs(2),
+            devices: HashMap::from([(curr.name.clone(), curr)]),
+        };
+
+        let rates = compute_io_rates(&previous, &current);
+        assert_eq!(rates.len(), 1);
+        let rate = &rates[0];
+        assert_eq!(rate.read_iops, 100.0);
+        assert_eq!(rate.write_iops, 50.0);
+        assert_eq!(rate.read_bytes_per_sec, 512.0 * 1024.0);
+        assert_eq!(rate.utilization_percent, 25.0);
+        assert_eq!(rate.avg_read_latency_ms, 0.5);
+        assert_eq!(rate.avg_write_latency_ms, 3.0);
+        assert_eq!(rate.avg_queue_size, 0.5);
+        assert_eq!(rate.discard_iops, Some(0.0));
+        assert_eq!(rate.flush_iops, Some(5.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_diskstats() -> Result<()> {
+        match sample_diskstats() {
+            Ok(first) => {
+                let second = sample_diskstats()?;
+                let rates = compute_io_rates(&first, &second);
+                assert!(rates.iter().all(|r| r.utilization_percent <= 100.0));
+                Ok(())
+            }
+            Err(_) => Ok(()),
+        }
+    }
+}
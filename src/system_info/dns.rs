@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+const HOSTNAME_PATH: &str = "/etc/hostname";
+const KERNEL_HOSTNAME_PATH: &str = "/proc/sys/kernel/hostname";
+const HOSTS_PATH: &str = "/etc/hosts";
+const RESOLV_CONF_PATH: &str = "/etc/resolv.conf";
+const NSSWITCH_CONF_PATH: &str = "/etc/nsswitch.conf";
+
+/// Listening address of the systemd-resolved stub resolver.
+const RESOLVED_STUB_ADDRESS: &str = "127.0.0.53";
+
+/// Host naming and name resolution configuration.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct NetworkIdentity {
+    /// Configured name from `/etc/hostname`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub static_hostname: Option<String>,
+    /// Name currently set in the kernel (`uname -n`).
+    pub transient_hostname: String,
+    /// Canonical name for the host according to `/etc/hosts`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fqdn: Option<String>,
+    pub resolv_conf: ResolvConf,
+    /// Whether `/etc/resolv.conf` points at the systemd-resolved stub, in which
+    /// case the real upstream servers are only known to resolved.
+    pub systemd_resolved_stub: bool,
+    /// Lookup sources of the `hosts` database in `/etc/nsswitch.conf`,
+    /// including action items such as `[NOTFOUND=return]`.
+    pub nsswitch_hosts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct ResolvConf {
+    /// Target of `/etc/resolv.conf` when it is a symlink.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub symlink_target: Option<String>,
+    pub nameservers: Vec<String>,
+    pub search: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub domain: Option<String>,
+    pub options: Vec<String>,
+    pub sortlist: Vec<String>,
+}
+
+impl NetworkIdentity {
+    pub fn new() -> Self {
+        let transient_hostname = read_transient_hostname();
+        let static_hostname = fs::read_to_string(HOSTNAME_PATH)
+            .ok()
+            .and_then(|content| parse_hostname(&content));
+        let lookup_name = static_hostname
+            .clone()
+            .unwrap_or_else(|| transient_hostname.clone());
+        let fqdn = fs::read_to_string(HOSTS_PATH)
+            .ok()
+            .and_then(|content| resolve_fqdn(&content, &lookup_name));
+        let resolv_conf = read_resolv_conf(RESOLV_CONF_PATH);
+        let nsswitch_hosts = fs::read_to_string(NSSWITCH_CONF_PATH)
+            .map(|content| parse_nsswitch_hosts(&content))
+            .unwrap_or_default();
+
+        Self {
+            static_hostname,
+            transient_hostname,
+            fqdn,
+            systemd_resolved_stub: is_systemd_resolved_stub(&resolv_conf),
+            resolv_conf,
+            nsswitch_hosts,
+        }
+    }
+}
+
+/// Reads the kernel hostname on every call; the cached `uname` would miss a
+/// rename after the first collection.
+fn read_transient_hostname() -> String {
+    fs::read_to_string(KERNEL_HOSTNAME_PATH)
+        .map(|content| content.trim().to_string())
+        .ok()
+        .or_else(|| {
+            nix::sys::utsname::uname()
+                .ok()
+                .map(|uname| uname.nodename().to_string_lossy().into_owned())
+        })
+        .unwrap_or_default()
+}
+
+fn parse_hostname(content: &str) -> Option<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+}
+
+/// Finds the entry of `/etc/hosts` that lists `hostname` and returns its
+/// canonical (first) name if it is fully qualified.
+fn resolve_fqdn(hosts: &str, hostname: &str) -> Option<String> {
+    let short_name = hostname.split('.').next().unwrap_or(hostname);
+
+    hosts
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _address = fields.next()?;
+            let names: Vec<&str> = fields.collect();
+            names
+                .iter()
+                .any(|name| *name == hostname || *name == short_name)
+                .then(|| names.first().copied())
+                .flatten()
+        })
+        .find(|canonical| canonical.contains('.') && !canonical.starts_with("localhost"))
+        .map(str::to_string)
+}
+
+pub fn read_resolv_conf<P: AsRef<Path>>(path: P) -> ResolvConf {
+    let path = path.as_ref();
+    let mut resolv_conf = fs::read_to_string(path)
+        .map(|content| parse_resolv_conf(&content))
+        .unwrap_or_default();
+    resolv_conf.symlink_target = fs::read_link(path)
+        .ok()
+        .map(|target| target.to_string_lossy().into_owned());
+    resolv_conf
+}
+
+/// Parses `resolv.conf(5)`. As in glibc, the last of `domain` and `search`
+/// wins and later `options` lines extend earlier ones.
+fn parse_resolv_conf(content: &str) -> ResolvConf {
+    let mut resolv_conf = ResolvConf::default();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let Some(keyword) = fields.next() else {
+            continue;
+        };
+        let values: Vec<String> = fields.map(str::to_string).collect();
+
+        match keyword {
+            "nameserver" => resolv_conf.nameservers.extend(values.into_iter().take(1)),
+            "search" => {
+                resolv_conf.search = values;
+                resolv_conf.domain = None;
+            }
+            "domain" => {
+                resolv_conf.domain = values.into_iter().next();
+                resolv_conf.search.clear();
+            }
+            "options" => resolv_conf.options.extend(values),
+            "sortlist" => resolv_conf.sortlist = values,
+            _ => {}
+        }
+    }
+    resolv_conf
+}
+
+fn is_systemd_resolved_stub(resolv_conf: &ResolvConf) -> bool {
+    let stub_link = resolv_conf
+        .symlink_target
+        .as_deref()
+        .is_some_and(|target| target.contains("systemd/resolve/stub-resolv.conf"));
+    stub_link
+        || resolv_conf
+            .nameservers
+            .iter()
+            .any(|server| server == RESOLVED_STUB_ADDRESS)
+}
+
+fn parse_nsswitch_hosts(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .find_map(|line| line.strip_prefix("hosts:"))
+        .map(|sources| sources.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hostname() {
+        assert_eq!(
+            parse_hostname("# comment\n\nweb01\n"),
+            Some("web01".to_string())
+        );
+        assert_eq!(parse_hostname("\n"), None);
+    }
+
+    #[test]
+    fn test_resolve_fqdn() {
+        let hosts = "\
+127.0.0.1 localhost localhost.localdomain
+127.0.1.1 web01.example.com web01 # added by installer
+::1 localhost ip6-localhost
+";
+        assert_eq!(
+            resolve_fqdn(hosts, "web01"),
+            Some("web01.example.com".to_string())
+        );
+        assert_eq!(resolve_fqdn(hosts, "db01"), None);
+        assert_eq!(resolve_fqdn(hosts, "localhost"), None);
+    }
+
+    #[test]
+    fn test_parse_resolv_conf() {
+        let content = "\
+# Generated by NetworkManager
+domain old.example
+search corp.example example.com
+nameserver 10.0.0.2
+nameserver 10.0.0.3 ignored
+; comment
+options edns0 trust-ad
+options timeout:2
+";
+        let resolv_conf = parse_resolv_conf(content);
+        assert_eq!(resolv_conf.nameservers, vec!["10.0.0.2", "10.0.0.3"]);
+        assert_eq!(resolv_conf.search, vec!["corp.example", "example.com"]);
+        assert_eq!(resolv_conf.domain, None);
+        assert_eq!(resolv_conf.options, vec!["edns0", "trust-ad", "timeout:2"]);
+        assert!(!is_systemd_resolved_stub(&resolv_conf));
+
+        let stub = parse_resolv_conf("nameserver 127.0.0.53\noptions edns0\n");
+        assert!(is_systemd_resolved_stub(&stub));
+    }
+
+    #[test]
+    fn test_parse_nsswitch_hosts() {
+        let content = "\
+passwd:         files systemd
+hosts:          files mdns4_minimal [NOTFOUND=return] dns # comment
+networks:       files
+";
+        assert_eq!(
+            parse_nsswitch_hosts(content),
+            vec!["files", "mdns4_minimal", "[NOTFOUND=return]", "dns"]
+        );
+        assert!(parse_nsswitch_hosts("").is_empty());
+    }
+
+    #[test]
+    fn test_network_identity_new() {
+        let identity = NetworkIdentity::new();
+        assert_eq!(identity.transient_hostname, read_transient_hostname());
+        assert!(!identity.transient_hostname.is_empty());
+    }
+}
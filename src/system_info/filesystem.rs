@@ -0,0 +1,400 @@
+use anyhow::{bail, Context, Result};
+use nix::sys::statvfs::statvfs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+pub(crate) const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
+/// Filesystem types that are not backed by any storage device.
+const PSEUDO_FILESYSTEMS: [&str; 27] = [
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "proc",
+    "pstore",
+    "ramfs",
+    "rpc_pipefs",
+    "securityfs",
+    "selinuxfs",
+    "sysfs",
+    "tmpfs",
+    "tracefs",
+    "fuse.gvfsd-fuse",
+    "fuse.lxcfs",
+    "fuse.portal",
+    "rootfs",
+];
+
+/// Filesystem types whose data lives on another machine.
+const NETWORK_FILESYSTEMS: [&str; 11] = [
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "ceph",
+    "glusterfs",
+    "fuse.glusterfs",
+    "fuse.sshfs",
+    "9p",
+    "afs",
+];
+
+/// A single entry of `/proc/self/mountinfo`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct MountEntry {
+    pub mount_id: u32,
+    pub parent_id: u32,
+    pub major: u32,
+    pub minor: u32,
+    pub root: String,
+    pub target: String,
+    pub options: Vec<String>,
+    pub propagation: Vec<String>,
+    pub fstype: String,
+    pub source: String,
+    pub super_options: Vec<String>,
+}
+
+impl MountEntry {
+    pub fn is_pseudo(&self) -> bool {
+        PSEUDO_FILESYSTEMS.contains(&self.fstype.as_str())
+    }
+
+    pub fn is_network(&self) -> bool {
+        NETWORK_FILESYSTEMS.contains(&self.fstype.as_str())
+    }
+
+    /// Returns true for filesystems served by a userspace daemon (`fuse`,
+    /// `fuseblk` and `fuse.<subtype>`).
+    pub fn is_fuse(&self) -> bool {
+        self.fstype == "fuse" || self.fstype == "fuseblk" || self.fstype.starts_with("fuse.")
+    }
+
+    /// Returns true if the mount is backed by a local block device.
+    pub fn is_local_block(&self) -> bool {
+        self.source.starts_with("/dev/") && !self.is_pseudo() && !self.is_network()
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.options.iter().any(|opt| opt == "ro")
+    }
+}
+
+/// Selects which entries of the mount table are returned.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MountFilter {
+    exclude_pseudo: bool,
+    local_block_only: bool,
+}
+
+impl MountFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn exclude_pseudo(mut self) -> Self {
+        self.exclude_pseudo = true;
+        self
+    }
+
+    pub fn local_block_only(mut self) -> Self {
+        self.local_block_only = true;
+        self
+    }
+
+    pub fn matches(&self, entry: &MountEntry) -> bool {
+        if self.exclude_pseudo && entry.is_pseudo() {
+            return false;
+        }
+        if self.local_block_only && !entry.is_local_block() {
+            return false;
+        }
+        true
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct FilesystemUsage {
+    pub total_bytes: u64,
+    pub free_bytes: u64,
+    pub available_bytes: u64,
+    pub used_bytes: u64,
+    pub total_inodes: u64,
+    pub free_inodes: u64,
+    pub used_inodes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Filesystem {
+    #[serde(flatten)]
+    pub mount: MountEntry,
+    /// Capacity statistics, absent when `statvfs` failed or was skipped for
+    /// network and FUSE filesystems.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub usage: Option<FilesystemUsage>,
+}
+
+/// Returns the non-pseudo filesystems together with their usage statistics.
+///
+/// Network and FUSE filesystems are listed but not queried, since `statvfs`
+/// can block for a long time on an unreachable server or a hung daemon. Use
+/// [`get_filesystem_usage`] to query them explicitly.
+pub fn get_filesystems() -> Result<Vec<Filesystem>> {
+    let mounts = read_mount_table(MOUNTINFO_PATH, MountFilter::new().exclude_pseudo())?;
+
+    Ok(mounts
+        .into_iter()
+        .map(|mount| {
+            let usage = if mount.is_network() || mount.is_fuse() {
+                None
+            } else {
+                get_filesystem_usage(&mount.target).ok()
+            };
+            Filesystem { mount, usage }
+        })
+        .collect())
+}
+
+pub fn get_filesystem_usage<P: AsRef<Path>>(path: P) -> Result<FilesystemUsage> {
+    let path = path.as_ref();
+    let stat = statvfs(path).with_context(|| format!("Failed to statvfs {}", path.display()))?;
+
+    let fragment_size = stat.fragment_size() as u64;
+    let total_bytes = stat.blocks() as u64 * fragment_size;
+    let free_bytes = stat.blocks_free() as u64 * fragment_size;
+    let total_inodes = stat.files() as u64;
+    let free_inodes = stat.files_free() as u64;
+
+    Ok(FilesystemUsage {
+        total_bytes,
+        free_bytes,
+        available_bytes: stat.blocks_available() as u64 * fragment_size,
+        used_bytes: total_bytes.saturating_sub(free_bytes),
+        total_inodes,
+        free_inodes,
+        used_inodes: total_inodes.saturating_sub(free_inodes),
+    })
+}
+
+/// Reads a mountinfo table. Lines that cannot be parsed are skipped so that
+/// one unexpected entry does not hide the rest of the table.
+pub fn read_mount_table<P: AsRef<Path>>(path: P, filter: MountFilter) -> Result<Vec<MountEntry>> {
+    let path = path.as_ref();
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    Ok(content
+        .lines()
+        .filter_map(|line| parse_mountinfo_line(line).ok())
+        .filter(|entry| filter.matches(entry))
+        .collect())
+}
+
+/// Parses a line of the format described in `proc_pid_mountinfo(5)`:
+///
+/// ```text
+/// 36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue
+/// ```
+fn parse_mountinfo_line(line: &str) -> Result<MountEntry> {
+    let (left, right) = line
+        .split_once(" - ")
+        .ok_or_else(|| anyhow::anyhow!("Missing separator in mountinfo line: {}", line))?;
+
+    let mut fields = left.split_whitespace();
+    let (Some(mount_id), Some(parent_id), Some(device), Some(root), Some(target), Some(options)) = (
+        fields.next(),
+        fields.next(),
+        fields.next(),
+        fields.next(),
+        fields.next(),
+        fields.next(),
+    ) else {
+        bail!("Truncated mountinfo line: {}", line);
+    };
+    let propagation = fields.map(str::to_string).collect();
+
+    let (major, minor) = device
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Invalid device number: {}", device))?;
+
+    let mut fields = right.split_whitespace();
+    let fstype = fields.next().unwrap_or_default();
+    let source = fields.next().unwrap_or_default();
+    let super_options = fields.next().unwrap_or_default();
+
+    Ok(MountEntry {
+        mount_id: mount_id.parse()?,
+        parent_id: parent_id.parse()?,
+        major: major.parse()?,
+        minor: minor.parse()?,
+        root: unescape_octal(root),
+        target: unescape_octal(target),
+        options: split_options(options),
+        propagation,
+        fstype: fstype.to_string(),
+        source: unescape_octal(source),
+        super_options: split_options(super_options),
+    })
+}
+
+fn split_options(options: &str) -> Vec<String> {
+    options
+        .split(',')
+        .filter(|opt| !opt.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The kernel escapes space, tab, newline and backslash as `\ooo`.
+pub(crate) fn unescape_octal(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() {
+            let digits = &bytes[i + 1..i + 4];
+            if digits.iter().all(|b| (b'0'..=b'7').contains(b)) {
+                let value = digits
+                    .iter()
+                    .fold(0u32, |acc, b| acc * 8 + u32::from(b - b'0'));
+                out.push(value as u8);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::test_util::TempDir;
+
+    const MOUNTINFO: &str = "\
+22 28 0:21 / /sys rw,nosuid,nodev,noexec,relatime shared:7 - sysfs sysfs rw
+28 1 8:2 / / rw,relatime shared:1 - ext4 /dev/sda2 rw,errors=remount-ro
+30 28 8:1 / /boot/efi rw,relatime shared:2 - vfat /dev/sda1 rw,fmask=0077
+41 28 0:38 / /mnt/my\\040share rw,relatime - nfs4 server:/export rw,vers=4.2
+45 28 0:40 / /tmp rw,nosuid shared:20 master:3 - tmpfs tmpfs rw,size=8G
+52 28 0:45 / /mnt/remote rw,nosuid,nodev - fuse.sshfs host:/srv rw,user_id=1000
+53 28 8:17 / /mnt/win rw,relatime - fuseblk /dev/sdb1 rw,user_id=0
+";
+
+    fn parse_all() -> Vec<MountEntry> {
+        MOUNTINFO
+            .lines()
+            .map(|line| parse_mountinfo_line(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_mountinfo_line() {
+        let entries = parse_all();
+        let root = &entries[1];
+        assert_eq!(root.mount_id, 28);
+        assert_eq!(root.parent_id, 1);
+        assert_eq!((root.major, root.minor), (8, 2));
+        assert_eq!(root.target, "/");
+        assert_eq!(root.fstype, "ext4");
+        assert_eq!(root.source, "/dev/sda2");
+        assert_eq!(root.propagation, vec!["shared:1"]);
+        assert_eq!(root.super_options, vec!["rw", "errors=remount-ro"]);
+        assert!(!root.is_read_only());
+
+        assert_eq!(entries[3].target, "/mnt/my share");
+        assert_eq!(entries[4].propagation, vec!["shared:20", "master:3"]);
+    }
+
+    #[test]
+    fn test_mount_filter() {
+        let entries = parse_all();
+
+        let filter = MountFilter::new().exclude_pseudo();
+        let targets: Vec<_> = entries
+            .iter()
+            .filter(|e| filter.matches(e))
+            .map(|e| e.target.as_str())
+            .collect();
+        assert_eq!(
+            targets,
+            vec!["/", "/boot/efi", "/mnt/my share", "/mnt/remote", "/mnt/win"]
+        );
+
+        let filter = MountFilter::new().local_block_only();
+        let targets: Vec<_> = entries
+            .iter()
+            .filter(|e| filter.matches(e))
+            .map(|e| e.target.as_str())
+            .collect();
+        assert_eq!(targets, vec!["/", "/boot/efi", "/mnt/win"]);
+    }
+
+    #[test]
+    fn test_fuse_detection() {
+        let entries = parse_all();
+        let fuse: Vec<_> = entries
+            .iter()
+            .filter(|e| e.is_fuse())
+            .map(|e| e.target.as_str())
+            .collect();
+        assert_eq!(fuse, vec!["/mnt/remote", "/mnt/win"]);
+        assert!(entries[5].is_network());
+        assert!(!entries[1].is_fuse());
+    }
+
+    #[test]
+    fn test_invalid_mountinfo_line() {
+        assert!(parse_mountinfo_line("28 1 8:2 / /").is_err());
+        assert!(parse_mountinfo_line("not a mount line").is_err());
+    }
+
+    #[test]
+    fn test_read_mount_table() -> Result<()> {
+        let mounts = read_mount_table(MOUNTINFO_PATH, MountFilter::new())?;
+        assert!(mounts.iter().any(|m| m.target == "/"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_mount_table_skips_invalid_lines() -> Result<()> {
+        let dir = TempDir::new("filesystem_mountinfo");
+        let path = dir.join("mountinfo");
+        fs::write(
+            &path,
+            format!("not a mount line\n{}\n28 1 8:2 / /\n", MOUNTINFO),
+        )?;
+
+        let mounts = read_mount_table(&path, MountFilter::new())?;
+        assert_eq!(mounts.len(), parse_all().len());
+        assert!(mounts.iter().any(|m| m.target == "/mnt/my share"));
+
+        assert!(read_mount_table(dir.join("missing"), MountFilter::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_filesystem_usage() -> Result<()> {
+        let usage = get_filesystem_usage("/")?;
+        assert!(usage.total_bytes >= usage.free_bytes);
+        assert!(usage.free_bytes >= usage.available_bytes);
+        Ok(())
+    }
+}
@@ -1,39 +1,66 @@
+use super::filesystem::{
+    get_filesystems, read_mount_table, Filesystem, MountFilter, MOUNTINFO_PATH,
+};
+use super::link_topology::LinkTopology;
+use super::network::{get_network_interfaces, NetworkInterface};
+use super::routing::RoutingInfo;
+use super::storage::StorageTopology;
 use anyhow::{bail, Result};
 use pnet::datalink;
 use serde::{Deserialize, Serialize};
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader, Read};
+use std::io::Read;
 use std::path::Path;
 use std::process::Command;
 use udev;
 
 const BIOS_INFO_PATH: &str = "/sys/firmware/dmi/entries/0-0/raw";
-const SYSTEM_INFO_PATH: &str = "/sys/firmware/dmi/entries/1-0/raw";
+pub(crate) const SYSTEM_INFO_PATH: &str = "/sys/firmware/dmi/entries/1-0/raw";
 const ENCLOSURE_INFO_PATH: &str = "/sys/firmware/dmi/entries/3-0/raw";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HardwareInfo {
     pub cpu_is_virtual: bool,
     pub disk_serial_number: String,
+    /// Comma separated MAC addresses of all interfaces that have one,
+    /// including loopback, kept unchanged for compatibility. Prefer
+    /// `network_interfaces`.
     pub mac_addresses: String,
+    #[serde(default)]
+    pub network_interfaces: Vec<NetworkInterface>,
+    #[serde(default)]
+    pub link_topology: LinkTopology,
+    #[serde(default)]
+    pub routing: RoutingInfo,
     pub bios_info: BiosInfo,
     pub system_info: SystemInfo,
     pub enclosure_info: EnclosureInfo,
+    #[serde(default)]
+    pub filesystems: Vec<Filesystem>,
+    #[serde(default)]
+    pub storage_topology: StorageTopology,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub extra: Option<serde_json::Value>,
 }
 
 impl HardwareInfo {
     pub fn new() -> Result<Self> {
+        let network_interfaces = get_network_interfaces()?;
+
         Ok(HardwareInfo {
             cpu_is_virtual: determine_virtual_machine_status(),
             disk_serial_number: get_root_device()
                 .and_then(|disk_part_name| get_serial_number(&disk_part_name))
                 .unwrap_or_default(),
             mac_addresses: get_mac_addresses()?,
+            network_interfaces,
+            link_topology: LinkTopology::new().unwrap_or_default(),
+            routing: RoutingInfo::new().unwrap_or_default(),
             bios_info: read_bios_info(BIOS_INFO_PATH).unwrap_or_default(),
             system_info: read_system_info(SYSTEM_INFO_PATH).unwrap_or_default(),
             enclosure_info: read_enclosure_info(ENCLOSURE_INFO_PATH).unwrap_or_default(),
+            filesystems: get_filesystems().unwrap_or_default(),
+            storage_topology: StorageTopology::new().unwrap_or_default(),
             extra: None,
         })
     }
@@ -72,6 +99,7 @@ pub struct EnclosureInfo {
 }
 
 #[cfg(target_arch = "x86_64")]
+#[allow(unused_unsafe)]
 fn is_hypervisor_present() -> bool {
     use std::arch::x86_64::__cpuid;
 
@@ -94,6 +122,7 @@ fn is_hypervisor_present() -> bool {
 }
 
 #[cfg(target_arch = "x86_64")]
+#[allow(unused_unsafe)]
 fn get_hypervisor_name() -> Option<&'static str> {
     use std::arch::x86_64::__cpuid;
 
@@ -224,25 +253,21 @@ fn determine_virtual_machine_status() -> bool {
         .unwrap_or(false)
 }
 
-fn get_root_device() -> Result<String> {
-    let file = File::open("/proc/mounts")
-        .map_err(|e| anyhow::anyhow!("Failed to open /proc/mounts: {}", e))?;
-    let reader = BufReader::with_capacity(4096, file);
-
-    for line in reader.lines() {
-        let line = line?;
-        let mut parts = line.split_whitespace().take(2);
-        if let (Some(device), Some("/")) = (parts.next(), parts.next()) {
-            if device.starts_with("/dev/") {
-                return Ok(device[5..].to_string());
-            }
-            return Ok(device.to_string());
-        }
+pub(crate) fn get_root_device() -> Result<String> {
+    // With stacked mounts the last entry for "/" is the visible one.
+    let root = read_mount_table(MOUNTINFO_PATH, MountFilter::new())?
+        .into_iter()
+        .rev()
+        .find(|mount| mount.target == "/")
+        .ok_or_else(|| anyhow::anyhow!("Root device not found in {}", MOUNTINFO_PATH))?;
+
+    match root.source.strip_prefix("/dev/") {
+        Some(device) => Ok(device.to_string()),
+        None => Ok(root.source),
     }
-    bail!("Root device not found in /proc/mounts")
 }
 
-fn get_serial_number(disk_part_name: &str) -> Result<String> {
+pub(crate) fn get_serial_number(disk_part_name: &str) -> Result<String> {
     let udev = udev::Udev::new()?;
     let mut enumerator = udev::Enumerator::with_udev(udev)?;
 
@@ -310,7 +335,7 @@ fn read_bios_info<P: AsRef<Path>>(path: P) -> Result<BiosInfo> {
     })
 }
 
-fn read_system_info<P: AsRef<Path>>(path: P) -> Result<SystemInfo> {
+pub(crate) fn read_system_info<P: AsRef<Path>>(path: P) -> Result<SystemInfo> {
     let mut buffer = Vec::new();
     File::open(&path)?.read_to_end(&mut buffer)?;
 
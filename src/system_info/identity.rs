@@ -0,0 +1,385 @@
+use super::hardware::{get_root_device, get_serial_number, read_system_info, SYSTEM_INFO_PATH};
+use super::network::get_primary_mac;
+use anyhow::{bail, Context, Result};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+const MACHINE_ID_PATHS: [&str; 2] = ["/etc/machine-id", "/var/lib/dbus/machine-id"];
+const BOARD_SERIAL_PATH: &str = "/sys/class/dmi/id/board_serial";
+
+/// Values firmware vendors leave in DMI fields they did not fill in.
+const PLACEHOLDER_VALUES: [&str; 10] = [
+    "to be filled by o.e.m.",
+    "default string",
+    "system serial number",
+    "not specified",
+    "not applicable",
+    "none",
+    "0",
+    "0123456789",
+    "00000000-0000-0000-0000-000000000000",
+    "03000200-0400-0500-0006-000700080009",
+];
+
+/// Returns the systemd machine ID as 32 lowercase hex digits.
+///
+/// The ID identifies the installation and should not be exposed to the
+/// network; use [`app_specific_machine_id`] for anything that leaves the host.
+pub fn get_machine_id() -> Result<String> {
+    let (path, content) = MACHINE_ID_PATHS
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok().map(|content| (path, content)))
+        .context("No machine ID found in /etc/machine-id or /var/lib/dbus/machine-id")?;
+
+    let id = content.trim().to_lowercase();
+    parse_id128(&id).with_context(|| format!("Invalid machine ID in {}", path))?;
+    Ok(id)
+}
+
+/// Derives a per-application machine ID the same way as systemd's
+/// `sd_id128_get_machine_app_specific()`, so that the value matches
+/// `systemd-id128 machine-id --app-specific=<app_id>`.
+///
+/// `app_id` is a 128-bit ID as 32 hex digits or a UUID.
+pub fn app_specific_machine_id(app_id: &str) -> Result<String> {
+    let machine_id = parse_id128(&get_machine_id()?)?;
+    let app_id = parse_id128(app_id).context("Invalid application ID")?;
+    Ok(format_id128(&app_specific_id(&machine_id, &app_id)))
+}
+
+fn app_specific_id(machine_id: &[u8; 16], app_id: &[u8; 16]) -> [u8; 16] {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(machine_id).expect("HMAC accepts keys of any length");
+    mac.update(app_id);
+    let digest = mac.finalize().into_bytes();
+
+    let mut id = [0u8; 16];
+    id.copy_from_slice(&digest[..16]);
+    // Mark the result as a random (version 4, variant 1) UUID.
+    id[6] = (id[6] & 0x0f) | 0x40;
+    id[8] = (id[8] & 0x3f) | 0x80;
+    id
+}
+
+fn parse_id128(id: &str) -> Result<[u8; 16]> {
+    let hex: String = id.chars().filter(|c| *c != '-').collect();
+    if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("Expected 32 hex digits, got {:?}", id);
+    }
+    let mut bytes = [0u8; 16];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)?;
+    }
+    Ok(bytes)
+}
+
+fn format_id128(id: &[u8; 16]) -> String {
+    id.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// A hardware property that can take part in a [`Fingerprint`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum FingerprintComponent {
+    /// SMBIOS system UUID, as in `SystemInfo::uuid`.
+    SystemUuid,
+    BoardSerial,
+    RootDiskSerial,
+    /// MAC address chosen by [`get_primary_mac`](super::network::get_primary_mac).
+    PrimaryMac,
+}
+
+impl FingerprintComponent {
+    fn name(self) -> &'static str {
+        match self {
+            FingerprintComponent::SystemUuid => "system_uuid",
+            FingerprintComponent::BoardSerial => "board_serial",
+            FingerprintComponent::RootDiskSerial => "root_disk_serial",
+            FingerprintComponent::PrimaryMac => "primary_mac",
+        }
+    }
+
+    fn read(self) -> Option<String> {
+        let value = match self {
+            FingerprintComponent::SystemUuid => read_system_info(SYSTEM_INFO_PATH).ok()?.uuid,
+            FingerprintComponent::BoardSerial => fs::read_to_string(BOARD_SERIAL_PATH).ok()?,
+            FingerprintComponent::RootDiskSerial => {
+                get_serial_number(&get_root_device().ok()?).ok()?
+            }
+            FingerprintComponent::PrimaryMac => get_primary_mac().ok()?.mac,
+        };
+        normalize_component(&value)
+    }
+}
+
+/// Trims and lowercases a component value, discarding firmware placeholders.
+fn normalize_component(value: &str) -> Option<String> {
+    let value = value.trim().to_lowercase();
+    (!value.is_empty() && !PLACEHOLDER_VALUES.contains(&value.as_str())).then_some(value)
+}
+
+/// A composite hardware fingerprint for binding data such as licenses to a
+/// machine.
+///
+/// Component values are stored only as salted SHA-256 hashes. A stored
+/// fingerprint still matches the current machine when up to `tolerance`
+/// components changed, so replacing a disk or NIC does not invalidate it.
+/// Components that could not be read, e.g. serials that need root, are
+/// recorded as unavailable and count as changed when matching.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Fingerprint {
+    /// Hex-encoded hash per available component, keyed by component name.
+    pub components: BTreeMap<String, String>,
+    /// Requested components that could not be read.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty", default)]
+    pub unavailable: BTreeSet<String>,
+    pub tolerance: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct FingerprintBuilder {
+    components: Vec<FingerprintComponent>,
+    tolerance: usize,
+    salt: String,
+}
+
+impl Default for FingerprintBuilder {
+    fn default() -> Self {
+        Self {
+            components: vec![
+                FingerprintComponent::SystemUuid,
+                FingerprintComponent::BoardSerial,
+                FingerprintComponent::RootDiskSerial,
+                FingerprintComponent::PrimaryMac,
+            ],
+            tolerance: 1,
+            salt: String::new(),
+        }
+    }
+}
+
+impl FingerprintBuilder {
+    /// Replaces the default set of components (all of them).
+    pub fn components(mut self, components: &[FingerprintComponent]) -> Self {
+        self.components = components.to_vec();
+        self
+    }
+
+    /// Number of components that may differ while still matching. Defaults to 1.
+    pub fn tolerance(mut self, tolerance: usize) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Application-specific salt, so fingerprints cannot be correlated
+    /// between applications.
+    pub fn salt(mut self, salt: &str) -> Self {
+        self.salt = salt.to_string();
+        self
+    }
+
+    /// Reads the configured components from the running machine.
+    pub fn build(self) -> Result<Fingerprint> {
+        let values = self
+            .components
+            .iter()
+            .filter_map(|component| component.read().map(|value| (*component, value)))
+            .collect();
+        self.build_from(values)
+    }
+
+    fn build_from(self, values: BTreeMap<FingerprintComponent, String>) -> Result<Fingerprint> {
+        let components: BTreeMap<String, String> = values
+            .into_iter()
+            .filter(|(component, _)| self.components.contains(component))
+            .map(|(component, value)| {
+                let hash = Sha256::new()
+                    .chain_update(self.salt.as_bytes())
+                    .chain_update([0])
+                    .chain_update(component.name())
+                    .chain_update([0])
+                    .chain_update(value.as_bytes())
+                    .finalize();
+                let hex = hash.iter().map(|byte| format!("{:02x}", byte)).collect();
+                (component.name().to_string(), hex)
+            })
+            .collect();
+
+        if components.len() <= self.tolerance {
+            bail!(
+                "Only {} fingerprint component(s) available, need more than the tolerance of {}",
+                components.len(),
+                self.tolerance
+            );
+        }
+        let unavailable = self
+            .components
+            .iter()
+            .map(|component| component.name().to_string())
+            .filter(|name| !components.contains_key(name))
+            .collect();
+        Ok(Fingerprint {
+            components,
+            unavailable,
+            tolerance: self.tolerance,
+        })
+    }
+}
+
+impl Fingerprint {
+    pub fn builder() -> FingerprintBuilder {
+        FingerprintBuilder::default()
+    }
+
+    /// Number of stored components that are different or missing in
+    /// `current`, including those `current` could not read.
+    pub fn changed_components(&self, current: &Fingerprint) -> usize {
+        self.components
+            .iter()
+            .filter(|(name, hash)| current.components.get(*name) != Some(hash))
+            .count()
+    }
+
+    /// Returns true if `current` was taken on the same machine, allowing up
+    /// to `self.tolerance` changed components.
+    pub fn matches(&self, current: &Fingerprint) -> bool {
+        self.changed_components(current) <= self.tolerance
+    }
+
+    /// A single digest over all components, for exact comparisons.
+    pub fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        for (name, hash) in &self.components {
+            hasher.update(name.as_bytes());
+            hasher.update([0]);
+            hasher.update(hash.as_bytes());
+            hasher.update([0]);
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_specific_id() -> Result<()> {
+        // Matches `systemd-id128 machine-id --app-specific=...` on a machine
+        // with this machine ID.
+        let machine_id = parse_id128("f03daaeb1c334b43a732172944bf772e")?;
+        let app_id = parse_id128("b03daaeb-1c33-4b43-a732-172944bf772e")?;
+        assert_eq!(
+            format_id128(&app_specific_id(&machine_id, &app_id)),
+            "2fd3937394f24f9791f49f16a2fdc5c7"
+        );
+        assert!(parse_id128("not-an-id").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_machine_id() -> Result<()> {
+        let machine_id = get_machine_id()?;
+        assert_eq!(machine_id.len(), 32);
+        let app_id = app_specific_machine_id("b03daaeb1c334b43a732172944bf772e")?;
+        assert_ne!(app_id, machine_id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint_tolerance() -> Result<()> {
+        use FingerprintComponent::*;
+
+        let values = |mac: &str, disk: &str| {
+            BTreeMap::from([
+                (
+                    SystemUuid,
+                    "4c4c4544-0031-3510-8052-b4c04f4e4d32".to_string(),
+                ),
+                (BoardSerial, "/7xy8z12/cn1296".to_string()),
+                (RootDiskSerial, disk.to_string()),
+                (PrimaryMac, mac.to_string()),
+            ])
+        };
+        let stored = Fingerprint::builder()
+            .salt("license-v1")
+            .build_from(values("52:54:00:aa:bb:01", "wd-123"))?;
+        assert_eq!(stored.components.len(), 4);
+
+        let new_disk = Fingerprint::builder()
+            .salt("license-v1")
+            .build_from(values("52:54:00:aa:bb:01", "wd-456"))?;
+        assert_eq!(stored.changed_components(&new_disk), 1);
+        assert!(stored.matches(&new_disk));
+        assert_ne!(stored.digest(), new_disk.digest());
+
+        let new_disk_and_nic = Fingerprint::builder()
+            .salt("license-v1")
+            .build_from(values("52:54:00:aa:bb:02", "wd-456"))?;
+        assert!(!stored.matches(&new_disk_and_nic));
+
+        let other_salt = Fingerprint::builder()
+            .salt("other-app")
+            .build_from(values("52:54:00:aa:bb:01", "wd-123"))?;
+        assert_eq!(stored.changed_components(&other_salt), 4);
+
+        // Serials that need root are recorded as unavailable when the
+        // fingerprint is checked as a normal user, and count as changed.
+        let unprivileged_values = |mac: &str| {
+            let mut values = values(mac, "wd-123");
+            values.remove(&BoardSerial);
+            values.remove(&RootDiskSerial);
+            values
+        };
+        let unprivileged = Fingerprint::builder()
+            .salt("license-v1")
+            .tolerance(0)
+            .build_from(unprivileged_values("52:54:00:aa:bb:01"))?;
+        assert_eq!(
+            unprivileged.unavailable,
+            BTreeSet::from(["board_serial".to_string(), "root_disk_serial".to_string()])
+        );
+        assert_eq!(stored.changed_components(&unprivileged), 2);
+        assert!(!stored.matches(&unprivileged));
+
+        // A matching MAC alone must not be enough.
+        let mut mac_only = unprivileged.clone();
+        mac_only.components.remove("system_uuid");
+        mac_only.unavailable.insert("system_uuid".to_string());
+        assert_eq!(stored.changed_components(&mac_only), 3);
+        assert!(!stored.matches(&mac_only));
+
+        // Fingerprints meant to be checked without root are stored over
+        // the components a normal user can read.
+        let stored_unprivileged = Fingerprint::builder()
+            .components(&[SystemUuid, PrimaryMac])
+            .salt("license-v1")
+            .build_from(values("52:54:00:aa:bb:01", "wd-123"))?;
+        assert!(stored_unprivileged.matches(&unprivileged));
+        let unprivileged_new_nic = Fingerprint::builder()
+            .salt("license-v1")
+            .tolerance(0)
+            .build_from(unprivileged_values("52:54:00:aa:bb:02"))?;
+        assert!(stored_unprivileged.matches(&unprivileged_new_nic));
+
+        let too_few = Fingerprint::builder()
+            .components(&[PrimaryMac])
+            .build_from(values("52:54:00:aa:bb:01", "wd-123"));
+        assert!(too_few.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_component() {
+        assert_eq!(normalize_component(" ABC123\n"), Some("abc123".to_string()));
+        assert_eq!(normalize_component("To Be Filled By O.E.M."), None);
+        assert_eq!(normalize_component(""), None);
+    }
+}
@@ -0,0 +1,372 @@
+use super::uname::Uname;
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+const CMDLINE_PATH: &str = "/proc/cmdline";
+const TAINTED_PATH: &str = "/proc/sys/kernel/tainted";
+const MODULES_PATH: &str = "/proc/modules";
+const SYS_MODULE_PATH: &str = "/sys/module";
+const LOCKDOWN_PATH: &str = "/sys/kernel/security/lockdown";
+const PROC_CONFIG_PATH: &str = "/proc/config.gz";
+const BOOT_CONFIG_DIR: &str = "/boot";
+
+/// Taint bits from `Documentation/admin-guide/tainted-kernels.rst`, indexed
+/// by bit number, as (letter, description).
+const TAINT_FLAGS: [(char, &str); 19] = [
+    ('P', "proprietary module was loaded"),
+    ('F', "module was force loaded"),
+    ('S', "kernel running on an out of specification system"),
+    ('R', "module was force unloaded"),
+    ('M', "processor reported a machine check exception"),
+    ('B', "bad page referenced or unexpected page flags"),
+    ('U', "taint requested by userspace"),
+    ('D', "kernel died recently (OOPS or BUG)"),
+    ('A', "ACPI table overridden by user"),
+    ('W', "kernel issued warning"),
+    ('C', "staging driver was loaded"),
+    ('I', "workaround for bug in platform firmware applied"),
+    ('O', "externally-built (out-of-tree) module was loaded"),
+    ('E', "unsigned module was loaded"),
+    ('L', "soft lockup occurred"),
+    ('K', "kernel has been live patched"),
+    ('X', "auxiliary taint, defined for and used by distros"),
+    ('T', "kernel was built with the struct randomization plugin"),
+    ('N', "an in-kernel test has been run"),
+];
+
+/// Runtime state of the running kernel.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct KernelInfo {
+    /// Raw contents of `/proc/cmdline`.
+    pub cmdline: String,
+    /// Kernel parameters in command line order. Parameters may repeat,
+    /// e.g. `console=`.
+    pub parameters: Vec<KernelParameter>,
+    /// Arguments after `--`, which the kernel passes on to init.
+    pub init_arguments: Vec<String>,
+    /// Raw value of `/proc/sys/kernel/tainted`.
+    pub tainted: u64,
+    pub taint_flags: Vec<TaintFlag>,
+    pub modules: Vec<KernelModule>,
+    /// Selected mode from `/sys/kernel/security/lockdown`, e.g. `none`,
+    /// `integrity` or `confidentiality`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub lockdown: Option<String>,
+    /// Kernel build options keyed by `CONFIG_*` name. Options that are
+    /// explicitly not set map to `n`.
+    pub config: BTreeMap<String, String>,
+    /// Where `config` was read from, if anywhere.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub config_source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct KernelParameter {
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct TaintFlag {
+    pub bit: u32,
+    pub letter: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct KernelModule {
+    pub name: String,
+    pub size: u64,
+    /// Reference count, absent if the kernel cannot unload modules.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub refcount: Option<u32>,
+    pub used_by: Vec<String>,
+    /// `Live`, `Loading` or `Unloading`.
+    pub state: String,
+    /// Taint letters of the module, e.g. `OE` for an unsigned out-of-tree module.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub taints: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub version: Option<String>,
+}
+
+impl KernelInfo {
+    pub fn new() -> Result<Self> {
+        let cmdline = fs::read_to_string(CMDLINE_PATH)
+            .with_context(|| format!("Failed to read {}", CMDLINE_PATH))?
+            .trim()
+            .to_string();
+        let (parameters, init_arguments) = parse_cmdline(&cmdline);
+        let tainted = fs::read_to_string(TAINTED_PATH)
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(0);
+        let mut modules = fs::read_to_string(MODULES_PATH)
+            .map(|content| parse_modules(&content))
+            .unwrap_or_default();
+        for module in &mut modules {
+            module.version = fs::read_to_string(
+                Path::new(SYS_MODULE_PATH)
+                    .join(&module.name)
+                    .join("version"),
+            )
+            .ok()
+            .map(|version| version.trim().to_string());
+        }
+        let (config_source, config) = read_kernel_config()
+            .map(|(source, config)| (Some(source), config))
+            .unwrap_or_default();
+
+        Ok(Self {
+            cmdline,
+            parameters,
+            init_arguments,
+            tainted,
+            taint_flags: decode_taint(tainted),
+            modules,
+            lockdown: fs::read_to_string(LOCKDOWN_PATH)
+                .ok()
+                .and_then(|content| parse_lockdown(&content)),
+            config,
+            config_source,
+        })
+    }
+
+    /// Returns the value of the last occurrence of a kernel parameter, which
+    /// is the one the kernel acts on. Flags without `=` yield an empty string.
+    pub fn parameter(&self, key: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .rev()
+            .find(|parameter| parameter.key == key)
+            .map(|parameter| parameter.value.as_deref().unwrap_or(""))
+    }
+}
+
+/// Splits the kernel command line into parameters and init arguments.
+/// Double quotes group a value containing spaces, as in the kernel's
+/// `next_arg()`.
+fn parse_cmdline(cmdline: &str) -> (Vec<KernelParameter>, Vec<String>) {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_word = false;
+
+    for c in cmdline.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_word = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_word {
+                    words.push(std::mem::take(&mut current));
+                    has_word = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_word = true;
+            }
+        }
+    }
+    if has_word {
+        words.push(current);
+    }
+
+    let mut parameters = Vec::new();
+    let mut words = words.into_iter();
+    for word in words.by_ref() {
+        if word == "--" {
+            break;
+        }
+        let parameter = match word.split_once('=') {
+            Some((key, value)) => KernelParameter {
+                key: key.to_string(),
+                value: Some(value.to_string()),
+            },
+            None => KernelParameter {
+                key: word,
+                value: None,
+            },
+        };
+        parameters.push(parameter);
+    }
+    (parameters, words.collect())
+}
+
+fn decode_taint(tainted: u64) -> Vec<TaintFlag> {
+    (0..u64::BITS)
+        .filter(|bit| tainted & (1 << bit) != 0)
+        .map(|bit| {
+            let (letter, description) = TAINT_FLAGS
+                .get(bit as usize)
+                .copied()
+                .unwrap_or(('?', "unknown taint flag"));
+            TaintFlag {
+                bit,
+                letter: letter.to_string(),
+                description: description.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Parses `/proc/modules`:
+///
+/// ```text
+/// nf_tables 372736 2 nft_chain_nat,nft_compat, Live 0x0000000000000000 (OE)
+/// ```
+fn parse_modules(content: &str) -> Vec<KernelModule> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 5 {
+                return None;
+            }
+            Some(KernelModule {
+                name: fields[0].to_string(),
+                size: fields[1].parse().ok()?,
+                refcount: fields[2].parse().ok(),
+                used_by: fields[3]
+                    .split(',')
+                    .filter(|name| !name.is_empty() && *name != "-")
+                    .map(str::to_string)
+                    .collect(),
+                state: fields[4].to_string(),
+                taints: fields
+                    .get(6)
+                    .map(|taints| taints.trim_matches(['(', ')']).to_string()),
+                version: None,
+            })
+        })
+        .collect()
+}
+
+/// Extracts the bracketed entry from `none [integrity] confidentiality`.
+fn parse_lockdown(content: &str) -> Option<String> {
+    content
+        .split_whitespace()
+        .find_map(|mode| mode.strip_prefix('[')?.strip_suffix(']'))
+        .map(str::to_string)
+}
+
+/// Reads the build configuration from `/proc/config.gz`, falling back to
+/// `/boot/config-<release>`.
+fn read_kernel_config() -> Option<(String, BTreeMap<String, String>)> {
+    let mut content = String::new();
+    let from_proc = fs::File::open(PROC_CONFIG_PATH)
+        .ok()
+        .and_then(|file| GzDecoder::new(file).read_to_string(&mut content).ok());
+    if from_proc.is_some() {
+        return Some((PROC_CONFIG_PATH.to_string(), parse_kernel_config(&content)));
+    }
+
+    let release = Uname::new().ok()?.release;
+    let path = Path::new(BOOT_CONFIG_DIR).join(format!("config-{}", release));
+    let content = fs::read_to_string(&path).ok()?;
+    Some((path.display().to_string(), parse_kernel_config(&content)))
+}
+
+fn parse_kernel_config(content: &str) -> BTreeMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            if let Some(name) = line
+                .strip_prefix("# ")
+                .and_then(|rest| rest.strip_suffix(" is not set"))
+            {
+                return Some((name.to_string(), "n".to_string()));
+            }
+            let (name, value) = line.split_once('=')?;
+            name.starts_with("CONFIG_")
+                .then(|| (name.to_string(), value.trim_matches('"').to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cmdline() {
+        let (parameters, init_arguments) = parse_cmdline(
+            "BOOT_IMAGE=/vmlinuz root=UUID=abcd ro quiet console=tty0 console=ttyS0,115200 dyndbg=\"file x.c +p\" -- single",
+        );
+        assert_eq!(parameters.len(), 7);
+        assert_eq!(parameters[1].key, "root");
+        assert_eq!(parameters[1].value.as_deref(), Some("UUID=abcd"));
+        assert_eq!(parameters[2].value, None);
+        assert_eq!(parameters[6].value.as_deref(), Some("file x.c +p"));
+        assert_eq!(init_arguments, vec!["single"]);
+
+        let kernel = KernelInfo {
+            parameters,
+            ..Default::default()
+        };
+        assert_eq!(kernel.parameter("console"), Some("ttyS0,115200"));
+        assert_eq!(kernel.parameter("quiet"), Some(""));
+        assert_eq!(kernel.parameter("nomodeset"), None);
+    }
+
+    #[test]
+    fn test_decode_taint() {
+        assert!(decode_taint(0).is_empty());
+        let flags = decode_taint((1 << 12) | (1 << 13) | (1 << 40));
+        let letters: Vec<&str> = flags.iter().map(|flag| flag.letter.as_str()).collect();
+        assert_eq!(letters, vec!["O", "E", "?"]);
+        assert_eq!(flags[0].bit, 12);
+    }
+
+    #[test]
+    fn test_parse_modules() {
+        let content = "\
+nft_chain_nat 12288 3 - Live 0x0000000000000000
+nf_tables 372736 2 nft_chain_nat,nft_compat, Live 0xffffffffc0a00000
+vboxdrv 696320 0 - Loading 0x0000000000000000 (OE)
+";
+        let modules = parse_modules(content);
+        assert_eq!(modules.len(), 3);
+        assert_eq!(modules[0].used_by, Vec::<String>::new());
+        assert_eq!(modules[1].size, 372736);
+        assert_eq!(modules[1].refcount, Some(2));
+        assert_eq!(modules[1].used_by, vec!["nft_chain_nat", "nft_compat"]);
+        assert_eq!(modules[2].state, "Loading");
+        assert_eq!(modules[2].taints.as_deref(), Some("OE"));
+        assert_eq!(modules[0].taints, None);
+    }
+
+    #[test]
+    fn test_parse_lockdown_and_config() {
+        assert_eq!(
+            parse_lockdown("none [integrity] confidentiality\n").as_deref(),
+            Some("integrity")
+        );
+        assert_eq!(parse_lockdown("none integrity"), None);
+
+        let config = parse_kernel_config(
+            "#\n# Automatically generated file\nCONFIG_64BIT=y\nCONFIG_LOCALVERSION=\"-generic\"\n# CONFIG_KASAN is not set\nCONFIG_HZ=250\n",
+        );
+        assert_eq!(config.len(), 4);
+        assert_eq!(config["CONFIG_64BIT"], "y");
+        assert_eq!(config["CONFIG_LOCALVERSION"], "-generic");
+        assert_eq!(config["CONFIG_KASAN"], "n");
+    }
+
+    #[test]
+    fn test_kernel_info_new() -> Result<()> {
+        let kernel = KernelInfo::new()?;
+        assert!(!kernel.cmdline.is_empty());
+        assert_eq!(kernel.taint_flags.len() as u32, kernel.tainted.count_ones());
+        assert_eq!(kernel.config_source.is_some(), !kernel.config.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,468 @@
+use anyhow::{bail, Context, Result};
+use flate2::read::ZlibDecoder;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Parents of the versioned `pythonX.Y` directories holding `site-packages`
+/// or, on Debian, `dist-packages`.
+const PYTHON_LIB_DIRS: [&str; 3] = ["/usr/lib", "/usr/lib64", "/usr/local/lib"];
+const NPM_GLOBAL_DIRS: [&str; 2] = ["/usr/lib/node_modules", "/usr/local/lib/node_modules"];
+const BINARY_DIRS: [&str; 4] = ["/usr/bin", "/usr/sbin", "/usr/local/bin", "/usr/local/sbin"];
+
+/// ELF section in which `cargo auditable` embeds the zlib-compressed
+/// dependency list.
+const AUDITABLE_SECTION: &str = ".dep-v0";
+/// Upper bound for the compressed and decompressed dependency list, which is
+/// a few kilobytes in practice.
+const AUDITABLE_MAX_SIZE: u64 = 8 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+    PyPI,
+    Npm,
+    Cargo,
+    #[default]
+    Other,
+}
+
+/// A package installed by a language package manager rather than the OS.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct LanguagePackage {
+    pub name: String,
+    pub version: String,
+    pub ecosystem: Ecosystem,
+    /// The `.dist-info` directory, the package directory, or for Rust the
+    /// binary the crate was compiled into.
+    pub path: String,
+}
+
+/// Inventories Python distributions, global npm modules and Rust binaries
+/// built with `cargo auditable` in the standard system locations.
+pub fn get_language_packages() -> Vec<LanguagePackage> {
+    let mut packages: Vec<LanguagePackage> = python_package_dirs()
+        .iter()
+        .flat_map(|dir| read_python_packages(dir))
+        .chain(
+            NPM_GLOBAL_DIRS
+                .iter()
+                .flat_map(|dir| read_npm_packages(Path::new(dir))),
+        )
+        .chain(
+            BINARY_DIRS
+                .iter()
+                .flat_map(|dir| read_auditable_binaries(Path::new(dir))),
+        )
+        .collect();
+
+    packages.sort_by(|a, b| (&a.path, &a.name, &a.version).cmp(&(&b.path, &b.name, &b.version)));
+    packages
+}
+
+fn python_package_dirs() -> Vec<PathBuf> {
+    PYTHON_LIB_DIRS
+        .iter()
+        .flat_map(|lib| list_dir(Path::new(lib)))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("python"))
+        })
+        .flat_map(|python| {
+            ["site-packages", "dist-packages"]
+                .into_iter()
+                .map(move |dir| python.join(dir))
+        })
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+/// Reads `Name` and `Version` from the `*.dist-info/METADATA` files in a
+/// `site-packages` directory.
+pub fn read_python_packages(site_packages: &Path) -> Vec<LanguagePackage> {
+    list_dir(site_packages)
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "dist-info"))
+        .filter_map(|dist_info| {
+            let metadata = fs::read_to_string(dist_info.join("METADATA")).ok()?;
+            let (name, version) = parse_python_metadata(&metadata)?;
+            Some(LanguagePackage {
+                name,
+                version,
+                ecosystem: Ecosystem::PyPI,
+                path: dist_info.display().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Parses the email-style header block of a core metadata file.
+fn parse_python_metadata(metadata: &str) -> Option<(String, String)> {
+    let headers = metadata.lines().take_while(|line| !line.is_empty());
+    let (mut name, mut version) = (None, None);
+    for line in headers {
+        if let Some(value) = line.strip_prefix("Name:") {
+            name = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("Version:") {
+            version = Some(value.trim().to_string());
+        }
+    }
+    Some((name?, version?))
+}
+
+/// Reads `package.json` of every module in a `node_modules` directory,
+/// including scoped `@scope/name` modules.
+pub fn read_npm_packages(node_modules: &Path) -> Vec<LanguagePackage> {
+    #[derive(Deserialize)]
+    struct PackageJson {
+        name: String,
+        version: String,
+    }
+
+    list_dir(node_modules)
+        .into_iter()
+        .flat_map(|path| {
+            let is_scope = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('@'));
+            if is_scope {
+                list_dir(&path)
+            } else {
+                vec![path]
+            }
+        })
+        .filter_map(|module| {
+            let content = fs::read_to_string(module.join("package.json")).ok()?;
+            let package: PackageJson = serde_json::from_str(&content).ok()?;
+            Some(LanguagePackage {
+                name: package.name,
+                version: package.version,
+                ecosystem: Ecosystem::Npm,
+                path: module.display().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Lists the crates embedded in every `cargo auditable` binary in `dir`.
+pub fn read_auditable_binaries(dir: &Path) -> Vec<LanguagePackage> {
+    list_dir(dir)
+        .into_iter()
+        .filter(|path| path.is_file())
+        .flat_map(|path| read_auditable_binary(&path).unwrap_or_default())
+        .collect()
+}
+
+/// Returns the crates recorded in the `.dep-v0` section of a binary, or an
+/// empty list if it is not an ELF file or was built without `cargo auditable`.
+pub fn read_auditable_binary(path: &Path) -> Result<Vec<LanguagePackage>> {
+    #[derive(Deserialize)]
+    struct VersionInfo {
+        packages: Vec<AuditablePackage>,
+    }
+    #[derive(Deserialize)]
+    struct AuditablePackage {
+        name: String,
+        version: String,
+        #[serde(default)]
+        kind: Option<String>,
+    }
+
+    let mut file = File::open(path)?;
+    let Some(compressed) = read_elf_section(&mut file, AUDITABLE_SECTION)? else {
+        return Ok(Vec::new());
+    };
+    let mut json = Vec::new();
+    ZlibDecoder::new(compressed.as_slice())
+        .take(AUDITABLE_MAX_SIZE)
+        .read_to_end(&mut json)
+        .context("Failed to decompress .dep-v0")?;
+    let info: VersionInfo = serde_json::from_slice(&json).context("Invalid .dep-v0 JSON")?;
+
+    Ok(info
+        .packages
+        .into_iter()
+        // Build dependencies run at compile time and are not part of the binary.
+        .filter(|package| package.kind.as_deref() != Some("build"))
+        .map(|package| LanguagePackage {
+            name: package.name,
+            version: package.version,
+            ecosystem: Ecosystem::Cargo,
+            path: path.display().to_string(),
+        })
+        .collect())
+}
+
+/// Reads the contents of the named section from an ELF file, without
+/// loading the rest of the file.
+fn read_elf_section<R: Read + Seek>(file: &mut R, name: &str) -> Result<Option<Vec<u8>>> {
+    let mut ident = [0u8; 64];
+    if file.read_exact(&mut ident).is_err() || &ident[..4] != b"\x7fELF" {
+        return Ok(None);
+    }
+    let is_64 = match ident[4] {
+        1 => false,
+        2 => true,
+        class => bail!("Unknown ELF class {}", class),
+    };
+    let big_endian = ident[5] == 2;
+    let u16_at = |bytes: &[u8], offset: usize| -> u64 {
+        let raw = [bytes[offset], bytes[offset + 1]];
+        (if big_endian {
+            u16::from_be_bytes(raw)
+        } else {
+            u16::from_le_bytes(raw)
+        }) as u64
+    };
+    let u32_at = |bytes: &[u8], offset: usize| -> u64 {
+        let raw = bytes[offset..offset + 4].try_into().unwrap();
+        (if big_endian {
+            u32::from_be_bytes(raw)
+        } else {
+            u32::from_le_bytes(raw)
+        }) as u64
+    };
+    let u64_at = |bytes: &[u8], offset: usize| -> u64 {
+        let raw = bytes[offset..offset + 8].try_into().unwrap();
+        if big_endian {
+            u64::from_be_bytes(raw)
+        } else {
+            u64::from_le_bytes(raw)
+        }
+    };
+
+    let (section_offset, entry_size, count, names_index) = if is_64 {
+        (
+            u64_at(&ident, 0x28),
+            u16_at(&ident, 0x3a),
+            u16_at(&ident, 0x3c),
+            u16_at(&ident, 0x3e),
+        )
+    } else {
+        (
+            u32_at(&ident, 0x20),
+            u16_at(&ident, 0x2e),
+            u16_at(&ident, 0x30),
+            u16_at(&ident, 0x32),
+        )
+    };
+    let min_entry_size = if is_64 { 64 } else { 40 };
+    if section_offset == 0 || entry_size < min_entry_size || names_index >= count {
+        return Ok(None);
+    }
+
+    // Header fields are untrusted: check every size against the file before
+    // allocating for it.
+    let file_len = file.seek(SeekFrom::End(0))?;
+    let check_range = |offset: u64, size: u64| -> Result<()> {
+        if size > AUDITABLE_MAX_SIZE {
+            bail!("ELF section of {} bytes is too large", size);
+        }
+        if offset.checked_add(size).is_none_or(|end| end > file_len) {
+            bail!(
+                "ELF section at {}+{} is beyond the end of the file",
+                offset,
+                size
+            );
+        }
+        Ok(())
+    };
+
+    let table_size = entry_size * count;
+    check_range(section_offset, table_size)?;
+    let mut table = vec![0u8; table_size as usize];
+    file.seek(SeekFrom::Start(section_offset))?;
+    file.read_exact(&mut table)?;
+
+    // (name offset, file offset, size) of each section header.
+    let sections: Vec<(u64, u64, u64)> = table
+        .chunks_exact(entry_size as usize)
+        .map(|entry| {
+            if is_64 {
+                (u32_at(entry, 0), u64_at(entry, 0x18), u64_at(entry, 0x20))
+            } else {
+                (u32_at(entry, 0), u32_at(entry, 0x10), u32_at(entry, 0x14))
+            }
+        })
+        .collect();
+
+    let mut read_at = |offset: u64, size: u64| -> Result<Vec<u8>> {
+        check_range(offset, size)?;
+        let mut data = vec![0u8; size as usize];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut data)?;
+        Ok(data)
+    };
+
+    let (_, names_offset, names_size) = sections[names_index as usize];
+    let names = read_at(names_offset, names_size)?;
+    let section = sections.iter().find(|(name_offset, _, _)| {
+        names
+            .get(*name_offset as usize..)
+            .and_then(|rest| rest.split(|b| *b == 0).next())
+            .is_some_and(|section_name| section_name == name.as_bytes())
+    });
+
+    match section {
+        Some(&(_, offset, size)) => read_at(offset, size).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn list_dir(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::test_util::TempDir;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// Builds a little-endian ELF64 file whose only sections are the section
+    /// name table and `section` with `data`.
+    fn elf_with_section(section: &str, data: &[u8]) -> Vec<u8> {
+        let names = format!("\0.shstrtab\0{}\0", section).into_bytes();
+        let names_offset = 64u64;
+        let data_offset = names_offset + names.len() as u64;
+        let table_offset = data_offset + data.len() as u64;
+
+        let mut elf = vec![0u8; 64];
+        elf[..4].copy_from_slice(b"\x7fELF");
+        elf[4] = 2;
+        elf[5] = 1;
+        elf[6] = 1;
+        elf[0x28..0x30].copy_from_slice(&table_offset.to_le_bytes());
+        elf[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+        elf[0x3c..0x3e].copy_from_slice(&3u16.to_le_bytes());
+        elf[0x3e..0x40].copy_from_slice(&1u16.to_le_bytes());
+        elf.extend_from_slice(&names);
+        elf.extend_from_slice(data);
+
+        let header = |name: u32, offset: u64, size: u64| {
+            let mut entry = vec![0u8; 64];
+            entry[..4].copy_from_slice(&name.to_le_bytes());
+            entry[0x18..0x20].copy_from_slice(&offset.to_le_bytes());
+            entry[0x20..0x28].copy_from_slice(&size.to_le_bytes());
+            entry
+        };
+        elf.extend(header(0, 0, 0));
+        elf.extend(header(1, names_offset, names.len() as u64));
+        elf.extend(header(11, data_offset, data.len() as u64));
+        elf
+    }
+
+    #[test]
+    fn test_read_auditable_binary() -> Result<()> {
+        let json = r#"{"packages":[
+            {"name":"ripgrep","version":"14.1.0","source":"crates.io","root":true,"dependencies":[1,2]},
+            {"name":"regex","version":"1.10.3","source":"crates.io"},
+            {"name":"cc","version":"1.0.83","source":"crates.io","kind":"build"}
+        ]}"#;
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(json.as_bytes())?;
+        let compressed = encoder.finish()?;
+
+        let dir = TempDir::new("auditable");
+        fs::write(
+            dir.join("rg"),
+            elf_with_section(AUDITABLE_SECTION, &compressed),
+        )?;
+        fs::write(dir.join("plain"), elf_with_section(".text", b"\x90\x90"))?;
+        fs::write(dir.join("script"), "#!/bin/sh\n")?;
+
+        let mut packages = read_auditable_binaries(&dir);
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "regex");
+        assert_eq!(packages[1].version, "14.1.0");
+        assert_eq!(packages[1].ecosystem, Ecosystem::Cargo);
+        assert!(packages[1].path.ends_with("/rg"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_elf_section_bounds() -> Result<()> {
+        use std::io::Cursor;
+
+        let elf = elf_with_section(".data", b"payload");
+        assert_eq!(
+            read_elf_section(&mut Cursor::new(&elf), ".data")?.as_deref(),
+            Some(&b"payload"[..])
+        );
+
+        // A header claiming 65535 entries of 65535 bytes must be rejected
+        // before allocating ~4 GiB.
+        let mut huge_table = elf.clone();
+        huge_table[0x3a..0x3c].copy_from_slice(&u16::MAX.to_le_bytes());
+        huge_table[0x3c..0x3e].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(read_elf_section(&mut Cursor::new(&huge_table), ".data").is_err());
+
+        // Truncating the file cuts off the section header table.
+        let truncated = &elf[..elf.len() - 1];
+        assert!(read_elf_section(&mut Cursor::new(truncated), ".data").is_err());
+
+        // A section size pointing past the end of the file.
+        let mut oversized = elf.clone();
+        let size_offset = elf.len() - 64 + 0x20;
+        oversized[size_offset..size_offset + 8].copy_from_slice(&(1u64 << 20).to_le_bytes());
+        assert!(read_elf_section(&mut Cursor::new(&oversized), ".data").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_python_and_npm_packages() -> Result<()> {
+        let dir = TempDir::new("langpkg");
+        let dist_info = dir.join("site-packages/requests-2.31.0.dist-info");
+        fs::create_dir_all(&dist_info)?;
+        fs::write(
+            dist_info.join("METADATA"),
+            "Metadata-Version: 2.1\nName: requests\nVersion: 2.31.0\n\nVersion: not a header\n",
+        )?;
+        fs::create_dir_all(dir.join("site-packages/requests"))?;
+
+        let node_modules = dir.join("node_modules");
+        fs::create_dir_all(node_modules.join("npm"))?;
+        fs::write(
+            node_modules.join("npm/package.json"),
+            r#"{"name":"npm","version":"10.2.4","bin":{"npm":"bin/npm-cli.js"}}"#,
+        )?;
+        fs::create_dir_all(node_modules.join("@angular/cli"))?;
+        fs::write(
+            node_modules.join("@angular/cli/package.json"),
+            r#"{"name":"@angular/cli","version":"17.1.0"}"#,
+        )?;
+
+        let python = read_python_packages(&dir.join("site-packages"));
+        let mut npm = read_npm_packages(&node_modules);
+        npm.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(python.len(), 1);
+        assert_eq!(python[0].name, "requests");
+        assert_eq!(python[0].version, "2.31.0");
+        assert_eq!(python[0].ecosystem, Ecosystem::PyPI);
+        assert_eq!(npm.len(), 2);
+        assert_eq!(npm[0].name, "@angular/cli");
+        assert_eq!(npm[1].version, "10.2.4");
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_language_packages() {
+        let packages = get_language_packages();
+        assert!(packages.iter().all(|package| !package.name.is_empty()));
+    }
+}
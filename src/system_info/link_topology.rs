@@ -0,0 +1,477 @@
+use super::network::{ethtool_driver_info, read_sysfs_string};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+const SYS_CLASS_NET_PATH: &str = "/sys/class/net";
+const PROC_NET_BONDING_PATH: &str = "/proc/net/bonding";
+const PROC_NET_VLAN_CONFIG_PATH: &str = "/proc/net/vlan/config";
+
+/// Port states from `<linux/if_bridge.h>`, indexed by their numeric value.
+const BRIDGE_PORT_STATES: [&str; 5] = [
+    "disabled",
+    "listening",
+    "learning",
+    "forwarding",
+    "blocking",
+];
+
+/// How aggregate and virtual links are assembled from other interfaces.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct LinkTopology {
+    pub bonds: Vec<Bond>,
+    pub bridges: Vec<Bridge>,
+    pub vlans: Vec<Vlan>,
+    pub teams: Vec<Team>,
+    /// macvlan, macvtap and ipvlan devices and the link they sit on.
+    pub stacked: Vec<StackedLink>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Bond {
+    pub name: String,
+    /// Bonding mode as printed by the driver, e.g. `802.3ad` or `active-backup`.
+    pub mode: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub active_slave: Option<String>,
+    pub mii_status: String,
+    pub slaves: Vec<BondSlave>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct BondSlave {
+    pub name: String,
+    pub mii_status: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub permanent_mac: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub link_failures: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Bridge {
+    pub name: String,
+    pub stp_enabled: bool,
+    pub ports: Vec<BridgePort>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct BridgePort {
+    pub name: String,
+    pub state: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Vlan {
+    pub name: String,
+    pub id: u16,
+    pub parent: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Team {
+    pub name: String,
+    pub ports: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct StackedLink {
+    pub name: String,
+    pub kind: String,
+    pub lower: String,
+}
+
+impl LinkTopology {
+    pub fn new() -> Result<Self> {
+        let sys_class_net = Path::new(SYS_CLASS_NET_PATH);
+        let entries = fs::read_dir(sys_class_net)
+            .with_context(|| format!("Failed to read {}", SYS_CLASS_NET_PATH))?;
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+
+        let mut topology = LinkTopology {
+            vlans: fs::read_to_string(PROC_NET_VLAN_CONFIG_PATH)
+                .map(|content| parse_vlan_config(&content))
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+
+        for name in names {
+            let path = sys_class_net.join(&name);
+            if path.join("bonding").exists() {
+                topology.bonds.push(read_bond(&name, &path));
+            } else if path.join("bridge").exists() {
+                topology.bridges.push(read_bridge(&name, &path));
+            } else if let Some(kind) = stacked_kind(&path) {
+                if let Some(lower) = lower_devices(&path).into_iter().next() {
+                    topology.stacked.push(StackedLink { name, kind, lower });
+                }
+            } else if ethtool_driver_info(&name).is_ok_and(|info| info.driver == "team") {
+                topology.teams.push(Team {
+                    ports: lower_devices(&path),
+                    name,
+                });
+            }
+        }
+        Ok(topology)
+    }
+
+    /// Returns the bond, bridge or team an interface is enslaved to.
+    pub fn master_of(&self, interface: &str) -> Option<&str> {
+        let bond = self
+            .bonds
+            .iter()
+            .find(|bond| bond.slaves.iter().any(|slave| slave.name == interface))
+            .map(|bond| bond.name.as_str());
+        let bridge = || {
+            self.bridges
+                .iter()
+                .find(|bridge| bridge.ports.iter().any(|port| port.name == interface))
+                .map(|bridge| bridge.name.as_str())
+        };
+        let team = || {
+            self.teams
+                .iter()
+                .find(|team| team.ports.iter().any(|port| port == interface))
+                .map(|team| team.name.as_str())
+        };
+        bond.or_else(bridge).or_else(team)
+    }
+}
+
+/// Reads a bond from `/proc/net/bonding/<name>`, which carries per-slave
+/// details, falling back to the sysfs attributes.
+fn read_bond(name: &str, path: &Path) -> Bond {
+    if let Ok(content) = fs::read_to_string(Path::new(PROC_NET_BONDING_PATH).join(name)) {
+        return parse_proc_bonding(name, &content);
+    }
+
+    let bonding = path.join("bonding");
+    // `<bond>/..` would resolve through the device symlink, so join the
+    // slave name onto the lexical parent instead.
+    let sys_class_net = path.parent().unwrap_or(path);
+    let slaves = read_sysfs_string(&bonding.join("slaves"))
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(|slave| {
+            let bonding_slave = sys_class_net.join(slave).join("bonding_slave");
+            BondSlave {
+                name: slave.to_string(),
+                mii_status: read_sysfs_string(&bonding_slave.join("mii_status"))
+                    .unwrap_or_default(),
+                permanent_mac: read_sysfs_string(&bonding_slave.join("perm_hwaddr")),
+                link_failures: read_sysfs_string(&bonding_slave.join("link_failure_count"))
+                    .and_then(|count| count.parse().ok()),
+            }
+        })
+        .collect();
+
+    Bond {
+        name: name.to_string(),
+        // sysfs prints the mode followed by its number, e.g. "802.3ad 4".
+        mode: read_sysfs_string(&bonding.join("mode"))
+            .and_then(|mode| mode.split_whitespace().next().map(str::to_string))
+            .unwrap_or_default(),
+        active_slave: read_sysfs_string(&bonding.join("active_slave"))
+            .filter(|slave| !slave.is_empty()),
+        mii_status: read_sysfs_string(&bonding.join("mii_status")).unwrap_or_default(),
+        slaves,
+    }
+}
+
+fn parse_proc_bonding(name: &str, content: &str) -> Bond {
+    let mut bond = Bond {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    let mut current_slave: Option<BondSlave> = None;
+
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+
+        match key {
+            "Slave Interface" => {
+                bond.slaves.extend(current_slave.take());
+                current_slave = Some(BondSlave {
+                    name: value.to_string(),
+                    ..Default::default()
+                });
+            }
+            "Bonding Mode" => bond.mode = bonding_mode_name(value).to_string(),
+            "Currently Active Slave" if value != "None" => {
+                bond.active_slave = Some(value.to_string())
+            }
+            "MII Status" => match current_slave.as_mut() {
+                Some(slave) => slave.mii_status = value.to_string(),
+                None => bond.mii_status = value.to_string(),
+            },
+            "Permanent HW addr" => {
+                if let Some(slave) = current_slave.as_mut() {
+                    slave.permanent_mac = Some(value.to_string());
+                }
+            }
+            "Link Failure Count" => {
+                if let Some(slave) = current_slave.as_mut() {
+                    slave.link_failures = value.parse().ok();
+                }
+            }
+            _ => {}
+        }
+    }
+    bond.slaves.extend(current_slave);
+    bond
+}
+
+/// Maps the descriptive mode of `/proc/net/bonding` to the short name used
+/// by sysfs and `ip link`. Active-backup bonds may carry a
+/// `(fail_over_mac active)` suffix, which is dropped.
+fn bonding_mode_name(description: &str) -> &str {
+    let description = match description.find(" (fail_over_mac") {
+        Some(index) => &description[..index],
+        None => description,
+    };
+    match description {
+        "load balancing (round-robin)" => "balance-rr",
+        "fault-tolerance (active-backup)" => "active-backup",
+        "load balancing (xor)" => "balance-xor",
+        "fault-tolerance (broadcast)" => "broadcast",
+        "IEEE 802.3ad Dynamic link aggregation" => "802.3ad",
+        "transmit load balancing" => "balance-tlb",
+        "adaptive load balancing" => "balance-alb",
+        other => other,
+    }
+}
+
+fn read_bridge(name: &str, path: &Path) -> Bridge {
+    let brif = path.join("brif");
+    let mut ports: Vec<BridgePort> = fs::read_dir(&brif)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| {
+                    let state = read_sysfs_string(&entry.path().join("state"))
+                        .and_then(|state| state.parse::<usize>().ok())
+                        .and_then(|state| BRIDGE_PORT_STATES.get(state).copied())
+                        .unwrap_or("unknown");
+                    BridgePort {
+                        name: entry.file_name().to_string_lossy().into_owned(),
+                        state: state.to_string(),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    ports.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Bridge {
+        name: name.to_string(),
+        stp_enabled: read_sysfs_string(&path.join("bridge/stp_state"))
+            .is_some_and(|state| state != "0"),
+        ports,
+    }
+}
+
+/// Parses `/proc/net/vlan/config`:
+///
+/// ```text
+/// VLAN Dev name    | VLAN ID
+/// Name-Type: VLAN_NAME_TYPE_RAW_PLUS_VID_NO_PAD
+/// eth0.100       | 100  | eth0
+/// ```
+fn parse_vlan_config(content: &str) -> Vec<Vlan> {
+    content
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let mut fields = line.split('|').map(str::trim);
+            let name = fields.next()?;
+            let id = fields.next()?.parse().ok()?;
+            let parent = fields.next()?;
+            Some(Vlan {
+                name: name.to_string(),
+                id,
+                parent: parent.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn stacked_kind(path: &Path) -> Option<String> {
+    read_sysfs_string(&path.join("uevent"))?
+        .lines()
+        .find_map(|line| line.strip_prefix("DEVTYPE="))
+        .filter(|devtype| matches!(*devtype, "macvlan" | "macvtap" | "ipvlan" | "ipvtap"))
+        .map(str::to_string)
+}
+
+/// Lists the `lower_<name>` links the kernel creates for stacked devices.
+fn lower_devices(path: &Path) -> Vec<String> {
+    let mut lower: Vec<String> = fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    entry
+                        .file_name()
+                        .to_str()
+                        .and_then(|name| name.strip_prefix("lower_"))
+                        .map(str::to_string)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    lower.sort();
+    lower
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::test_util::TempDir;
+
+    const PROC_BONDING: &str = "\
+Ethernet Channel Bonding Driver: v5.15.0
+
+Bonding Mode: IEEE 802.3ad Dynamic link aggregation
+Transmit Hash Policy: layer3+4 (1)
+MII Status: up
+MII Polling Interval (ms): 100
+
+802.3ad info
+LACP rate: fast
+
+Slave Interface: eth0
+MII Status: up
+Speed: 10000 Mbps
+Link Failure Count: 0
+Permanent HW addr: 52:54:00:aa:bb:01
+Slave queue ID: 0
+
+Slave Interface: eth1
+MII Status: down
+Link Failure Count: 3
+Permanent HW addr: 52:54:00:aa:bb:02
+";
+
+    #[test]
+    fn test_parse_proc_bonding() {
+        let bond = parse_proc_bonding("bond0", PROC_BONDING);
+        assert_eq!(bond.mode, "802.3ad");
+        assert_eq!(bond.mii_status, "up");
+        assert_eq!(bond.active_slave, None);
+        assert_eq!(bond.slaves.len(), 2);
+        assert_eq!(bond.slaves[0].name, "eth0");
+        assert_eq!(bond.slaves[0].mii_status, "up");
+        assert_eq!(
+            bond.slaves[1].permanent_mac.as_deref(),
+            Some("52:54:00:aa:bb:02")
+        );
+        assert_eq!(bond.slaves[1].link_failures, Some(3));
+
+        let topology = LinkTopology {
+            bonds: vec![bond],
+            ..Default::default()
+        };
+        assert_eq!(topology.master_of("eth1"), Some("bond0"));
+        assert_eq!(topology.master_of("eth2"), None);
+    }
+
+    #[test]
+    fn test_bonding_mode_name() {
+        assert_eq!(
+            bonding_mode_name("fault-tolerance (active-backup)"),
+            "active-backup"
+        );
+        assert_eq!(
+            bonding_mode_name("fault-tolerance (active-backup) (fail_over_mac active)"),
+            "active-backup"
+        );
+        assert_eq!(
+            bonding_mode_name("fault-tolerance (active-backup) (fail_over_mac follow)"),
+            "active-backup"
+        );
+        assert_eq!(bonding_mode_name("load balancing (xor)"), "balance-xor");
+        assert_eq!(bonding_mode_name("something new"), "something new");
+    }
+
+    #[test]
+    fn test_parse_vlan_config() {
+        let content = "\
+VLAN Dev name    | VLAN ID
+Name-Type: VLAN_NAME_TYPE_RAW_PLUS_VID_NO_PAD
+eth0.100       | 100  | eth0
+vlan200        | 200  | bond0
+";
+        let vlans = parse_vlan_config(content);
+        assert_eq!(
+            vlans,
+            vec![
+                Vlan {
+                    name: "eth0.100".to_string(),
+                    id: 100,
+                    parent: "eth0".to_string()
+                },
+                Vlan {
+                    name: "vlan200".to_string(),
+                    id: 200,
+                    parent: "bond0".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_bridge() -> Result<()> {
+        let dir = TempDir::new("bridge");
+        let bridge = dir.join("br0");
+        fs::create_dir_all(bridge.join("bridge"))?;
+        fs::write(bridge.join("bridge/stp_state"), "1\n")?;
+        for (port, state) in [("veth1", "3"), ("eth0", "4")] {
+            fs::create_dir_all(bridge.join("brif").join(port))?;
+            fs::write(bridge.join("brif").join(port).join("state"), state)?;
+        }
+        fs::create_dir_all(dir.join("macvlan0").join("lower_eth0"))?;
+        fs::write(dir.join("macvlan0/uevent"), "DEVTYPE=macvlan\n")?;
+
+        let br = read_bridge("br0", &bridge);
+        let kind = stacked_kind(&dir.join("macvlan0"));
+        let lower = lower_devices(&dir.join("macvlan0"));
+
+        assert!(br.stp_enabled);
+        assert_eq!(
+            br.ports,
+            vec![
+                BridgePort {
+                    name: "eth0".to_string(),
+                    state: "blocking".to_string()
+                },
+                BridgePort {
+                    name: "veth1".to_string(),
+                    state: "forwarding".to_string()
+                },
+            ]
+        );
+        assert_eq!(kind.as_deref(), Some("macvlan"));
+        assert_eq!(lower, vec!["eth0"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_link_topology_new() -> Result<()> {
+        let topology = LinkTopology::new()?;
+        for bond in &topology.bonds {
+            for slave in &bond.slaves {
+                assert_eq!(topology.master_of(&slave.name), Some(bond.name.as_str()));
+            }
+        }
+        Ok(())
+    }
+}
@@ -13,18 +13,59 @@
 //! }
 //! ```
 
+pub mod accounts;
+pub mod cgroup;
+pub mod cpustats;
+pub mod diskstats;
+pub mod dns;
+pub mod filesystem;
 pub mod hardware;
+pub mod identity;
+pub mod kernel;
+pub mod language_packages;
+pub mod link_topology;
+pub mod netstats;
+pub mod network;
+pub mod os_release;
+pub mod osv;
+pub mod packages;
+pub mod pressure;
+pub mod processes;
+pub mod routing;
+pub mod sbom;
+pub mod services;
+pub mod sockets;
 pub mod software;
+pub mod storage;
+#[cfg(test)]
+mod test_util;
+pub mod uname;
+pub mod uptime;
+pub mod versions;
 
+use accounts::AccountsInfo;
 use anyhow::Result;
 use hardware::HardwareInfo;
+use kernel::KernelInfo;
 use serde::{Deserialize, Serialize};
 use software::SoftwareInfo;
+use uptime::UptimeInfo;
+
+/// Version of the serialized [`MachineInfo`] layout.
+///
+/// 2.0 serializes `software.uname` as an object instead of a JSON string.
+pub const SCHEMA_VERSION: &str = "2.0";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MachineInfo {
     pub hardware: HardwareInfo,
     pub software: SoftwareInfo,
+    #[serde(default)]
+    pub kernel: KernelInfo,
+    #[serde(default)]
+    pub uptime: UptimeInfo,
+    #[serde(default)]
+    pub accounts: AccountsInfo,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub extra: Option<serde_json::Value>,
     pub version: String,
@@ -40,6 +81,9 @@ impl MachineInfo {
 pub struct MachineInfoBuilder {
     hardware: Option<HardwareInfo>,
     software: Option<SoftwareInfo>,
+    kernel: Option<KernelInfo>,
+    uptime: Option<UptimeInfo>,
+    accounts: Option<AccountsInfo>,
     extra: Option<serde_json::Value>,
     version: Option<String>,
 }
@@ -55,6 +99,21 @@ impl MachineInfoBuilder {
         self
     }
 
+    pub fn kernel(mut self, kernel: KernelInfo) -> Self {
+        self.kernel = Some(kernel);
+        self
+    }
+
+    pub fn uptime(mut self, uptime: UptimeInfo) -> Self {
+        self.uptime = Some(uptime);
+        self
+    }
+
+    pub fn accounts(mut self, accounts: AccountsInfo) -> Self {
+        self.accounts = Some(accounts);
+        self
+    }
+
     pub fn extra(mut self, extra: serde_json::Value) -> Self {
         self.extra = Some(extra);
         self
@@ -73,8 +132,11 @@ impl MachineInfoBuilder {
             software: self
                 .software
                 .ok_or_else(|| anyhow::anyhow!("Software info is required"))?,
+            kernel: self.kernel.unwrap_or_default(),
+            uptime: self.uptime.unwrap_or_default(),
+            accounts: self.accounts.unwrap_or_default(),
             extra: self.extra,
-            version: self.version.unwrap_or_else(|| "1.0".to_string()),
+            version: self.version.unwrap_or_else(|| SCHEMA_VERSION.to_string()),
         })
     }
 }
@@ -83,7 +145,10 @@ pub fn get_machine_info() -> Result<MachineInfo> {
     MachineInfo::builder()
         .hardware(HardwareInfo::new()?)
         .software(SoftwareInfo::new()?)
-        .version("1.0".to_string())
+        .kernel(KernelInfo::new().unwrap_or_default())
+        .uptime(UptimeInfo::new().unwrap_or_default())
+        .accounts(AccountsInfo::new().unwrap_or_default())
+        .version(SCHEMA_VERSION.to_string())
         .build()
 }
 
@@ -123,8 +188,13 @@ mod tests {
     fn test_get_machine_info() -> Result<()> {
         let machine_info = get_machine_info()?;
         assert!(!machine_info.hardware.mac_addresses.is_empty());
+        assert!(!machine_info.hardware.network_interfaces.is_empty());
         assert!(!machine_info.software.os_release.is_empty());
-        assert_eq!(machine_info.version, "1.0");
+        assert!(!machine_info.software.uname.release.is_empty());
+        assert!(!machine_info.kernel.cmdline.is_empty());
+        assert!(machine_info.uptime.boot_time > 0);
+        assert!(!machine_info.accounts.users.is_empty());
+        assert_eq!(machine_info.version, SCHEMA_VERSION);
         Ok(())
     }
 
@@ -236,7 +306,8 @@ mod tests {
 
         // Test software fields
         assert!(deserialized.software.os_release.contains("Cloud Linux"));
-        assert!(deserialized.software.uname.contains("x86_64"));
+        assert_eq!(deserialized.software.uname.machine, "x86_64");
+        assert_eq!(deserialized.software.uname.release, "6.6.31-cloudlinux");
         assert_eq!(deserialized.version, "1.0");
 
         Ok(())
@@ -249,6 +320,9 @@ mod tests {
                 cpu_is_virtual: true,
                 disk_serial_number: "********".to_string(),
                 mac_addresses: "**:**:**:**:**:**".to_string(),
+                network_interfaces: Vec::new(),
+                link_topology: Default::default(),
+                routing: Default::default(),
                 bios_info: BiosInfo {
                     vendor: "Test Vendor".to_string(),
                     bios_version: "1.0".to_string(),
@@ -270,13 +344,22 @@ mod tests {
                     serial_number: "********".to_string(),
                     asset_tag_number: "********".to_string(),
                 },
+                filesystems: Vec::new(),
+                storage_topology: Default::default(),
                 extra: None,
             },
             software: SoftwareInfo {
                 os_release: "Test OS 1.0".to_string(),
-                uname: "Test Uname".to_string(),
+                os: Default::default(),
+                uname: Default::default(),
+                network_identity: Default::default(),
+                language_packages: Vec::new(),
+                services: Vec::new(),
                 extra: None,
             },
+            kernel: Default::default(),
+            uptime: Default::default(),
+            accounts: Default::default(),
             extra: None,
             version: "1.0".to_string(),
         };
@@ -288,11 +371,14 @@ mod tests {
         assert!(deserialized["hardware"]["cpu_is_virtual"].is_boolean());
         assert!(deserialized["hardware"]["disk_serial_number"].is_string());
         assert!(deserialized["hardware"]["mac_addresses"].is_string());
+        assert!(deserialized["hardware"]["network_interfaces"].is_array());
         assert!(deserialized["hardware"]["bios_info"]["vendor"].is_string());
         assert!(deserialized["hardware"]["system_info"]["manufacturer"].is_string());
         assert!(deserialized["hardware"]["enclosure_info"]["manufacturer"].is_string());
         assert!(deserialized["software"]["os_release"].is_string());
-        assert!(deserialized["software"]["uname"].is_string());
+        assert!(deserialized["software"]["uname"]["release"].is_string());
+        assert!(deserialized["kernel"]["parameters"].is_array());
+        assert!(deserialized["uptime"]["load_average"]["one"].is_number());
         assert_eq!(deserialized["version"], "1.0");
 
         // Ensure that extra fields are not present
@@ -350,6 +436,10 @@ mod tests {
         assert!(deserialized.hardware.cpu_is_virtual);
         assert_eq!(deserialized.hardware.disk_serial_number, "********");
         assert_eq!(deserialized.software.os_release, "Test OS 1.0");
+        assert_eq!(
+            deserialized.software.uname.raw.as_deref(),
+            Some("Test Uname")
+        );
         assert_eq!(deserialized.version, "1.0");
 
         // Check that extra fields are ignored without causing errors
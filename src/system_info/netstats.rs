@@ -0,0 +1,251 @@
+use super::network::read_sysfs_value;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const SYS_CLASS_NET_PATH: &str = "/sys/class/net";
+
+/// Cumulative counters of a network interface from
+/// `/sys/class/net/<iface>/statistics`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct InterfaceStats {
+    pub name: String,
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub rx_errors: u64,
+    pub rx_dropped: u64,
+    pub rx_fifo_errors: u64,
+    pub rx_frame_errors: u64,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+    pub tx_errors: u64,
+    pub tx_dropped: u64,
+    pub tx_fifo_errors: u64,
+    pub tx_carrier_errors: u64,
+    pub collisions: u64,
+    pub multicast: u64,
+    /// Number of link up/down transitions, absent on old kernels.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub carrier_changes: Option<u64>,
+}
+
+/// A set of counters taken at a single point in time.
+#[derive(Debug, Clone)]
+pub struct InterfaceStatsSample {
+    pub taken_at: Instant,
+    pub interfaces: HashMap<String, InterfaceStats>,
+}
+
+/// Per-interface rates derived from two [`InterfaceStatsSample`]s.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct InterfaceRates {
+    pub name: String,
+    pub rx_bytes_per_sec: f64,
+    pub tx_bytes_per_sec: f64,
+    pub rx_packets_per_sec: f64,
+    pub tx_packets_per_sec: f64,
+    pub rx_errors_per_sec: f64,
+    pub tx_errors_per_sec: f64,
+    pub rx_dropped_per_sec: f64,
+    pub tx_dropped_per_sec: f64,
+    /// Errors as a fraction of the packets seen during the interval.
+    pub error_ratio: f64,
+    /// Link transitions during the interval; non-zero means the link flapped.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub carrier_changes: Option<u64>,
+}
+
+pub fn sample_interface_stats() -> Result<InterfaceStatsSample> {
+    let interfaces = read_interface_stats(SYS_CLASS_NET_PATH)?
+        .into_iter()
+        .map(|stats| (stats.name.clone(), stats))
+        .collect();
+
+    Ok(InterfaceStatsSample {
+        taken_at: Instant::now(),
+        interfaces,
+    })
+}
+
+pub fn read_interface_stats<P: AsRef<Path>>(sys_class_net: P) -> Result<Vec<InterfaceStats>> {
+    let sys_class_net = sys_class_net.as_ref();
+    let entries = fs::read_dir(sys_class_net)
+        .with_context(|| format!("Failed to read {}", sys_class_net.display()))?;
+
+    let mut stats: Vec<InterfaceStats> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("statistics").is_dir())
+        .map(|entry| read_interface_counters(&entry.path()))
+        .collect();
+
+    stats.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(stats)
+}
+
+fn read_interface_counters(path: &Path) -> InterfaceStats {
+    let statistics = path.join("statistics");
+    let counter = |name: &str| read_sysfs_value(&statistics.join(name)).unwrap_or(0);
+
+    InterfaceStats {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        rx_bytes: counter("rx_bytes"),
+        rx_packets: counter("rx_packets"),
+        rx_errors: counter("rx_errors"),
+        rx_dropped: counter("rx_dropped"),
+        rx_fifo_errors: counter("rx_fifo_errors"),
+        rx_frame_errors: counter("rx_frame_errors"),
+        tx_bytes: counter("tx_bytes"),
+        tx_packets: counter("tx_packets"),
+        tx_errors: counter("tx_errors"),
+        tx_dropped: counter("tx_dropped"),
+        tx_fifo_errors: counter("tx_fifo_errors"),
+        tx_carrier_errors: counter("tx_carrier_errors"),
+        collisions: counter("collisions"),
+        multicast: counter("multicast"),
+        // Reading carrier_changes fails with EINVAL while the device is down.
+        carrier_changes: read_sysfs_value(&path.join("carrier_changes")),
+    }
+}
+
+/// Computes per-interface rates between two samples.
+///
+/// Interfaces missing from either sample are skipped. Counters that went
+/// backwards (driver reload, interface recreated) yield zero.
+pub fn compute_interface_rates(
+    previous: &InterfaceStatsSample,
+    current: &InterfaceStatsSample,
+) -> Vec<InterfaceRates> {
+    let elapsed = current
+        .taken_at
+        .saturating_duration_since(previous.taken_at);
+    let mut rates: Vec<InterfaceRates> = current
+        .interfaces
+        .values()
+        .filter_map(|curr| {
+            let prev = previous.interfaces.get(&curr.name)?;
+            Some(interface_rates_between(prev, curr, elapsed))
+        })
+        .collect();
+
+    rates.sort_by(|a, b| a.name.cmp(&b.name));
+    rates
+}
+
+fn interface_rates_between(
+    prev: &InterfaceStats,
+    curr: &InterfaceStats,
+    elapsed: Duration,
+) -> InterfaceRates {
+    let secs = elapsed.as_secs_f64();
+    let per_sec = |delta: u64| if secs > 0.0 { delta as f64 / secs } else { 0.0 };
+
+    let rx_packets = curr.rx_packets.saturating_sub(prev.rx_packets);
+    let tx_packets = curr.tx_packets.saturating_sub(prev.tx_packets);
+    let rx_errors = curr.rx_errors.saturating_sub(prev.rx_errors);
+    let tx_errors = curr.tx_errors.saturating_sub(prev.tx_errors);
+    let packets = rx_packets + tx_packets;
+
+    InterfaceRates {
+        name: curr.name.clone(),
+        rx_bytes_per_sec: per_sec(curr.rx_bytes.saturating_sub(prev.rx_bytes)),
+        tx_bytes_per_sec: per_sec(curr.tx_bytes.saturating_sub(prev.tx_bytes)),
+        rx_packets_per_sec: per_sec(rx_packets),
+        tx_packets_per_sec: per_sec(tx_packets),
+        rx_errors_per_sec: per_sec(rx_errors),
+        tx_errors_per_sec: per_sec(tx_errors),
+        rx_dropped_per_sec: per_sec(curr.rx_dropped.saturating_sub(prev.rx_dropped)),
+        tx_dropped_per_sec: per_sec(curr.tx_dropped.saturating_sub(prev.tx_dropped)),
+        error_ratio: if packets > 0 {
+            (rx_errors + tx_errors) as f64 / packets as f64
+        } else {
+            0.0
+        },
+        carrier_changes: match (curr.carrier_changes, prev.carrier_changes) {
+            (Some(curr), Some(prev)) => Some(curr.saturating_sub(prev)),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::test_util::TempDir;
+
+    #[test]
+    fn test_read_interface_stats() -> Result<()> {
+        let dir = TempDir::new("netstats");
+        let statistics = dir.join("eth0/statistics");
+        fs::create_dir_all(&statistics)?;
+        fs::write(statistics.join("rx_bytes"), "123456\n")?;
+        fs::write(statistics.join("tx_errors"), "7\n")?;
+        fs::write(dir.join("eth0/carrier_changes"), "4\n")?;
+        fs::write(dir.join("bonding_masters"), "bond0\n")?;
+
+        let stats = read_interface_stats(&dir)?;
+
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].name, "eth0");
+        assert_eq!(stats[0].rx_bytes, 123456);
+        assert_eq!(stats[0].tx_errors, 7);
+        assert_eq!(stats[0].rx_packets, 0);
+        assert_eq!(stats[0].carrier_changes, Some(4));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_interface_rates() {
+        let prev = InterfaceStats {
+            name: "eth0".to_string(),
+            rx_bytes: 1_000,
+            rx_packets: 100,
+            tx_packets: 100,
+            carrier_changes: Some(2),
+            ..Default::default()
+        };
+        let curr = InterfaceStats {
+            rx_bytes: 5_000,
+            rx_packets: 300,
+            tx_packets: 300,
+            rx_errors: 4,
+            carrier_changes: Some(4),
+            ..prev.clone()
+        };
+
+        let start = Instant::now();
+        let previous = InterfaceStatsSample {
+            taken_at: start,
+            interfaces: HashMap::from([(prev.name.clone(), prev)]),
+        };
+        let current = InterfaceStatsSample {
+            taken_at: start + Duration::from_secs(4),
+            interfaces: HashMap::from([(curr.name.clone(), curr)]),
+        };
+
+        let rates = compute_interface_rates(&previous, &current);
+        assert_eq!(rates.len(), 1);
+        assert_eq!(rates[0].rx_bytes_per_sec, 1_000.0);
+        assert_eq!(rates[0].rx_packets_per_sec, 50.0);
+        assert_eq!(rates[0].rx_errors_per_sec, 1.0);
+        assert_eq!(rates[0].error_ratio, 0.01);
+        assert_eq!(rates[0].carrier_changes, Some(2));
+    }
+
+    #[test]
+    fn test_sample_interface_stats() -> Result<()> {
+        let first = sample_interface_stats()?;
+        let second = sample_interface_stats()?;
+        let rates = compute_interface_rates(&first, &second);
+        assert_eq!(
+            rates.len(),
+            first.interfaces.len().min(second.interfaces.len())
+        );
+        Ok(())
+    }
+}
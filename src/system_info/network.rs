@@ -0,0 +1,656 @@
+use anyhow::{bail, Result};
+use nix::libc;
+use nix::sys::socket::{socket, AddressFamily, SockFlag, SockType};
+use pnet::datalink;
+use pnet::ipnetwork::IpNetwork;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::fs;
+use std::net::IpAddr;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+const SYS_CLASS_NET_PATH: &str = "/sys/class/net";
+
+const SIOCETHTOOL: libc::c_ulong = 0x8946;
+const ETHTOOL_GDRVINFO: u32 = 0x0000_0003;
+const ETHTOOL_GPERMADDR: u32 = 0x0000_0020;
+const MAX_ADDR_LEN: usize = 32;
+
+/// Interface flags from `<linux/if.h>`, in the order `ip link` prints them.
+const INTERFACE_FLAGS: [(u32, &str); 14] = [
+    (0x1, "up"),
+    (0x2, "broadcast"),
+    (0x4, "debug"),
+    (0x8, "loopback"),
+    (0x10, "pointopoint"),
+    (0x40, "running"),
+    (0x80, "noarp"),
+    (0x100, "promisc"),
+    (0x200, "allmulti"),
+    (0x400, "master"),
+    (0x800, "slave"),
+    (0x1000, "multicast"),
+    (0x10000, "lower_up"),
+    (0x20000, "dormant"),
+];
+
+const IFF_LOOPBACK: u32 = 0x8;
+const IFF_TUN: u32 = 0x1;
+const IFF_TAP: u32 = 0x2;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InterfaceKind {
+    Physical,
+    Loopback,
+    Bridge,
+    Veth,
+    Bond,
+    Vlan,
+    Tun,
+    Tap,
+    Wireguard,
+    Macvlan,
+    #[default]
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InterfaceAddress {
+    pub address: IpAddr,
+    pub prefix_len: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub index: u32,
+    pub kind: InterfaceKind,
+    /// Current hardware address, `None` for interfaces without one (or an
+    /// all-zero one, like loopback).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub mac: Option<String>,
+    /// Burned-in address reported by the driver, which differs from `mac`
+    /// when the address has been changed by software.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub permanent_mac: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub mtu: Option<u32>,
+    pub flags: Vec<String>,
+    pub operstate: String,
+    pub ipv4: Vec<InterfaceAddress>,
+    pub ipv6: Vec<InterfaceAddress>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub driver: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub bus_info: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub speed_mbps: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub duplex: Option<String>,
+}
+
+impl NetworkInterface {
+    pub fn is_up(&self) -> bool {
+        self.flags.iter().any(|flag| flag == "up")
+    }
+}
+
+/// Returns all network interfaces ordered by interface index.
+pub fn get_network_interfaces() -> Result<Vec<NetworkInterface>> {
+    let sys_class_net = Path::new(SYS_CLASS_NET_PATH);
+
+    let mut interfaces: Vec<NetworkInterface> = datalink::interfaces()
+        .into_iter()
+        .map(|iface| {
+            let sys_path = sys_class_net.join(&iface.name);
+            let drvinfo = ethtool_driver_info(&iface.name).ok();
+            let driver = drvinfo
+                .as_ref()
+                .map(|info| info.driver.clone())
+                .filter(|driver| !driver.is_empty())
+                .or_else(|| sysfs_link_name(&sys_path.join("device/driver")));
+            let (ipv4, ipv6) = split_addresses(&iface.ips);
+
+            NetworkInterface {
+                kind: classify_interface(&sys_path, iface.flags, driver.as_deref()),
+                mac: iface
+                    .mac
+                    .map(|mac| mac.to_string())
+                    .filter(|mac| !is_zero_mac(mac)),
+                permanent_mac: ethtool_permanent_address(&iface.name).ok().flatten(),
+                mtu: read_sysfs_value(&sys_path.join("mtu")),
+                flags: flag_names(iface.flags),
+                operstate: read_sysfs_string(&sys_path.join("operstate")).unwrap_or_default(),
+                ipv4,
+                ipv6,
+                driver,
+                bus_info: drvinfo
+                    .map(|info| info.bus_info)
+                    .filter(|bus| !bus.is_empty()),
+                speed_mbps: read_sysfs_value::<i64>(&sys_path.join("speed"))
+                    .filter(|speed| *speed > 0)
+                    .map(|speed| speed as u32),
+                duplex: read_sysfs_string(&sys_path.join("duplex"))
+                    .filter(|duplex| duplex != "unknown"),
+                name: iface.name,
+                index: iface.index,
+            }
+        })
+        .collect();
+
+    interfaces.sort_by_key(|iface| iface.index);
+    Ok(interfaces)
+}
+
+/// Bus a NIC is attached to, in order of preference for [`get_primary_mac`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum NicBus {
+    Pci,
+    /// Platform devices and other non-PCI buses, e.g. SoC Ethernet.
+    #[default]
+    Other,
+    /// Removable adapters, which must not displace a built-in NIC.
+    Usb,
+}
+
+/// The MAC address chosen to identify this machine.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PrimaryMac {
+    pub interface: String,
+    pub mac: String,
+    /// Whether `mac` is the burned-in address rather than the current one.
+    pub permanent: bool,
+    #[serde(default)]
+    pub bus: NicBus,
+    /// PCI address, USB port (`2-1.4`) or sysfs device path for other buses,
+    /// used for ordering within a bus.
+    pub bus_location: String,
+}
+
+/// Picks a hardware MAC address that stays the same across reboots,
+/// interface renames and containers coming and going.
+///
+/// Only interfaces backed by a device (`/sys/class/net/*/device`) are
+/// considered. The permanent address is preferred over the current one. PCI
+/// NICs rank before other buses and USB adapters come last, so plugging in a
+/// dongle does not change the result; within a bus the lowest location wins.
+pub fn get_primary_mac() -> Result<PrimaryMac> {
+    let entries = fs::read_dir(SYS_CLASS_NET_PATH)?;
+
+    let candidates = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let device = fs::canonicalize(entry.path().join("device")).ok()?;
+            let current = read_sysfs_string(&entry.path().join("address"));
+            let permanent = ethtool_permanent_address(&name).ok().flatten();
+
+            let (mac, permanent) = match (permanent, current) {
+                (Some(mac), _) => (mac, true),
+                (None, Some(mac)) if !is_zero_mac(&mac) => (mac, false),
+                _ => return None,
+            };
+            let (bus, bus_location) = bus_location(&device);
+            Some(PrimaryMac {
+                interface: name,
+                mac: mac.to_lowercase(),
+                permanent,
+                bus,
+                bus_location,
+            })
+        })
+        .collect();
+
+    select_primary_mac(candidates)
+        .ok_or_else(|| anyhow::anyhow!("No physical network interface with a MAC address"))
+}
+
+fn select_primary_mac(mut candidates: Vec<PrimaryMac>) -> Option<PrimaryMac> {
+    candidates.sort_by(|a, b| {
+        (a.bus, &a.bus_location, &a.mac, &a.interface).cmp(&(
+            b.bus,
+            &b.bus_location,
+            &b.mac,
+            &b.interface,
+        ))
+    });
+    candidates.into_iter().next()
+}
+
+/// Classifies a resolved sysfs device path and returns a location that is
+/// stable across interface renames: the USB port (`2-1.4`) for USB adapters,
+/// which sit below a PCI host controller, the deepest PCI address
+/// (`0000:00:1f.6`) for PCI devices, or the whole path for other buses.
+fn bus_location(device: &Path) -> (NicBus, String) {
+    if let Some(port) = device
+        .ancestors()
+        .find(|path| is_usb_device(path))
+        .and_then(|path| path.file_name())
+    {
+        return (NicBus::Usb, port.to_string_lossy().into_owned());
+    }
+
+    let components: Vec<&str> = device
+        .components()
+        .filter_map(|component| component.as_os_str().to_str())
+        .collect();
+    match components.iter().rev().find(|c| is_pci_address(c)) {
+        Some(address) => (NicBus::Pci, address.to_string()),
+        None => (NicBus::Other, device.to_string_lossy().into_owned()),
+    }
+}
+
+/// USB devices and their interfaces both link to the `usb` subsystem; only
+/// devices have a `devpath` attribute.
+fn is_usb_device(path: &Path) -> bool {
+    path.join("devpath").is_file()
+        && fs::read_link(path.join("subsystem"))
+            .is_ok_and(|subsystem| subsystem.file_name() == Some(OsStr::new("usb")))
+}
+
+fn is_pci_address(s: &str) -> bool {
+    // domain:bus:device.function, e.g. 0000:00:1f.6
+    let bytes = s.as_bytes();
+    bytes.len() == 12
+        && bytes[4] == b':'
+        && bytes[7] == b':'
+        && bytes[10] == b'.'
+        && bytes
+            .iter()
+            .enumerate()
+            .all(|(i, b)| matches!(i, 4 | 7 | 10) || b.is_ascii_hexdigit())
+}
+
+fn split_addresses(ips: &[IpNetwork]) -> (Vec<InterfaceAddress>, Vec<InterfaceAddress>) {
+    let mut ipv4 = Vec::new();
+    let mut ipv6 = Vec::new();
+    for ip in ips {
+        let address = InterfaceAddress {
+            address: ip.ip(),
+            prefix_len: ip.prefix(),
+        };
+        match ip {
+            IpNetwork::V4(_) => ipv4.push(address),
+            IpNetwork::V6(_) => ipv6.push(address),
+        }
+    }
+    (ipv4, ipv6)
+}
+
+fn flag_names(flags: u32) -> Vec<String> {
+    INTERFACE_FLAGS
+        .iter()
+        .filter(|(bit, _)| flags & bit != 0)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+pub(crate) fn is_zero_mac(mac: &str) -> bool {
+    mac.chars().all(|c| c == '0' || c == ':')
+}
+
+/// Infers the link type from sysfs, where each virtual driver leaves a
+/// distinctive attribute directory or `DEVTYPE` in `uevent`.
+fn classify_interface(sys_path: &Path, flags: u32, driver: Option<&str>) -> InterfaceKind {
+    if flags & IFF_LOOPBACK != 0 {
+        return InterfaceKind::Loopback;
+    }
+    if sys_path.join("bridge").exists() {
+        return InterfaceKind::Bridge;
+    }
+    if sys_path.join("bonding").exists() {
+        return InterfaceKind::Bond;
+    }
+    if let Some(tun_flags) = read_sysfs_string(&sys_path.join("tun_flags")) {
+        let tun_flags = u32::from_str_radix(tun_flags.trim_start_matches("0x"), 16).unwrap_or(0);
+        if tun_flags & IFF_TAP != 0 {
+            return InterfaceKind::Tap;
+        }
+        if tun_flags & IFF_TUN != 0 {
+            return InterfaceKind::Tun;
+        }
+    }
+
+    let devtype = read_sysfs_string(&sys_path.join("uevent")).and_then(|uevent| {
+        uevent
+            .lines()
+            .find_map(|line| line.strip_prefix("DEVTYPE="))
+            .map(str::to_string)
+    });
+    match devtype.as_deref() {
+        Some("vlan") => return InterfaceKind::Vlan,
+        Some("wireguard") => return InterfaceKind::Wireguard,
+        Some("macvlan") | Some("macvtap") => return InterfaceKind::Macvlan,
+        Some("bridge") => return InterfaceKind::Bridge,
+        Some("bond") => return InterfaceKind::Bond,
+        _ => {}
+    }
+
+    if sys_path.join("device").exists() {
+        return InterfaceKind::Physical;
+    }
+    // veth pairs set neither DEVTYPE nor a sysfs directory of their own.
+    if driver == Some("veth") {
+        return InterfaceKind::Veth;
+    }
+    InterfaceKind::Other
+}
+
+pub(crate) fn read_sysfs_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+pub(crate) fn read_sysfs_value<T: std::str::FromStr>(path: &Path) -> Option<T> {
+    read_sysfs_string(path)?.parse().ok()
+}
+
+/// Returns the last component of the path a sysfs symlink points to.
+fn sysfs_link_name(path: &Path) -> Option<String> {
+    fs::read_link(path)
+        .ok()?
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct EthtoolDriverInfo {
+    pub driver: String,
+    pub bus_info: String,
+}
+
+#[repr(C)]
+struct EthtoolDrvinfo {
+    cmd: u32,
+    driver: [u8; 32],
+    version: [u8; 32],
+    fw_version: [u8; 32],
+    bus_info: [u8; 32],
+    erom_version: [u8; 32],
+    reserved2: [u8; 12],
+    n_priv_flags: u32,
+    n_stats: u32,
+    testinfo_len: u32,
+    eedump_len: u32,
+    regdump_len: u32,
+}
+
+#[repr(C)]
+struct EthtoolPermAddr {
+    cmd: u32,
+    size: u32,
+    data: [u8; MAX_ADDR_LEN],
+}
+
+pub(crate) fn ethtool_driver_info(name: &str) -> Result<EthtoolDriverInfo> {
+    // SAFETY: the struct only holds integers and byte arrays.
+    let mut info: EthtoolDrvinfo = unsafe { std::mem::zeroed() };
+    info.cmd = ETHTOOL_GDRVINFO;
+    ethtool_ioctl(name, &mut info)?;
+
+    Ok(EthtoolDriverInfo {
+        driver: c_bytes_to_string(&info.driver),
+        bus_info: c_bytes_to_string(&info.bus_info),
+    })
+}
+
+/// Reads the permanent hardware address with `ETHTOOL_GPERMADDR`. Virtual
+/// devices report an empty or all-zero address, which yields `None`.
+pub(crate) fn ethtool_permanent_address(name: &str) -> Result<Option<String>> {
+    let mut perm = EthtoolPermAddr {
+        cmd: ETHTOOL_GPERMADDR,
+        size: MAX_ADDR_LEN as u32,
+        data: [0; MAX_ADDR_LEN],
+    };
+    ethtool_ioctl(name, &mut perm)?;
+
+    let len = (perm.size as usize).min(MAX_ADDR_LEN);
+    let bytes = &perm.data[..len];
+    if bytes.is_empty() || bytes.iter().all(|&b| b == 0) {
+        return Ok(None);
+    }
+    Ok(Some(
+        bytes
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(":"),
+    ))
+}
+
+fn ethtool_ioctl<T>(name: &str, data: &mut T) -> Result<()> {
+    if name.len() >= libc::IFNAMSIZ {
+        bail!("Interface name too long: {}", name);
+    }
+
+    let sock = socket(
+        AddressFamily::Inet,
+        SockType::Datagram,
+        SockFlag::SOCK_CLOEXEC,
+        None,
+    )?;
+
+    // SAFETY: ifreq is a plain C struct for which all-zero is a valid value.
+    let mut ifr: libc::ifreq = unsafe { std::mem::zeroed() };
+    for (dst, src) in ifr.ifr_name.iter_mut().zip(name.bytes()) {
+        *dst = src as libc::c_char;
+    }
+    ifr.ifr_ifru.ifru_data = data as *mut T as *mut libc::c_char;
+
+    // SAFETY: `ifr` and `data` outlive the call and `data` is the ethtool
+    // command structure matching its `cmd` field.
+    let ret = unsafe { libc::ioctl(sock.as_raw_fd(), SIOCETHTOOL as _, &mut ifr) };
+    if ret < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+fn c_bytes_to_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::test_util::TempDir;
+
+    #[test]
+    fn test_flag_names() {
+        assert_eq!(
+            flag_names(0x1 | 0x40 | 0x1000 | 0x10000),
+            vec!["up", "running", "multicast", "lower_up"]
+        );
+        assert!(flag_names(0).is_empty());
+    }
+
+    #[test]
+    fn test_is_zero_mac() {
+        assert!(is_zero_mac("00:00:00:00:00:00"));
+        assert!(!is_zero_mac("52:54:00:12:34:56"));
+    }
+
+    #[test]
+    fn test_classify_interface() {
+        let dir = TempDir::new("network_classify");
+
+        let bridge = dir.join("br0");
+        fs::create_dir_all(bridge.join("bridge")).unwrap();
+        assert_eq!(classify_interface(&bridge, 0, None), InterfaceKind::Bridge);
+
+        let tap = dir.join("tap0");
+        fs::create_dir_all(&tap).unwrap();
+        fs::write(tap.join("tun_flags"), "0x1002\n").unwrap();
+        assert_eq!(classify_interface(&tap, 0, None), InterfaceKind::Tap);
+
+        let vlan = dir.join("eth0.100");
+        fs::create_dir_all(&vlan).unwrap();
+        fs::write(vlan.join("uevent"), "DEVTYPE=vlan\nINTERFACE=eth0.100\n").unwrap();
+        assert_eq!(classify_interface(&vlan, 0, None), InterfaceKind::Vlan);
+
+        let wg = dir.join("wg0");
+        fs::create_dir_all(&wg).unwrap();
+        fs::write(wg.join("uevent"), "DEVTYPE=wireguard\n").unwrap();
+        assert_eq!(classify_interface(&wg, 0, None), InterfaceKind::Wireguard);
+
+        let eth = dir.join("eth0");
+        fs::create_dir_all(eth.join("device")).unwrap();
+        assert_eq!(classify_interface(&eth, 0, None), InterfaceKind::Physical);
+        assert_eq!(
+            classify_interface(&eth, IFF_LOOPBACK, None),
+            InterfaceKind::Loopback
+        );
+
+        let veth = dir.join("veth1234");
+        fs::create_dir_all(&veth).unwrap();
+        assert_eq!(classify_interface(&veth, 0, None), InterfaceKind::Other);
+        assert_eq!(
+            classify_interface(&veth, 0, Some("veth")),
+            InterfaceKind::Veth
+        );
+    }
+
+    #[test]
+    fn test_bus_location() {
+        let root = TempDir::new("network_bus");
+        let device = |path: &str, subsystem: &str, attribute: Option<&str>| {
+            let dir = root.join(path);
+            fs::create_dir_all(&dir).unwrap();
+            std::os::unix::fs::symlink(root.join("bus").join(subsystem), dir.join("subsystem"))
+                .unwrap();
+            if let Some(attribute) = attribute {
+                fs::write(dir.join(attribute), "1\n").unwrap();
+            }
+            dir
+        };
+        let location = |path: &Path| {
+            let (bus, location) = bus_location(path);
+            (bus, location.replace(&*root.to_string_lossy(), ""))
+        };
+
+        let nic = device("devices/pci0000:00/0000:00:1c.0/0000:03:00.0", "pci", None);
+        assert_eq!(location(&nic), (NicBus::Pci, "0000:03:00.0".to_string()));
+
+        let virtio = device("devices/pci0000:00/0000:00:04.0/virtio3", "virtio", None);
+        assert_eq!(location(&virtio), (NicBus::Pci, "0000:00:04.0".to_string()));
+
+        device(
+            "devices/pci0000:00/0000:00:14.0/usb2",
+            "usb",
+            Some("devpath"),
+        );
+        device(
+            "devices/pci0000:00/0000:00:14.0/usb2/2-1",
+            "usb",
+            Some("devpath"),
+        );
+        device(
+            "devices/pci0000:00/0000:00:14.0/usb2/2-1/2-1.4",
+            "usb",
+            Some("devpath"),
+        );
+        let dongle = device(
+            "devices/pci0000:00/0000:00:14.0/usb2/2-1/2-1.4/2-1.4:1.0",
+            "usb",
+            Some("bInterfaceNumber"),
+        );
+        assert_eq!(location(&dongle), (NicBus::Usb, "2-1.4".to_string()));
+
+        // An i2c address looks like a USB port but is not one.
+        device("devices/platform/i2c-0/0-0050", "i2c", None);
+        let i2c = device("devices/platform/i2c-0/0-0050/eth0", "platform", None);
+        assert_eq!(
+            location(&i2c),
+            (
+                NicBus::Other,
+                "/devices/platform/i2c-0/0-0050/eth0".to_string()
+            )
+        );
+
+        let platform = device("devices/platform/soc/ethernet", "platform", None);
+        assert_eq!(
+            location(&platform),
+            (NicBus::Other, "/devices/platform/soc/ethernet".to_string())
+        );
+    }
+
+    #[test]
+    fn test_select_primary_mac() {
+        let candidate = |interface: &str, mac: &str, bus: NicBus, bus_location: &str| PrimaryMac {
+            interface: interface.to_string(),
+            mac: mac.to_string(),
+            permanent: true,
+            bus,
+            bus_location: bus_location.to_string(),
+        };
+        let candidates = vec![
+            candidate("enp3s0", "aa:aa:aa:aa:aa:03", NicBus::Pci, "0000:03:00.0"),
+            candidate("eno1", "aa:aa:aa:aa:aa:01", NicBus::Pci, "0000:00:1f.6"),
+            candidate("wlp2s0", "aa:aa:aa:aa:aa:02", NicBus::Pci, "0000:02:00.0"),
+        ];
+        let primary = select_primary_mac(candidates.clone()).unwrap();
+        assert_eq!(primary.interface, "eno1");
+
+        // Renaming the interface must not change the choice.
+        let mut renamed = candidates.clone();
+        renamed.reverse();
+        renamed[2].interface = "eth7".to_string();
+        assert_eq!(
+            select_primary_mac(renamed).unwrap().mac,
+            "aa:aa:aa:aa:aa:01"
+        );
+        assert!(select_primary_mac(Vec::new()).is_none());
+
+        // A USB dongle below a low-numbered host controller and a platform
+        // NIC with a path that sorts before any PCI address must not win.
+        let mut with_dongle = candidates;
+        with_dongle.push(candidate(
+            "enx001122334455",
+            "00:11:22:33:44:55",
+            NicBus::Usb,
+            "1-1",
+        ));
+        with_dongle.push(candidate(
+            "end0",
+            "aa:aa:aa:aa:aa:00",
+            NicBus::Other,
+            "/sys/devices/platform/soc/ethernet",
+        ));
+        assert_eq!(select_primary_mac(with_dongle).unwrap().interface, "eno1");
+
+        let without_pci = vec![
+            candidate("enx001122334455", "00:11:22:33:44:55", NicBus::Usb, "1-1"),
+            candidate(
+                "end0",
+                "aa:aa:aa:aa:aa:00",
+                NicBus::Other,
+                "/sys/devices/platform/soc/ethernet",
+            ),
+        ];
+        assert_eq!(select_primary_mac(without_pci).unwrap().interface, "end0");
+    }
+
+    #[test]
+    fn test_get_primary_mac() {
+        if let Ok(primary) = get_primary_mac() {
+            assert!(!is_zero_mac(&primary.mac));
+            assert_eq!(get_primary_mac().unwrap(), primary);
+        }
+    }
+
+    #[test]
+    fn test_get_network_interfaces() -> Result<()> {
+        let interfaces = get_network_interfaces()?;
+        assert!(interfaces.windows(2).all(|w| w[0].index < w[1].index));
+        if let Some(lo) = interfaces.iter().find(|iface| iface.name == "lo") {
+            assert_eq!(lo.kind, InterfaceKind::Loopback);
+            assert_eq!(lo.mac, None);
+        }
+        Ok(())
+    }
+}
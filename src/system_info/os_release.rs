@@ -0,0 +1,361 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+const OS_RELEASE_PATHS: [&str; 2] = ["etc/os-release", "usr/lib/os-release"];
+const LSB_RELEASE_PATH: &str = "etc/lsb-release";
+const REDHAT_RELEASE_PATH: &str = "etc/redhat-release";
+const DEBIAN_VERSION_PATH: &str = "etc/debian_version";
+const ALPINE_RELEASE_PATH: &str = "etc/alpine-release";
+
+/// Operating system identification as described by `os-release(5)`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct OsRelease {
+    pub name: String,
+    pub id: String,
+    /// Closely related distributions, most related first.
+    pub id_like: Vec<String>,
+    pub pretty_name: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub version_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub version_codename: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub variant: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub variant_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub build_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub image_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub image_version: Option<String>,
+    /// End of support as an ISO 8601 date, e.g. `2029-05-31`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub support_end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cpe_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub home_url: Option<String>,
+    /// All other assignments, keyed by their original variable name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub other: BTreeMap<String, String>,
+    /// Path of the file the information was read from, e.g. `/etc/os-release`.
+    pub source: String,
+}
+
+impl OsRelease {
+    /// Parses the contents of an `os-release` file. Unset `NAME`, `ID` and
+    /// `PRETTY_NAME` take the defaults given by the specification.
+    pub fn parse(content: &str) -> Self {
+        let mut os_release = OsRelease::default();
+
+        for (key, value) in parse_assignments(content) {
+            match key.as_str() {
+                "NAME" => os_release.name = value,
+                "ID" => os_release.id = value,
+                "ID_LIKE" => {
+                    os_release.id_like = value.split_whitespace().map(str::to_string).collect()
+                }
+                "PRETTY_NAME" => os_release.pretty_name = value,
+                "VERSION" => os_release.version = Some(value),
+                "VERSION_ID" => os_release.version_id = Some(value),
+                "VERSION_CODENAME" => os_release.version_codename = Some(value),
+                "VARIANT" => os_release.variant = Some(value),
+                "VARIANT_ID" => os_release.variant_id = Some(value),
+                "BUILD_ID" => os_release.build_id = Some(value),
+                "IMAGE_ID" => os_release.image_id = Some(value),
+                "IMAGE_VERSION" => os_release.image_version = Some(value),
+                "SUPPORT_END" => os_release.support_end = Some(value),
+                "CPE_NAME" => os_release.cpe_name = Some(value),
+                "HOME_URL" => os_release.home_url = Some(value),
+                _ => {
+                    os_release.other.insert(key, value);
+                }
+            }
+        }
+
+        if os_release.name.is_empty() {
+            os_release.name = "Linux".to_string();
+        }
+        if os_release.id.is_empty() {
+            os_release.id = "linux".to_string();
+        }
+        if os_release.pretty_name.is_empty() {
+            os_release.pretty_name = "Linux".to_string();
+        }
+        os_release
+    }
+
+    /// Returns true if this is `id` or a distribution derived from it.
+    pub fn is_like(&self, id: &str) -> bool {
+        self.id == id || self.id_like.iter().any(|like| like == id)
+    }
+}
+
+/// Reads the OS identification below `root`, trying `os-release` first and
+/// then the legacy distribution-specific release files.
+///
+/// Returns the raw file contents together with the parsed fields, or `None`
+/// if no known file exists.
+pub fn read_os_release<P: AsRef<Path>>(root: P) -> Option<(String, OsRelease)> {
+    let root = root.as_ref();
+    let read = |path: &str| fs::read_to_string(root.join(path)).ok();
+
+    let (source, content, mut os_release) = if let Some((path, content)) = OS_RELEASE_PATHS
+        .iter()
+        .find_map(|path| read(path).map(|content| (*path, content)))
+    {
+        let os_release = OsRelease::parse(&content);
+        (path, content, os_release)
+    } else if let Some(content) = read(LSB_RELEASE_PATH) {
+        let os_release = parse_lsb_release(&content);
+        (LSB_RELEASE_PATH, content, os_release)
+    } else if let Some(content) = read(REDHAT_RELEASE_PATH) {
+        let os_release = parse_redhat_release(&content);
+        (REDHAT_RELEASE_PATH, content, os_release)
+    } else if let Some(content) = read(DEBIAN_VERSION_PATH) {
+        let os_release = parse_debian_version(&content);
+        (DEBIAN_VERSION_PATH, content, os_release)
+    } else if let Some(content) = read(ALPINE_RELEASE_PATH) {
+        let os_release = parse_alpine_release(&content);
+        (ALPINE_RELEASE_PATH, content, os_release)
+    } else {
+        return None;
+    };
+
+    os_release.source = format!("/{}", source);
+    Some((content, os_release))
+}
+
+/// Parses `KEY=value` lines with the shell quoting rules allowed by
+/// `os-release(5)`: single quotes, double quotes with `\"`, `\\`, `` \` ``
+/// and `\$` escapes, and backslash escapes in unquoted values.
+fn parse_assignments(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            let key = key.trim();
+            let valid_key =
+                !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            valid_key.then(|| (key.to_string(), unquote(value)))
+        })
+        .collect()
+}
+
+fn unquote(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    let mut quote: Option<char> = None;
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') => match chars.next() {
+                Some(escaped @ ('"' | '\\' | '`' | '$')) => result.push(escaped),
+                Some(other) => {
+                    result.push('\\');
+                    result.push(other);
+                }
+                None => result.push('\\'),
+            },
+            (None, '\\') => result.extend(chars.next()),
+            // An unquoted value ends at the first whitespace, and anything
+            // after it would be a separate shell word.
+            (None, c) if c.is_whitespace() => break,
+            (_, c) => result.push(c),
+        }
+    }
+    result
+}
+
+/// Parses `/etc/lsb-release` as written by Ubuntu and derivatives.
+fn parse_lsb_release(content: &str) -> OsRelease {
+    let mut os_release = OsRelease::default();
+    for (key, value) in parse_assignments(content) {
+        match key.as_str() {
+            "DISTRIB_ID" => {
+                os_release.id = value.to_lowercase();
+                os_release.name = value;
+            }
+            "DISTRIB_RELEASE" => os_release.version_id = Some(value),
+            "DISTRIB_CODENAME" => os_release.version_codename = Some(value),
+            "DISTRIB_DESCRIPTION" => os_release.pretty_name = value,
+            _ => {
+                os_release.other.insert(key, value);
+            }
+        }
+    }
+    if os_release.pretty_name.is_empty() {
+        os_release.pretty_name = os_release.name.clone();
+    }
+    os_release
+}
+
+/// Parses `/etc/redhat-release`, e.g. `CentOS Linux release 7.9.2009 (Core)`.
+fn parse_redhat_release(content: &str) -> OsRelease {
+    let line = content.lines().next().unwrap_or_default().trim();
+    let (name, rest) = line.split_once(" release ").unwrap_or((line, ""));
+    let (version, codename) = match rest.split_once('(') {
+        Some((version, codename)) => (
+            version.trim(),
+            Some(codename.trim_end_matches(')').trim().to_string()),
+        ),
+        None => (rest.trim(), None),
+    };
+
+    let (id, id_like): (&str, &[&str]) = if name.starts_with("Red Hat") {
+        ("rhel", &["fedora"])
+    } else if name.starts_with("CentOS") {
+        ("centos", &["rhel", "fedora"])
+    } else if name.starts_with("Rocky") {
+        ("rocky", &["rhel", "centos", "fedora"])
+    } else if name.starts_with("AlmaLinux") {
+        ("almalinux", &["rhel", "centos", "fedora"])
+    } else if name.starts_with("Fedora") {
+        ("fedora", &[])
+    } else {
+        ("", &["rhel", "fedora"])
+    };
+
+    OsRelease {
+        name: name.to_string(),
+        id: if id.is_empty() {
+            name.split_whitespace()
+                .next()
+                .unwrap_or("linux")
+                .to_lowercase()
+        } else {
+            id.to_string()
+        },
+        id_like: id_like.iter().map(|id| id.to_string()).collect(),
+        pretty_name: line.to_string(),
+        version: (!rest.is_empty()).then(|| rest.trim().to_string()),
+        version_id: (!version.is_empty()).then(|| version.to_string()),
+        version_codename: codename,
+        ..Default::default()
+    }
+}
+
+/// Parses `/etc/debian_version`, which holds either a point release such as
+/// `12.4` or, on testing and unstable, a codename such as `trixie/sid`.
+fn parse_debian_version(content: &str) -> OsRelease {
+    let version = content.trim();
+    let numeric = version.starts_with(|c: char| c.is_ascii_digit());
+
+    OsRelease {
+        name: "Debian GNU/Linux".to_string(),
+        id: "debian".to_string(),
+        pretty_name: format!("Debian GNU/Linux {}", version),
+        version_id: numeric.then(|| version.split('.').next().unwrap_or(version).to_string()),
+        version: numeric.then(|| version.to_string()),
+        version_codename: (!numeric)
+            .then(|| version.split('/').next().unwrap_or(version).to_string()),
+        ..Default::default()
+    }
+}
+
+/// Parses `/etc/alpine-release`, e.g. `3.19.1`.
+fn parse_alpine_release(content: &str) -> OsRelease {
+    let version = content.trim();
+    OsRelease {
+        name: "Alpine Linux".to_string(),
+        id: "alpine".to_string(),
+        pretty_name: format!("Alpine Linux v{}", version),
+        version_id: Some(version.to_string()),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::test_util::TempDir;
+
+    #[test]
+    fn test_parse_os_release() {
+        let content = r#"
+# Comment
+NAME="Ubuntu"
+ID=ubuntu
+ID_LIKE='debian linux'
+PRETTY_NAME="Ubuntu \"Jammy\" 22.04 \$LTS \\o/"
+VERSION_ID="22.04"
+VERSION_CODENAME=jammy
+BUILD_ID=rolling\ build
+SUPPORT_END=2027-04-01
+UBUNTU_CODENAME=jammy
+invalid line
+"#;
+        let os_release = OsRelease::parse(content);
+        assert_eq!(os_release.name, "Ubuntu");
+        assert_eq!(os_release.id, "ubuntu");
+        assert_eq!(os_release.id_like, vec!["debian", "linux"]);
+        assert_eq!(os_release.pretty_name, r#"Ubuntu "Jammy" 22.04 $LTS \o/"#);
+        assert_eq!(os_release.version_id.as_deref(), Some("22.04"));
+        assert_eq!(os_release.version_codename.as_deref(), Some("jammy"));
+        assert_eq!(os_release.build_id.as_deref(), Some("rolling build"));
+        assert_eq!(os_release.support_end.as_deref(), Some("2027-04-01"));
+        assert_eq!(os_release.other.get("UBUNTU_CODENAME").unwrap(), "jammy");
+        assert!(os_release.is_like("debian"));
+        assert!(!os_release.is_like("rhel"));
+
+        let empty = OsRelease::parse("");
+        assert_eq!((empty.name.as_str(), empty.id.as_str()), ("Linux", "linux"));
+    }
+
+    #[test]
+    fn test_parse_legacy_release_files() {
+        let lsb = parse_lsb_release(
+            "DISTRIB_ID=Ubuntu\nDISTRIB_RELEASE=20.04\nDISTRIB_CODENAME=focal\nDISTRIB_DESCRIPTION=\"Ubuntu 20.04.6 LTS\"\n",
+        );
+        assert_eq!(lsb.id, "ubuntu");
+        assert_eq!(lsb.version_id.as_deref(), Some("20.04"));
+        assert_eq!(lsb.pretty_name, "Ubuntu 20.04.6 LTS");
+
+        let centos = parse_redhat_release("CentOS Linux release 7.9.2009 (Core)\n");
+        assert_eq!(centos.id, "centos");
+        assert_eq!(centos.name, "CentOS Linux");
+        assert_eq!(centos.version_id.as_deref(), Some("7.9.2009"));
+        assert_eq!(centos.version_codename.as_deref(), Some("Core"));
+        assert!(centos.is_like("rhel"));
+
+        let debian = parse_debian_version("12.4\n");
+        assert_eq!(debian.version_id.as_deref(), Some("12"));
+        let sid = parse_debian_version("trixie/sid\n");
+        assert_eq!(sid.version_codename.as_deref(), Some("trixie"));
+        assert_eq!(sid.version_id, None);
+
+        let alpine = parse_alpine_release("3.19.1\n");
+        assert_eq!(alpine.id, "alpine");
+        assert_eq!(alpine.version_id.as_deref(), Some("3.19.1"));
+    }
+
+    #[test]
+    fn test_read_os_release_fallback() -> std::io::Result<()> {
+        let root = TempDir::new("os_release");
+        fs::create_dir_all(root.join("etc"))?;
+        fs::create_dir_all(root.join("usr/lib"))?;
+
+        assert_eq!(read_os_release(&root), None);
+
+        fs::write(root.join("etc/alpine-release"), "3.19.1\n")?;
+        let (raw, alpine) = read_os_release(&root).unwrap();
+        assert_eq!(raw, "3.19.1\n");
+        assert_eq!(alpine.source, "/etc/alpine-release");
+
+        fs::write(root.join("usr/lib/os-release"), "ID=fedora\nNAME=Fedora\n")?;
+        let (_, fedora) = read_os_release(&root).unwrap();
+
+        assert_eq!(fedora.id, "fedora");
+        assert_eq!(fedora.source, "/usr/lib/os-release");
+        Ok(())
+    }
+}
@@ -0,0 +1,533 @@
+use super::language_packages::{Ecosystem, LanguagePackage};
+use super::os_release::OsRelease;
+use super::packages::{Package, PackageManager};
+use super::versions::{compare_versions, VersionScheme};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// OSV ecosystem names of the distributions whose advisories are matched
+/// against OS packages, keyed by os-release `ID`.
+const DISTRO_ECOSYSTEMS: [(&str, &str); 6] = [
+    ("debian", "Debian"),
+    ("ubuntu", "Ubuntu"),
+    ("alpine", "Alpine"),
+    ("rhel", "Red Hat"),
+    ("rocky", "Rocky Linux"),
+    ("almalinux", "AlmaLinux"),
+];
+
+/// An installed package affected by an OSV advisory.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Vulnerability {
+    /// Advisory ID, e.g. `DSA-5678-1` or `GHSA-xxxx-xxxx-xxxx`.
+    pub id: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub summary: Option<String>,
+    /// Ecosystem as written in the advisory, e.g. `Debian:12` or `PyPI`.
+    pub ecosystem: String,
+    /// Installed package name.
+    pub package: String,
+    pub installed_version: String,
+    /// First version fixing the advisory, if one was released.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fixed_version: Option<String>,
+    #[serde(default)]
+    pub severity: Vec<Severity>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Severity {
+    /// Scoring system, e.g. `CVSS_V3`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub score: String,
+}
+
+/// The subset of the OSV schema needed for matching.
+#[derive(Debug, Clone, Deserialize, Default)]
+struct Advisory {
+    id: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    withdrawn: Option<String>,
+    #[serde(default)]
+    severity: Vec<Severity>,
+    #[serde(default)]
+    affected: Vec<Affected>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+struct Affected {
+    #[serde(default)]
+    package: AffectedPackage,
+    #[serde(default)]
+    ranges: Vec<Range>,
+    #[serde(default)]
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+struct AffectedPackage {
+    #[serde(default)]
+    ecosystem: String,
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+struct Range {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+struct Event {
+    introduced: Option<String>,
+    fixed: Option<String>,
+    last_affected: Option<String>,
+}
+
+/// A local copy of OSV advisories, e.g. an extracted
+/// `https://osv-vulnerabilities.storage.googleapis.com/<ecosystem>/all.zip`.
+#[derive(Debug, Clone, Default)]
+pub struct OsvDatabase {
+    advisories: Vec<Advisory>,
+    /// Advisory indices by (ecosystem without release, normalized package name).
+    index: HashMap<(String, String), Vec<(usize, usize)>>,
+    /// Files that could not be parsed as OSV JSON.
+    pub skipped: Vec<String>,
+}
+
+impl OsvDatabase {
+    /// Loads all `*.json` files below `dir`. Files that are not valid OSV
+    /// advisories are listed in `skipped` instead of failing the load.
+    /// Symlinked directories are not followed, so a link loop cannot make the
+    /// walk run forever.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let mut database = OsvDatabase::default();
+        let mut pending = vec![dir.as_ref().to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let entries =
+                fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                    pending.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "json") {
+                    match fs::read_to_string(&path)
+                        .ok()
+                        .and_then(|content| serde_json::from_str::<Advisory>(&content).ok())
+                    {
+                        Some(advisory) => database.add(advisory),
+                        None => database.skipped.push(path.display().to_string()),
+                    }
+                }
+            }
+        }
+        Ok(database)
+    }
+
+    fn add(&mut self, advisory: Advisory) {
+        if advisory.withdrawn.is_some() {
+            return;
+        }
+        let advisory_index = self.advisories.len();
+        for (affected_index, affected) in advisory.affected.iter().enumerate() {
+            let ecosystem = base_ecosystem(&affected.package.ecosystem).to_string();
+            let name = normalize_name(&ecosystem, &affected.package.name);
+            self.index
+                .entry((ecosystem, name))
+                .or_default()
+                .push((advisory_index, affected_index));
+        }
+        self.advisories.push(advisory);
+    }
+
+    /// Number of loaded (non-withdrawn) advisories.
+    pub fn len(&self) -> usize {
+        self.advisories.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.advisories.is_empty()
+    }
+
+    /// Returns the advisories affecting the given OS and language packages,
+    /// sorted by advisory ID and package name.
+    ///
+    /// OS packages are only matched against advisories for the distribution
+    /// and release in `os`. They are looked up by both binary and source
+    /// package name, as Debian, Ubuntu and Alpine publish advisories per
+    /// source package.
+    pub fn find_vulnerabilities(
+        &self,
+        os: &OsRelease,
+        packages: &[Package],
+        language_packages: &[LanguagePackage],
+    ) -> Vec<Vulnerability> {
+        let mut vulnerabilities = Vec::new();
+
+        if let Some((_, ecosystem)) = DISTRO_ECOSYSTEMS.iter().find(|(id, _)| *id == os.id) {
+            for package in packages {
+                let scheme = match package.manager {
+                    PackageManager::Dpkg => VersionScheme::Dpkg,
+                    PackageManager::Rpm => VersionScheme::Rpm,
+                    PackageManager::Apk => VersionScheme::Apk,
+                    PackageManager::Other => continue,
+                };
+                let mut names = vec![package.name.as_str()];
+                if let Some(source) = package.source.as_deref().filter(|s| *s != package.name) {
+                    names.push(source);
+                }
+                for name in names {
+                    self.match_package(
+                        ecosystem,
+                        name,
+                        &package.name,
+                        &package.version,
+                        scheme,
+                        Some(os),
+                        &mut vulnerabilities,
+                    );
+                }
+            }
+        }
+
+        for package in language_packages {
+            let (ecosystem, scheme) = match package.ecosystem {
+                Ecosystem::PyPI => ("PyPI", VersionScheme::Pep440),
+                Ecosystem::Npm => ("npm", VersionScheme::Semver),
+                Ecosystem::Cargo => ("crates.io", VersionScheme::Semver),
+                Ecosystem::Other => continue,
+            };
+            self.match_package(
+                ecosystem,
+                &package.name,
+                &package.name,
+                &package.version,
+                scheme,
+                None,
+                &mut vulnerabilities,
+            );
+        }
+
+        vulnerabilities.sort_by(|a, b| {
+            (&a.id, &a.package, &a.installed_version).cmp(&(
+                &b.id,
+                &b.package,
+                &b.installed_version,
+            ))
+        });
+        vulnerabilities.dedup_by(|a, b| {
+            a.id == b.id && a.package == b.package && a.installed_version == b.installed_version
+        });
+        vulnerabilities
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn match_package(
+        &self,
+        ecosystem: &str,
+        advisory_name: &str,
+        package_name: &str,
+        version: &str,
+        scheme: VersionScheme,
+        os: Option<&OsRelease>,
+        vulnerabilities: &mut Vec<Vulnerability>,
+    ) {
+        let key = (
+            ecosystem.to_string(),
+            normalize_name(ecosystem, advisory_name),
+        );
+        for &(advisory_index, affected_index) in self.index.get(&key).into_iter().flatten() {
+            let advisory = &self.advisories[advisory_index];
+            let affected = &advisory.affected[affected_index];
+            if let Some(os) = os {
+                if !release_matches(&affected.package.ecosystem, os) {
+                    continue;
+                }
+            }
+            if let Some(fixed_version) = affected_by(affected, version, scheme) {
+                vulnerabilities.push(Vulnerability {
+                    id: advisory.id.clone(),
+                    aliases: advisory.aliases.clone(),
+                    summary: advisory.summary.clone(),
+                    ecosystem: affected.package.ecosystem.clone(),
+                    package: package_name.to_string(),
+                    installed_version: version.to_string(),
+                    fixed_version,
+                    severity: advisory.severity.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// Strips the release from an OSV ecosystem, e.g. `Debian:12` -> `Debian`.
+fn base_ecosystem(ecosystem: &str) -> &str {
+    ecosystem.split(':').next().unwrap_or(ecosystem)
+}
+
+/// PyPI names are case-insensitive and treat runs of `-`, `_` and `.` alike.
+fn normalize_name(ecosystem: &str, name: &str) -> String {
+    if ecosystem != "PyPI" {
+        return name.to_string();
+    }
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.to_lowercase().chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !normalized.ends_with('-') {
+                normalized.push('-');
+            }
+        } else {
+            normalized.push(c);
+        }
+    }
+    normalized
+}
+
+/// Checks the release in an OSV ecosystem such as `Debian:12`,
+/// `Alpine:v3.19`, `Ubuntu:22.04:LTS` or `Red Hat:enterprise_linux:9::appstream`
+/// against the os-release `VERSION_ID`. Ecosystems without a release apply
+/// to all releases.
+fn release_matches(ecosystem: &str, os: &OsRelease) -> bool {
+    let release = ecosystem
+        .split(':')
+        .skip(1)
+        .map(|part| part.trim_start_matches('v'))
+        .find(|part| part.starts_with(|c: char| c.is_ascii_digit()));
+    let Some(release) = release else {
+        return true;
+    };
+    let Some(version_id) = os.version_id.as_deref() else {
+        return false;
+    };
+    version_id == release
+        || version_id
+            .strip_prefix(release)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Evaluates the ranges and explicit versions of an affected entry. Returns
+/// `None` if `version` is not affected, otherwise the fixed version if known.
+fn affected_by(
+    affected: &Affected,
+    version: &str,
+    scheme: VersionScheme,
+) -> Option<Option<String>> {
+    if affected.versions.iter().any(|v| v == version) {
+        let fixed = affected
+            .ranges
+            .iter()
+            .find_map(|range| range_affects(range, version, scheme).flatten());
+        return Some(fixed);
+    }
+    affected
+        .ranges
+        .iter()
+        .find_map(|range| range_affects(range, version, scheme))
+}
+
+/// Applies the OSV range evaluation algorithm to the sorted events.
+fn range_affects(range: &Range, version: &str, scheme: VersionScheme) -> Option<Option<String>> {
+    let scheme = match range.kind.as_str() {
+        "ECOSYSTEM" => scheme,
+        "SEMVER" => VersionScheme::Semver,
+        // GIT ranges need commit history, which is not available offline.
+        _ => return None,
+    };
+    let compare = |a: &str, b: &str| match (a, b) {
+        ("0", "0") => Ordering::Equal,
+        ("0", _) => Ordering::Less,
+        (_, "0") => Ordering::Greater,
+        _ => compare_versions(scheme, a, b),
+    };
+
+    let mut events: Vec<&Event> = range.events.iter().collect();
+    events.sort_by(|a, b| compare(event_version(a), event_version(b)));
+
+    let mut affected = false;
+    for event in events {
+        if let Some(introduced) = &event.introduced {
+            if compare(version, introduced) != Ordering::Less {
+                affected = true;
+            }
+        } else if let Some(fixed) = &event.fixed {
+            if compare(version, fixed) != Ordering::Less {
+                affected = false;
+            } else if affected {
+                return Some(Some(fixed.clone()));
+            }
+        } else if let Some(last_affected) = &event.last_affected {
+            if compare(version, last_affected) == Ordering::Greater {
+                affected = false;
+            }
+        }
+    }
+    affected.then_some(None)
+}
+
+fn event_version(event: &Event) -> &str {
+    event
+        .introduced
+        .as_deref()
+        .or(event.fixed.as_deref())
+        .or(event.last_affected.as_deref())
+        .unwrap_or("0")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::test_util::TempDir;
+
+    fn write_advisory(dir: &Path, name: &str, json: &str) -> Result<()> {
+        fs::write(dir.join(name), json)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_vulnerabilities() -> Result<()> {
+        let dir = TempDir::new("osv");
+        fs::create_dir_all(dir.join("debian"))?;
+
+        write_advisory(
+            &dir.join("debian"),
+            "DSA-5000-1.json",
+            r#"{"id": "DSA-5000-1", "aliases": ["CVE-2024-0001"], "summary": "glibc overflow",
+                "affected": [
+                  {"package": {"ecosystem": "Debian:12", "name": "glibc"},
+                   "ranges": [{"type": "ECOSYSTEM", "events": [{"introduced": "0"}, {"fixed": "2.36-9+deb12u4"}]}]},
+                  {"package": {"ecosystem": "Debian:11", "name": "glibc"},
+                   "ranges": [{"type": "ECOSYSTEM", "events": [{"introduced": "0"}, {"fixed": "2.31-13+deb11u8"}]}]}
+                ]}"#,
+        )?;
+        write_advisory(
+            &dir.join("debian"),
+            "DSA-5001-1.json",
+            r#"{"id": "DSA-5001-1",
+                "affected": [{"package": {"ecosystem": "Debian:12", "name": "openssl"},
+                  "ranges": [{"type": "ECOSYSTEM", "events": [{"introduced": "0"}, {"fixed": "3.0.11-1~deb12u2"}]}]}]}"#,
+        )?;
+        write_advisory(
+            &dir,
+            "GHSA-test.json",
+            r#"{"id": "GHSA-aaaa-bbbb-cccc", "severity": [{"type": "CVSS_V3", "score": "CVSS:3.1/AV:N"}],
+                "affected": [{"package": {"ecosystem": "PyPI", "name": "Requests"},
+                  "ranges": [{"type": "ECOSYSTEM", "events": [{"introduced": "2.3.0"}, {"fixed": "2.31.0"}]}]},
+                 {"package": {"ecosystem": "npm", "name": "left-pad"},
+                  "ranges": [{"type": "SEMVER", "events": [{"introduced": "0"}, {"last_affected": "1.2.0"}]}]}]}"#,
+        )?;
+        write_advisory(
+            &dir,
+            "withdrawn.json",
+            r#"{"id": "GHSA-gone", "withdrawn": "2024-01-01T00:00:00Z",
+                "affected": [{"package": {"ecosystem": "PyPI", "name": "requests"}, "versions": ["2.28.1"]}]}"#,
+        )?;
+        write_advisory(&dir, "broken.json", "{not json")?;
+        std::os::unix::fs::symlink(&*dir, dir.join("debian/loop"))?;
+
+        let database = OsvDatabase::load(&dir)?;
+        assert_eq!(database.len(), 3);
+        assert_eq!(database.skipped.len(), 1);
+
+        let os = OsRelease {
+            id: "debian".to_string(),
+            version_id: Some("12".to_string()),
+            ..Default::default()
+        };
+        let packages = vec![
+            Package {
+                name: "libc6".to_string(),
+                version: "2.36-9+deb12u3".to_string(),
+                source: Some("glibc".to_string()),
+                manager: PackageManager::Dpkg,
+                ..Default::default()
+            },
+            Package {
+                name: "openssl".to_string(),
+                version: "3.0.11-1~deb12u2".to_string(),
+                manager: PackageManager::Dpkg,
+                ..Default::default()
+            },
+        ];
+        let language_packages = vec![
+            LanguagePackage {
+                name: "requests".to_string(),
+                version: "2.28.1".to_string(),
+                ecosystem: Ecosystem::PyPI,
+                ..Default::default()
+            },
+            LanguagePackage {
+                name: "left-pad".to_string(),
+                version: "1.3.0".to_string(),
+                ecosystem: Ecosystem::Npm,
+                ..Default::default()
+            },
+        ];
+
+        let vulnerabilities = database.find_vulnerabilities(&os, &packages, &language_packages);
+        let found: Vec<(&str, &str, Option<&str>)> = vulnerabilities
+            .iter()
+            .map(|v| {
+                (
+                    v.id.as_str(),
+                    v.package.as_str(),
+                    v.fixed_version.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("DSA-5000-1", "libc6", Some("2.36-9+deb12u4")),
+                ("GHSA-aaaa-bbbb-cccc", "requests", Some("2.31.0")),
+            ]
+        );
+        assert_eq!(vulnerabilities[0].ecosystem, "Debian:12");
+        assert_eq!(vulnerabilities[0].aliases, vec!["CVE-2024-0001"]);
+        assert_eq!(vulnerabilities[1].severity[0].kind, "CVSS_V3");
+
+        // Bookworm package versions are past the Bullseye fix.
+        let bullseye = OsRelease {
+            version_id: Some("11".to_string()),
+            ..os
+        };
+        assert!(database
+            .find_vulnerabilities(&bullseye, &packages, &[])
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_matches() {
+        let os = |id: &str, version_id: &str| OsRelease {
+            id: id.to_string(),
+            version_id: Some(version_id.to_string()),
+            ..Default::default()
+        };
+        assert!(release_matches("Alpine:v3.19", &os("alpine", "3.19.1")));
+        assert!(!release_matches("Alpine:v3.1", &os("alpine", "3.19.1")));
+        assert!(release_matches("Ubuntu:22.04:LTS", &os("ubuntu", "22.04")));
+        assert!(release_matches(
+            "Red Hat:enterprise_linux:9::appstream",
+            &os("rhel", "9.3")
+        ));
+        assert!(release_matches("Rocky Linux", &os("rocky", "8.9")));
+        assert_eq!(
+            normalize_name("PyPI", "Zope.Interface__x"),
+            "zope-interface-x"
+        );
+    }
+}
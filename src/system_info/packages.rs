@@ -0,0 +1,551 @@
+use super::os_release::OsRelease;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const DPKG_STATUS_PATH: &str = "var/lib/dpkg/status";
+const APK_INSTALLED_PATH: &str = "lib/apk/db/installed";
+/// rpm database directories; `/usr/lib/sysimage/rpm` is used by openSUSE and
+/// Fedora 36+, with `/var/lib/rpm` often a symlink to it.
+const RPM_DB_DIRS: [&str; 2] = ["usr/lib/sysimage/rpm", "var/lib/rpm"];
+const RPM_SQLITE_DB: &str = "rpmdb.sqlite";
+/// Berkeley DB (`Packages`) and NDB (`Packages.db`) databases of older rpm
+/// releases, which are read through the `rpm` binary.
+const RPM_LEGACY_DBS: [&str; 2] = ["Packages", "Packages.db"];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageManager {
+    Dpkg,
+    Rpm,
+    Apk,
+    #[default]
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Package {
+    pub name: String,
+    /// Full version as the package manager compares it: `[epoch:]version[-revision]`
+    /// for dpkg, `[epoch:]version-release` for rpm and `version-rN` for apk.
+    pub version: String,
+    pub architecture: String,
+    /// Source package (dpkg `Source`, rpm source RPM name, apk origin).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub source: Option<String>,
+    /// Installed size in bytes.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub installed_size: Option<u64>,
+    pub manager: PackageManager,
+}
+
+impl PackageManager {
+    /// Returns the native package manager of a distribution.
+    pub fn for_os(os: &OsRelease) -> Self {
+        if os.is_like("debian") || os.is_like("ubuntu") {
+            PackageManager::Dpkg
+        } else if os.is_like("alpine") {
+            PackageManager::Apk
+        } else if [
+            "rhel", "fedora", "centos", "suse", "opensuse", "amzn", "mariner",
+        ]
+        .iter()
+        .any(|id| os.is_like(id))
+        {
+            PackageManager::Rpm
+        } else {
+            PackageManager::Other
+        }
+    }
+}
+
+/// Lists the packages installed on the running system, using the package
+/// manager of the distribution described by `os`.
+pub fn get_installed_packages(os: &OsRelease) -> Result<Vec<Package>> {
+    read_installed_packages("/", os)
+}
+
+/// Lists the packages installed in the system image below `root`. If the
+/// distribution is unknown, the first package database found is used.
+pub fn read_installed_packages<P: AsRef<Path>>(root: P, os: &OsRelease) -> Result<Vec<Package>> {
+    let root = root.as_ref();
+    let manager = match PackageManager::for_os(os) {
+        PackageManager::Other => detect_package_manager(root).unwrap_or_default(),
+        manager => manager,
+    };
+
+    let mut packages = match manager {
+        PackageManager::Dpkg => {
+            let path = root.join(DPKG_STATUS_PATH);
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            parse_dpkg_status(&content)
+        }
+        PackageManager::Apk => {
+            let path = root.join(APK_INSTALLED_PATH);
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            parse_apk_installed(&content)
+        }
+        PackageManager::Rpm => read_rpm_packages(root)?,
+        PackageManager::Other => bail!("No dpkg, rpm or apk package database found"),
+    };
+    packages.sort_by(|a, b| (&a.name, &a.architecture).cmp(&(&b.name, &b.architecture)));
+    Ok(packages)
+}
+
+fn detect_package_manager(root: &Path) -> Option<PackageManager> {
+    if root.join(DPKG_STATUS_PATH).exists() {
+        Some(PackageManager::Dpkg)
+    } else if root.join(APK_INSTALLED_PATH).exists() {
+        Some(PackageManager::Apk)
+    } else if rpm_db_dir(root).is_some() {
+        Some(PackageManager::Rpm)
+    } else {
+        None
+    }
+}
+
+/// Parses the deb822 paragraphs of `/var/lib/dpkg/status`, keeping only
+/// packages whose status is `installed`.
+fn parse_dpkg_status(content: &str) -> Vec<Package> {
+    content
+        .split("\n\n")
+        .filter_map(|paragraph| {
+            let field = |name: &str| {
+                paragraph.lines().find_map(|line| {
+                    line.strip_prefix(name)
+                        .and_then(|rest| rest.strip_prefix(':'))
+                        .map(str::trim)
+                })
+            };
+            if field("Status")?.split_whitespace().last() != Some("installed") {
+                return None;
+            }
+            let name = field("Package")?;
+            Some(Package {
+                name: name.to_string(),
+                version: field("Version")?.to_string(),
+                architecture: field("Architecture").unwrap_or_default().to_string(),
+                // "Source: glibc (2.36-9)" names the source version when it
+                // differs from the binary version.
+                source: Some(
+                    field("Source")
+                        .and_then(|source| source.split_whitespace().next())
+                        .unwrap_or(name)
+                        .to_string(),
+                ),
+                installed_size: field("Installed-Size")
+                    .and_then(|size| size.parse::<u64>().ok())
+                    .map(|kib| kib * 1024),
+                manager: PackageManager::Dpkg,
+            })
+        })
+        .collect()
+}
+
+/// Parses the `X:value` records of `/lib/apk/db/installed`.
+fn parse_apk_installed(content: &str) -> Vec<Package> {
+    content
+        .split("\n\n")
+        .filter_map(|record| {
+            let mut package = Package {
+                manager: PackageManager::Apk,
+                ..Default::default()
+            };
+            for line in record.lines() {
+                let Some((key, value)) = line.split_once(':') else {
+                    continue;
+                };
+                match key {
+                    "P" => package.name = value.to_string(),
+                    "V" => package.version = value.to_string(),
+                    "A" => package.architecture = value.to_string(),
+                    "o" => package.source = Some(value.to_string()),
+                    "I" => package.installed_size = value.parse().ok(),
+                    _ => {}
+                }
+            }
+            (!package.name.is_empty()).then_some(package)
+        })
+        .collect()
+}
+
+fn rpm_db_dir(root: &Path) -> Option<std::path::PathBuf> {
+    RPM_DB_DIRS.iter().map(|dir| root.join(dir)).find(|dir| {
+        std::iter::once(RPM_SQLITE_DB)
+            .chain(RPM_LEGACY_DBS)
+            .any(|db| dir.join(db).exists())
+    })
+}
+
+fn read_rpm_packages(root: &Path) -> Result<Vec<Package>> {
+    let dir = rpm_db_dir(root).context("No rpm database found")?;
+
+    #[cfg(feature = "rpm-sqlite")]
+    if dir.join(RPM_SQLITE_DB).exists() {
+        return read_rpm_sqlite(&dir.join(RPM_SQLITE_DB));
+    }
+
+    query_rpm_binary(root, &dir)
+}
+
+/// Reads the header blobs stored in the `Packages` table of `rpmdb.sqlite`.
+#[cfg(feature = "rpm-sqlite")]
+fn read_rpm_sqlite(path: &Path) -> Result<Vec<Package>> {
+    let connection =
+        rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut statement = connection.prepare("SELECT blob FROM Packages")?;
+    let blobs = statement.query_map([], |row| row.get::<_, Vec<u8>>(0))?;
+
+    let mut packages = Vec::new();
+    for blob in blobs {
+        let package = header::parse_rpm_header(&blob?)?;
+        // gpg-pubkey entries are imported signing keys, not packages.
+        if package.name != "gpg-pubkey" {
+            packages.push(package);
+        }
+    }
+    Ok(packages)
+}
+
+/// Queries legacy Berkeley DB and NDB databases through `rpm` itself, since
+/// their on-disk formats are not worth reimplementing.
+fn query_rpm_binary(root: &Path, dir: &Path) -> Result<Vec<Package>> {
+    let output = Command::new("rpm")
+        .arg("--root")
+        .arg(root)
+        .arg("--dbpath")
+        .arg(Path::new("/").join(dir.strip_prefix(root).unwrap_or(dir)))
+        .args([
+            "-qa",
+            "--queryformat",
+            "%{NAME}\\t%{EPOCH}\\t%{VERSION}\\t%{RELEASE}\\t%{ARCH}\\t%{SOURCERPM}\\t%{LONGSIZE}\\n",
+        ])
+        .output()
+        .context("Failed to run rpm")?;
+    if !output.status.success() {
+        bail!(
+            "rpm -qa failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(parse_rpm_query(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_rpm_query(output: &str) -> Vec<Package> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [name, epoch, version, release, arch, source_rpm, size] = fields[..] else {
+                return None;
+            };
+            let known = |value: &str| (value != "(none)").then(|| value.to_string());
+            Some(Package {
+                name: name.to_string(),
+                version: rpm_version(known(epoch), version, release),
+                architecture: known(arch).unwrap_or_default(),
+                source: known(source_rpm).map(|rpm| source_rpm_name(&rpm)),
+                installed_size: size.parse().ok(),
+                manager: PackageManager::Rpm,
+            })
+        })
+        .filter(|package| package.name != "gpg-pubkey")
+        .collect()
+}
+
+fn rpm_version(epoch: Option<String>, version: &str, release: &str) -> String {
+    match epoch {
+        Some(epoch) => format!("{}:{}-{}", epoch, version, release),
+        None => format!("{}-{}", version, release),
+    }
+}
+
+/// Turns `bash-5.1.8-6.el9.src.rpm` into `bash`.
+fn source_rpm_name(source_rpm: &str) -> String {
+    let stem = source_rpm
+        .strip_suffix(".src.rpm")
+        .or_else(|| source_rpm.strip_suffix(".nosrc.rpm"))
+        .unwrap_or(source_rpm);
+    let mut parts = stem.rsplitn(3, '-');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(_release), Some(_version), Some(name)) => name.to_string(),
+        _ => stem.to_string(),
+    }
+}
+
+/// Parsing of rpm header blobs as stored in `rpmdb.sqlite`.
+#[cfg_attr(not(feature = "rpm-sqlite"), allow(dead_code))]
+mod header {
+    use super::{rpm_version, source_rpm_name, Package, PackageManager};
+    use anyhow::{bail, Context, Result};
+
+    pub(super) const RPMTAG_NAME: u32 = 1000;
+    pub(super) const RPMTAG_VERSION: u32 = 1001;
+    pub(super) const RPMTAG_RELEASE: u32 = 1002;
+    pub(super) const RPMTAG_EPOCH: u32 = 1003;
+    pub(super) const RPMTAG_SIZE: u32 = 1009;
+    pub(super) const RPMTAG_ARCH: u32 = 1022;
+    pub(super) const RPMTAG_SOURCERPM: u32 = 1044;
+    pub(super) const RPMTAG_LONGSIZE: u32 = 5009;
+
+    pub(super) const RPM_INT32_TYPE: u32 = 4;
+    pub(super) const RPM_INT64_TYPE: u32 = 5;
+    pub(super) const RPM_STRING_TYPE: u32 = 6;
+    pub(super) const RPM_I18NSTRING_TYPE: u32 = 9;
+
+    /// Extracts the fields we need from an rpm header blob: a big-endian index
+    /// count and data length, followed by 16-byte index entries (tag, type,
+    /// offset, count) and the data store they point into.
+    pub(super) fn parse_rpm_header(blob: &[u8]) -> Result<Package> {
+        let read_u32 = |offset: usize| -> Option<u32> {
+            blob.get(offset..offset + 4)
+                .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()))
+        };
+        let index_count = read_u32(0).context("Truncated rpm header")? as usize;
+        let data_length = read_u32(4).context("Truncated rpm header")? as usize;
+        let data_start = 8 + index_count * 16;
+        let data = blob
+            .get(data_start..data_start + data_length)
+            .context("rpm header data store out of bounds")?;
+
+        let mut package = Package {
+            manager: PackageManager::Rpm,
+            ..Default::default()
+        };
+        let (mut version, mut release, mut epoch) = (String::new(), String::new(), None);
+
+        for entry in 0..index_count {
+            let base = 8 + entry * 16;
+            let (Some(tag), Some(kind), Some(offset)) =
+                (read_u32(base), read_u32(base + 4), read_u32(base + 8))
+            else {
+                bail!("Truncated rpm header index");
+            };
+            let offset = offset as usize;
+            let string = || -> Option<String> {
+                if kind != RPM_STRING_TYPE && kind != RPM_I18NSTRING_TYPE {
+                    return None;
+                }
+                let bytes = data.get(offset..)?;
+                let end = bytes.iter().position(|b| *b == 0)?;
+                Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+            };
+            let integer = || -> Option<u64> {
+                match kind {
+                    RPM_INT32_TYPE => data
+                        .get(offset..offset + 4)
+                        .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()) as u64),
+                    RPM_INT64_TYPE => data
+                        .get(offset..offset + 8)
+                        .map(|bytes| u64::from_be_bytes(bytes.try_into().unwrap())),
+                    _ => None,
+                }
+            };
+
+            match tag {
+                RPMTAG_NAME => package.name = string().unwrap_or_default(),
+                RPMTAG_VERSION => version = string().unwrap_or_default(),
+                RPMTAG_RELEASE => release = string().unwrap_or_default(),
+                RPMTAG_EPOCH => epoch = integer().map(|epoch| epoch.to_string()),
+                RPMTAG_ARCH => package.architecture = string().unwrap_or_default(),
+                RPMTAG_SOURCERPM => package.source = string().map(|rpm| source_rpm_name(&rpm)),
+                RPMTAG_SIZE if package.installed_size.is_none() => {
+                    package.installed_size = integer()
+                }
+                RPMTAG_LONGSIZE => package.installed_size = integer(),
+                _ => {}
+            }
+        }
+
+        if package.name.is_empty() {
+            bail!("rpm header without a name");
+        }
+        package.version = rpm_version(epoch, &version, &release);
+        Ok(package)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::header::*;
+    use super::*;
+    use crate::system_info::test_util::TempDir;
+
+    /// Builds an rpm header blob from (tag, type, value) entries.
+    fn rpm_header(entries: &[(u32, u32, &[u8])]) -> Vec<u8> {
+        let mut index = Vec::new();
+        let mut data = Vec::new();
+        for (tag, kind, value) in entries {
+            if *kind == RPM_INT32_TYPE {
+                while data.len() % 4 != 0 {
+                    data.push(0);
+                }
+            }
+            index.extend_from_slice(&tag.to_be_bytes());
+            index.extend_from_slice(&kind.to_be_bytes());
+            index.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            index.extend_from_slice(&1u32.to_be_bytes());
+            data.extend_from_slice(value);
+        }
+        let mut blob = Vec::new();
+        blob.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        blob.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        blob.extend(index);
+        blob.extend(data);
+        blob
+    }
+
+    fn bash_header() -> Vec<u8> {
+        rpm_header(&[
+            (RPMTAG_NAME, RPM_STRING_TYPE, b"bash\0"),
+            (RPMTAG_VERSION, RPM_STRING_TYPE, b"5.1.8\0"),
+            (RPMTAG_RELEASE, RPM_STRING_TYPE, b"6.el9\0"),
+            (RPMTAG_EPOCH, RPM_INT32_TYPE, &1u32.to_be_bytes()),
+            (RPMTAG_SIZE, RPM_INT32_TYPE, &7_738_634u32.to_be_bytes()),
+            (RPMTAG_ARCH, RPM_STRING_TYPE, b"x86_64\0"),
+            (
+                RPMTAG_SOURCERPM,
+                RPM_STRING_TYPE,
+                b"bash-5.1.8-6.el9.src.rpm\0",
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_parse_dpkg_status() {
+        let content = "\
+Package: libc6
+Status: install ok installed
+Installed-Size: 12965
+Architecture: amd64
+Source: glibc (2.36-9+deb12u4)
+Version: 2.36-9+deb12u4
+Description: GNU C Library: Shared libraries
+ Contains the standard libraries.
+
+Package: removed-pkg
+Status: deinstall ok config-files
+Architecture: amd64
+Version: 1.0
+
+Package: bash
+Status: install ok installed
+Installed-Size: 7164
+Architecture: amd64
+Version: 5.2.15-2+b2
+";
+        let packages = parse_dpkg_status(content);
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "libc6");
+        assert_eq!(packages[0].source.as_deref(), Some("glibc"));
+        assert_eq!(packages[0].installed_size, Some(12965 * 1024));
+        assert_eq!(packages[1].source.as_deref(), Some("bash"));
+        assert_eq!(packages[1].version, "5.2.15-2+b2");
+    }
+
+    #[test]
+    fn test_parse_apk_installed() {
+        let content = "\
+C:Q1abc=
+P:musl
+V:1.2.4_git20230717-r4
+A:x86_64
+S:407497
+I:651264
+o:musl
+m:Timo Teräs <timo.teras@iki.fi>
+
+P:busybox-binsh
+V:1.36.1-r15
+A:x86_64
+I:1
+o:busybox
+";
+        let packages = parse_apk_installed(content);
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].version, "1.2.4_git20230717-r4");
+        assert_eq!(packages[0].installed_size, Some(651264));
+        assert_eq!(packages[1].source.as_deref(), Some("busybox"));
+        assert_eq!(packages[1].manager, PackageManager::Apk);
+    }
+
+    #[test]
+    fn test_parse_rpm_header() -> Result<()> {
+        let package = parse_rpm_header(&bash_header())?;
+        assert_eq!(package.name, "bash");
+        assert_eq!(package.version, "1:5.1.8-6.el9");
+        assert_eq!(package.architecture, "x86_64");
+        assert_eq!(package.source.as_deref(), Some("bash"));
+        assert_eq!(package.installed_size, Some(7_738_634));
+
+        assert!(parse_rpm_header(&[0, 0, 0, 9]).is_err());
+        assert_eq!(
+            source_rpm_name("python3-dnf-4.14.0-9.el9.src.rpm"),
+            "python3-dnf"
+        );
+
+        let queried = parse_rpm_query(
+            "bash\t(none)\t5.1.8\t6.el9\tx86_64\tbash-5.1.8-6.el9.src.rpm\t7738634\ngpg-pubkey\t(none)\tfd431d51\t4ae0493b\t(none)\t(none)\t0\n",
+        );
+        assert_eq!(queried.len(), 1);
+        assert_eq!(queried[0].version, "5.1.8-6.el9");
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_installed_packages() -> Result<()> {
+        let root = TempDir::new("packages");
+        fs::create_dir_all(root.join("lib/apk/db"))?;
+        fs::write(
+            root.join(APK_INSTALLED_PATH),
+            "P:zlib\nV:1.3.1-r0\nA:x86_64\n\nP:alpine-base\nV:3.19.1-r0\nA:x86_64\n",
+        )?;
+
+        let alpine = OsRelease::parse("ID=alpine\n");
+        let packages = read_installed_packages(&root, &alpine)?;
+        let unknown = read_installed_packages(&root, &OsRelease::parse("ID=custom\n"))?;
+        let debian = read_installed_packages(&root, &OsRelease::parse("ID=debian\n"));
+
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "alpine-base");
+        assert_eq!(unknown, packages);
+        assert!(debian.is_err());
+
+        let empty = TempDir::new("packages_empty");
+        assert!(read_installed_packages(&empty, &OsRelease::parse("ID=custom\n")).is_err());
+        assert_eq!(PackageManager::for_os(&alpine), PackageManager::Apk);
+        assert_eq!(
+            PackageManager::for_os(&OsRelease::parse(
+                "ID=rocky\nID_LIKE=\"rhel centos fedora\"\n"
+            )),
+            PackageManager::Rpm
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "rpm-sqlite")]
+    #[test]
+    fn test_read_rpm_sqlite() -> Result<()> {
+        let root = TempDir::new("rpmdb");
+        let dir = root.join("var/lib/rpm");
+        fs::create_dir_all(&dir)?;
+        let connection = rusqlite::Connection::open(dir.join(RPM_SQLITE_DB))?;
+        connection.execute(
+            "CREATE TABLE Packages (hnum INTEGER PRIMARY KEY AUTOINCREMENT, blob BLOB NOT NULL)",
+            [],
+        )?;
+        connection.execute("INSERT INTO Packages (blob) VALUES (?1)", [bash_header()])?;
+        drop(connection);
+
+        let packages = read_installed_packages(&root, &OsRelease::parse("ID=rhel\n"))?;
+
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "bash");
+        Ok(())
+    }
+}
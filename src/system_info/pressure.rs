@@ -0,0 +1,457 @@
+use anyhow::{bail, Context, Result};
+use nix::errno::Errno;
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::os::fd::{AsFd, BorrowedFd};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const PRESSURE_PATH: &str = "/proc/pressure";
+
+/// Trigger windows the kernel accepts.
+const MIN_TRIGGER_WINDOW: Duration = Duration::from_millis(500);
+const MAX_TRIGGER_WINDOW: Duration = Duration::from_secs(10);
+
+/// A resource tracked by pressure stall information (PSI).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PressureResource {
+    Cpu,
+    Memory,
+    Io,
+    /// Available since Linux 6.1 with `CONFIG_IRQ_TIME_ACCOUNTING`.
+    Irq,
+}
+
+impl PressureResource {
+    pub const ALL: [PressureResource; 4] = [
+        PressureResource::Cpu,
+        PressureResource::Memory,
+        PressureResource::Io,
+        PressureResource::Irq,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            PressureResource::Cpu => "cpu",
+            PressureResource::Memory => "memory",
+            PressureResource::Io => "io",
+            PressureResource::Irq => "irq",
+        }
+    }
+
+    /// `/proc/pressure/<resource>`.
+    pub fn system_path(self) -> PathBuf {
+        Path::new(PRESSURE_PATH).join(self.name())
+    }
+
+    /// `<resource>.pressure` in a cgroup v2 directory.
+    pub fn cgroup_path<P: AsRef<Path>>(self, cgroup_dir: P) -> PathBuf {
+        cgroup_dir
+            .as_ref()
+            .join(format!("{}.pressure", self.name()))
+    }
+}
+
+/// Share of time in which tasks stalled on a resource.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct PressureStall {
+    /// Percentage of wall time over the last 10, 60 and 300 seconds.
+    pub avg10: f64,
+    pub avg60: f64,
+    pub avg300: f64,
+    /// Total stall time in microseconds.
+    pub total: u64,
+}
+
+/// Contents of one pressure file.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Pressure {
+    /// At least one task stalled. Not reported for `irq`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub some: Option<PressureStall>,
+    /// All non-idle tasks stalled at the same time. System-wide `cpu` reports
+    /// it since Linux 5.13.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub full: Option<PressureStall>,
+}
+
+/// Pressure of all resources, system-wide or for a cgroup. Resources the
+/// kernel does not track are `None`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct PressureInfo {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cpu: Option<Pressure>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub memory: Option<Pressure>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub io: Option<Pressure>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub irq: Option<Pressure>,
+}
+
+/// Reads system-wide pressure from `/proc/pressure`. Fails if the kernel was
+/// built without PSI or booted with `psi=0`.
+pub fn get_pressure() -> Result<PressureInfo> {
+    if !Path::new(PRESSURE_PATH).is_dir() {
+        bail!("{} not found, PSI is not enabled", PRESSURE_PATH);
+    }
+    Ok(read_pressure_info(PressureResource::system_path))
+}
+
+/// Reads the `*.pressure` files of a cgroup v2 directory, e.g.
+/// `/sys/fs/cgroup/system.slice/nginx.service`.
+pub fn read_cgroup_pressure<P: AsRef<Path>>(cgroup_dir: P) -> Result<PressureInfo> {
+    let cgroup_dir = cgroup_dir.as_ref();
+    let info = read_pressure_info(|resource| resource.cgroup_path(cgroup_dir));
+    if info == PressureInfo::default() {
+        bail!("No pressure files in {}", cgroup_dir.display());
+    }
+    Ok(info)
+}
+
+fn read_pressure_info(path_of: impl Fn(PressureResource) -> PathBuf) -> PressureInfo {
+    let read = |resource: PressureResource| read_pressure_file(path_of(resource)).ok();
+    PressureInfo {
+        cpu: read(PressureResource::Cpu),
+        memory: read(PressureResource::Memory),
+        io: read(PressureResource::Io),
+        irq: read(PressureResource::Irq),
+    }
+}
+
+pub fn read_pressure_file<P: AsRef<Path>>(path: P) -> Result<Pressure> {
+    let path = path.as_ref();
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_pressure(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Parses lines such as `some avg10=0.12 avg60=0.05 avg300=0.01 total=123456`.
+fn parse_pressure(content: &str) -> Result<Pressure> {
+    let mut pressure = Pressure::default();
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(kind) = fields.next() else {
+            continue;
+        };
+        let mut stall = PressureStall::default();
+        for field in fields {
+            let (key, value) = field
+                .split_once('=')
+                .with_context(|| format!("Invalid field {:?}", field))?;
+            let invalid = || format!("Invalid value in {:?}", field);
+            match key {
+                "avg10" => stall.avg10 = value.parse().with_context(invalid)?,
+                "avg60" => stall.avg60 = value.parse().with_context(invalid)?,
+                "avg300" => stall.avg300 = value.parse().with_context(invalid)?,
+                "total" => stall.total = value.parse().with_context(invalid)?,
+                _ => {}
+            }
+        }
+        match kind {
+            "some" => pressure.some = Some(stall),
+            "full" => pressure.full = Some(stall),
+            _ => bail!("Unknown pressure line {:?}", line),
+        }
+    }
+    if pressure.some.is_none() && pressure.full.is_none() {
+        bail!("No pressure lines");
+    }
+    Ok(pressure)
+}
+
+/// Which stall state a trigger watches.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StallKind {
+    Some,
+    Full,
+}
+
+/// A PSI trigger: the kernel signals the file descriptor whenever the stall
+/// time within `window` exceeds `threshold`.
+///
+/// Unprivileged users need `window` to be a multiple of 2 seconds. The fd
+/// can also be registered with an external event loop through [`AsFd`],
+/// waiting for `POLLPRI`.
+#[derive(Debug)]
+pub struct PressureTrigger {
+    file: File,
+    path: PathBuf,
+}
+
+impl PressureTrigger {
+    /// Registers a trigger on a system-wide pressure file.
+    pub fn system(
+        resource: PressureResource,
+        kind: StallKind,
+        threshold: Duration,
+        window: Duration,
+    ) -> Result<Self> {
+        Self::new(resource.system_path(), kind, threshold, window)
+    }
+
+    /// Registers a trigger on any pressure file, e.g. a cgroup's
+    /// `memory.pressure`.
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        kind: StallKind,
+        threshold: Duration,
+        window: Duration,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let config = trigger_config(kind, threshold, window)?;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(nix::libc::O_NONBLOCK)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        // The trigger must be written in a single write(), including the NUL.
+        file.write_all(config.as_bytes()).with_context(|| {
+            format!(
+                "Failed to register trigger {:?} on {}",
+                config,
+                path.display()
+            )
+        })?;
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Waits for the next event. Returns `false` on timeout; `None` waits
+    /// indefinitely. Fails if the trigger was destroyed, e.g. because the
+    /// cgroup was removed.
+    ///
+    /// `poll` is interrupted by every signal handler regardless of
+    /// `SA_RESTART`; it is resumed with the remaining time.
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<bool> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut fds = [PollFd::new(self.file.as_fd(), PollFlags::POLLPRI)];
+        let ready = loop {
+            let timeout = match deadline {
+                Some(deadline) => {
+                    PollTimeout::try_from(deadline.saturating_duration_since(Instant::now()))
+                        .unwrap_or(PollTimeout::MAX)
+                }
+                None => PollTimeout::NONE,
+            };
+            match poll(&mut fds, timeout) {
+                Err(Errno::EINTR) => continue,
+                result => {
+                    break result
+                        .with_context(|| format!("Failed to poll {}", self.path.display()))?
+                }
+            }
+        };
+        if ready == 0 {
+            return Ok(false);
+        }
+        let revents = fds[0].revents().unwrap_or(PollFlags::empty());
+        if revents.intersects(PollFlags::POLLERR | PollFlags::POLLNVAL) {
+            bail!("Pressure trigger on {} is gone", self.path.display());
+        }
+        Ok(revents.contains(PollFlags::POLLPRI))
+    }
+
+    /// Blocks and yields the time of each event, ending after the first
+    /// error.
+    pub fn events(&self) -> impl Iterator<Item = Result<Instant>> + '_ {
+        let mut failed = false;
+        std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            loop {
+                match self.wait(None) {
+                    Ok(true) => return Some(Ok(Instant::now())),
+                    Ok(false) => continue,
+                    Err(error) => {
+                        failed = true;
+                        return Some(Err(error));
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl AsFd for PressureTrigger {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+/// Formats a trigger as the kernel expects it, e.g. `some 150000 1000000`.
+fn trigger_config(kind: StallKind, threshold: Duration, window: Duration) -> Result<String> {
+    if window < MIN_TRIGGER_WINDOW || window > MAX_TRIGGER_WINDOW {
+        bail!(
+            "Trigger window must be between {:?} and {:?}, got {:?}",
+            MIN_TRIGGER_WINDOW,
+            MAX_TRIGGER_WINDOW,
+            window
+        );
+    }
+    if threshold.is_zero() || threshold > window {
+        bail!(
+            "Trigger threshold must be positive and at most the window, got {:?}",
+            threshold
+        );
+    }
+    let kind = match kind {
+        StallKind::Some => "some",
+        StallKind::Full => "full",
+    };
+    Ok(format!(
+        "{} {} {}\0",
+        kind,
+        threshold.as_micros(),
+        window.as_micros()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::test_util::TempDir;
+    use nix::sys::pthread::{pthread_kill, pthread_self};
+    use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+    use std::os::fd::OwnedFd;
+    use std::os::unix::net::UnixStream;
+
+    #[test]
+    fn test_parse_pressure() -> Result<()> {
+        let memory = parse_pressure(
+            "some avg10=7.32 avg60=3.96 avg300=3.22 total=95176568\n\
+             full avg10=0.00 avg60=0.00 avg300=0.00 total=0\n",
+        )?;
+        let some = memory.some.unwrap();
+        assert_eq!(some.avg10, 7.32);
+        assert_eq!(some.avg300, 3.22);
+        assert_eq!(some.total, 95176568);
+        assert_eq!(memory.full.unwrap().total, 0);
+
+        let irq = parse_pressure("full avg10=0.10 avg60=0.02 avg300=0.00 total=4242\n")?;
+        assert!(irq.some.is_none());
+        assert_eq!(irq.full.unwrap().avg10, 0.10);
+
+        assert!(parse_pressure("").is_err());
+        assert!(parse_pressure("some avg10=x\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_cgroup_pressure() -> Result<()> {
+        let dir = TempDir::new("pressure");
+        fs::write(
+            dir.join("cpu.pressure"),
+            "some avg10=1.00 avg60=0.50 avg300=0.10 total=1000\n\
+             full avg10=0.00 avg60=0.00 avg300=0.00 total=0\n",
+        )?;
+        fs::write(
+            dir.join("io.pressure"),
+            "some avg10=0.00 avg60=0.00 avg300=0.00 total=7\n\
+             full avg10=0.00 avg60=0.00 avg300=0.00 total=3\n",
+        )?;
+
+        let info = read_cgroup_pressure(&dir)?;
+        assert_eq!(info.cpu.unwrap().some.unwrap().avg10, 1.0);
+        assert_eq!(info.io.unwrap().full.unwrap().total, 3);
+        assert!(info.memory.is_none());
+        assert!(info.irq.is_none());
+        assert!(read_cgroup_pressure(dir.join("missing")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_trigger_config() -> Result<()> {
+        assert_eq!(
+            trigger_config(
+                StallKind::Some,
+                Duration::from_millis(150),
+                Duration::from_secs(1)
+            )?,
+            "some 150000 1000000\0"
+        );
+        assert!(trigger_config(StallKind::Full, Duration::ZERO, Duration::from_secs(1)).is_err());
+        assert!(trigger_config(
+            StallKind::Full,
+            Duration::from_secs(2),
+            Duration::from_secs(1)
+        )
+        .is_err());
+        assert!(trigger_config(
+            StallKind::Some,
+            Duration::from_millis(10),
+            Duration::from_millis(100)
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_retries_on_eintr() -> Result<()> {
+        extern "C" fn ignore(_: nix::libc::c_int) {}
+
+        // Without SA_RESTART, as in a program with its own SIGUSR1 handler.
+        let action = SigAction::new(
+            SigHandler::Handler(ignore),
+            SaFlags::empty(),
+            SigSet::empty(),
+        );
+        unsafe { sigaction(Signal::SIGUSR1, &action) }?;
+
+        // A socket never reports POLLPRI, so only the timeout ends the wait.
+        let (socket, _peer) = UnixStream::pair()?;
+        let trigger = PressureTrigger {
+            file: File::from(OwnedFd::from(socket)),
+            path: PathBuf::from("socket"),
+        };
+
+        let waiter = pthread_self();
+        let start = Instant::now();
+        let signaller = std::thread::spawn(move || {
+            for _ in 0..3 {
+                std::thread::sleep(Duration::from_millis(50));
+                let _ = pthread_kill(waiter, Signal::SIGUSR1);
+            }
+        });
+        let fired = trigger.wait(Some(Duration::from_millis(400)));
+        signaller.join().unwrap();
+
+        assert!(!fired?);
+        assert!(start.elapsed() >= Duration::from_millis(400));
+        Ok(())
+    }
+
+    #[test]
+    fn test_system_pressure_and_trigger() -> Result<()> {
+        // Skipped on kernels without PSI.
+        let Ok(info) = get_pressure() else {
+            return Ok(());
+        };
+        assert!(info.cpu.is_some_and(|cpu| cpu.some.is_some()));
+
+        // Registering needs write access to /proc/pressure, which containers
+        // may not grant.
+        if let Ok(trigger) = PressureTrigger::system(
+            PressureResource::Cpu,
+            StallKind::Some,
+            Duration::from_millis(500),
+            Duration::from_secs(2),
+        ) {
+            trigger.wait(Some(Duration::from_millis(10)))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,434 @@
+use anyhow::{Context, Result};
+use nix::unistd::{sysconf, SysconfVar};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+const PROC_PATH: &str = "/proc";
+const NAMESPACES: [&str; 8] = ["cgroup", "ipc", "mnt", "net", "pid", "time", "user", "uts"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Process {
+    pub pid: u32,
+    pub ppid: u32,
+    /// Command name (`comm`), at most 15 characters.
+    pub name: String,
+    /// Arguments from `/proc/[pid]/cmdline`; empty for kernel threads.
+    pub cmdline: Vec<String>,
+    /// Executable path. Requires the same user or `CAP_SYS_PTRACE`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub exe: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cwd: Option<String>,
+    /// State letter, e.g. `R` (running), `S` (sleeping) or `Z` (zombie).
+    pub state: String,
+    /// Real and effective user and group IDs.
+    pub uid: u32,
+    pub euid: u32,
+    pub gid: u32,
+    pub egid: u32,
+    /// Start time in seconds since the Unix epoch.
+    pub start_time: u64,
+    pub threads: u32,
+    /// Resident set size in bytes.
+    pub rss: u64,
+    /// Virtual memory size in bytes.
+    pub vsz: u64,
+    /// CPU time spent in user and kernel mode, in seconds.
+    pub user_time: f64,
+    pub system_time: f64,
+    pub nice: i32,
+    pub priority: i32,
+    /// Path in the cgroup v2 hierarchy, or in the v1 `name=systemd` hierarchy.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cgroup: Option<String>,
+    /// Namespace inode numbers by type (`mnt`, `net`, `pid`, ...). Only
+    /// readable for processes of the same user without privileges.
+    #[serde(default)]
+    pub namespaces: BTreeMap<String, u64>,
+}
+
+/// A process with its child processes.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ProcessNode {
+    pub process: Process,
+    pub children: Vec<ProcessNode>,
+}
+
+/// Kernel constants needed to convert `/proc/[pid]/stat` values.
+struct ProcContext {
+    boot_time: u64,
+    clock_ticks: u64,
+    page_size: u64,
+}
+
+impl ProcContext {
+    fn new(proc_root: &Path) -> Result<Self> {
+        let stat_path = proc_root.join("stat");
+        let stat = fs::read_to_string(&stat_path)
+            .with_context(|| format!("Failed to read {}", stat_path.display()))?;
+        Ok(Self {
+            boot_time: stat
+                .lines()
+                .find_map(|line| line.strip_prefix("btime "))
+                .and_then(|value| value.trim().parse().ok())
+                .with_context(|| format!("Failed to find btime in {}", stat_path.display()))?,
+            clock_ticks: sysconf(SysconfVar::CLK_TCK)
+                .ok()
+                .flatten()
+                .map_or(100, |ticks| ticks as u64),
+            page_size: sysconf(SysconfVar::PAGE_SIZE)
+                .ok()
+                .flatten()
+                .map_or(4096, |size| size as u64),
+        })
+    }
+}
+
+/// Lists all processes, sorted by PID.
+///
+/// Processes that exit while being read are skipped. Without privileges,
+/// `exe`, `cwd` and `namespaces` are only filled in for the caller's own
+/// processes.
+pub fn get_processes() -> Result<Vec<Process>> {
+    read_processes(Path::new(PROC_PATH))
+}
+
+/// Reads a single process. Fails if it does not exist (anymore).
+pub fn read_process(pid: u32) -> Result<Process> {
+    let proc_root = Path::new(PROC_PATH);
+    read_process_at(proc_root, pid, &ProcContext::new(proc_root)?)
+}
+
+fn read_processes(proc_root: &Path) -> Result<Vec<Process>> {
+    let context = ProcContext::new(proc_root)?;
+    let mut processes: Vec<Process> = fs::read_dir(proc_root)
+        .with_context(|| format!("Failed to read {}", proc_root.display()))?
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|pid| read_process_at(proc_root, pid, &context).ok())
+        .collect();
+    processes.sort_by_key(|process| process.pid);
+    Ok(processes)
+}
+
+fn read_process_at(proc_root: &Path, pid: u32, context: &ProcContext) -> Result<Process> {
+    let dir = proc_root.join(pid.to_string());
+    // `stat` is the only required file: once it is read the process existed,
+    // and anything that vanishes afterwards is left empty.
+    let stat = fs::read_to_string(dir.join("stat"))
+        .with_context(|| format!("Failed to read {}/stat", dir.display()))?;
+    let mut process = parse_stat(&stat, context)
+        .with_context(|| format!("Failed to parse {}/stat", dir.display()))?;
+    process.pid = pid;
+
+    if let Ok(status) = fs::read_to_string(dir.join("status")) {
+        apply_status(&status, &mut process);
+    }
+    process.cmdline = fs::read(dir.join("cmdline"))
+        .map(|data| parse_cmdline(&data))
+        .unwrap_or_default();
+    let read_link = |name: &str| {
+        fs::read_link(dir.join(name))
+            .ok()
+            .map(|path| path.display().to_string())
+    };
+    process.exe = read_link("exe");
+    process.cwd = read_link("cwd");
+    process.cgroup = fs::read_to_string(dir.join("cgroup"))
+        .ok()
+        .and_then(|content| parse_cgroup_path(&content));
+    process.namespaces = NAMESPACES
+        .iter()
+        .filter_map(|ns| {
+            let target = read_link(&format!("ns/{}", ns))?;
+            Some((ns.to_string(), parse_namespace_inode(&target)?))
+        })
+        .collect();
+    Ok(process)
+}
+
+/// Parses `/proc/[pid]/stat`. The command name is enclosed in parentheses
+/// and may itself contain spaces and parentheses.
+fn parse_stat(stat: &str, context: &ProcContext) -> Option<Process> {
+    let (head, rest) = stat.rsplit_once(')')?;
+    let (_, name) = head.split_once('(')?;
+    // Fields from the third (state) onwards, so field N is at index N - 3.
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3).copied();
+    let number = |n: usize| field(n)?.parse::<u64>().ok();
+    let ticks = context.clock_ticks as f64;
+
+    Some(Process {
+        name: name.to_string(),
+        state: field(3)?.to_string(),
+        ppid: field(4)?.parse().ok()?,
+        user_time: number(14)? as f64 / ticks,
+        system_time: number(15)? as f64 / ticks,
+        priority: field(18)?.parse().ok()?,
+        nice: field(19)?.parse().ok()?,
+        threads: field(20)?.parse().ok()?,
+        start_time: context.boot_time + number(22)? / context.clock_ticks,
+        vsz: number(23)?,
+        rss: number(24)? * context.page_size,
+        ..Default::default()
+    })
+}
+
+fn apply_status(status: &str, process: &mut Process) {
+    for line in status.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let ids: Vec<u32> = value
+            .split_whitespace()
+            .filter_map(|id| id.parse().ok())
+            .collect();
+        match (key, ids.as_slice()) {
+            ("Uid", [real, effective, ..]) => (process.uid, process.euid) = (*real, *effective),
+            ("Gid", [real, effective, ..]) => (process.gid, process.egid) = (*real, *effective),
+            _ => {}
+        }
+    }
+}
+
+/// Splits the NUL-terminated arguments, keeping empty ones such as the
+/// second argument of `prog "" x`. Trailing NULs are all dropped, like procps
+/// does, since processes that rewrite their title pad the old arguments
+/// with them.
+fn parse_cmdline(data: &[u8]) -> Vec<String> {
+    let end = data.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+    let data = &data[..end];
+    if data.is_empty() {
+        return Vec::new();
+    }
+    data.split(|b| *b == 0)
+        .map(|arg| String::from_utf8_lossy(arg).to_string())
+        .collect()
+}
+
+/// Picks the cgroup v2 path (`0::/path`) from `/proc/[pid]/cgroup`, falling
+/// back to the `name=systemd` hierarchy on cgroup v1.
+fn parse_cgroup_path(content: &str) -> Option<String> {
+    let entries: Vec<(&str, &str)> = content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ':');
+            let (_, controllers, path) = (parts.next()?, parts.next()?, parts.next()?);
+            Some((controllers, path))
+        })
+        .collect();
+    entries
+        .iter()
+        .find(|(controllers, _)| controllers.is_empty())
+        .or_else(|| {
+            entries
+                .iter()
+                .find(|(controllers, _)| *controllers == "name=systemd")
+        })
+        .map(|(_, path)| path.to_string())
+}
+
+/// Parses a namespace link target such as `net:[4026531840]`.
+fn parse_namespace_inode(target: &str) -> Option<u64> {
+    target.split_once(":[")?.1.strip_suffix(']')?.parse().ok()
+}
+
+/// Arranges processes into trees. Processes whose parent is not in the list,
+/// such as `init` and `kthreadd` (PPID 0), become roots. Children are sorted
+/// by PID.
+///
+/// A PID reused while `/proc` was being read can make parent links form a
+/// cycle that no root reaches; such a cycle is broken at its lowest PID, so
+/// every process appears exactly once.
+pub fn build_process_tree(processes: &[Process]) -> Vec<ProcessNode> {
+    let pids: HashSet<u32> = processes.iter().map(|process| process.pid).collect();
+    let mut children: HashMap<u32, Vec<&Process>> = HashMap::new();
+    let mut roots = Vec::new();
+    for process in processes {
+        if process.ppid != process.pid && pids.contains(&process.ppid) {
+            children.entry(process.ppid).or_default().push(process);
+        } else {
+            roots.push(process);
+        }
+    }
+
+    fn build(
+        process: &Process,
+        children: &HashMap<u32, Vec<&Process>>,
+        visited: &mut HashSet<u32>,
+    ) -> ProcessNode {
+        visited.insert(process.pid);
+        let mut nodes = Vec::new();
+        for child in children.get(&process.pid).into_iter().flatten() {
+            if !visited.contains(&child.pid) {
+                nodes.push(build(child, children, visited));
+            }
+        }
+        nodes.sort_by_key(|node| node.process.pid);
+        ProcessNode {
+            process: process.clone(),
+            children: nodes,
+        }
+    }
+
+    let mut visited = HashSet::new();
+    roots.sort_by_key(|process| process.pid);
+    let mut trees: Vec<ProcessNode> = roots
+        .into_iter()
+        .map(|process| build(process, &children, &mut visited))
+        .collect();
+
+    let mut unreachable: Vec<&Process> = processes
+        .iter()
+        .filter(|process| !visited.contains(&process.pid))
+        .collect();
+    unreachable.sort_by_key(|process| process.pid);
+    for process in unreachable {
+        if !visited.contains(&process.pid) {
+            trees.push(build(process, &children, &mut visited));
+        }
+    }
+    trees.sort_by_key(|node| node.process.pid);
+    trees
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::test_util::TempDir;
+
+    #[test]
+    fn test_read_processes_fixture() -> Result<()> {
+        let proc_root = TempDir::new("processes");
+        fs::create_dir_all(proc_root.join("1"))?;
+        fs::create_dir_all(proc_root.join("250"))?;
+        // A process that exited between listing and reading.
+        fs::create_dir_all(proc_root.join("999"))?;
+        fs::create_dir_all(proc_root.join("self"))?;
+        fs::write(proc_root.join("stat"), "cpu 1 2 3\nbtime 1700000000\n")?;
+
+        fs::write(
+            proc_root.join("1/stat"),
+            "1 (systemd) S 0 1 1 0 -1 4194560 1 2 3 4 500 250 0 0 20 0 1 0 200 \
+             171282432 3072 18446744073709551615 1 1 0 0 0 0 671173123 4096 1260 0 0 0 17 0 0 0 0 0 0\n",
+        )?;
+        fs::write(
+            proc_root.join("1/status"),
+            "Name:\tsystemd\nState:\tS (sleeping)\nUid:\t0\t0\t0\t0\nGid:\t0\t0\t0\t0\n",
+        )?;
+        fs::write(proc_root.join("1/cmdline"), b"/sbin/init\0splash\0")?;
+        fs::write(proc_root.join("1/cgroup"), "0::/init.scope\n")?;
+
+        fs::write(
+            proc_root.join("250/stat"),
+            "250 (tmux: server (1)) R 1 250 250 0 -1 0 0 0 0 0 100 0 0 0 39 19 3 0 1000 \
+             8192000 100 0\n",
+        )?;
+        fs::write(
+            proc_root.join("250/status"),
+            "Uid:\t1000\t1001\t1000\t1000\nGid:\t100\t100\t100\t100\n",
+        )?;
+        fs::write(
+            proc_root.join("250/cgroup"),
+            "12:cpu,cpuacct:/user.slice\n1:name=systemd:/user.slice/session-1.scope\n",
+        )?;
+
+        let processes = read_processes(&proc_root)?;
+        assert_eq!(processes.len(), 2);
+        let context = ProcContext::new(&proc_root)?;
+        let ticks = context.clock_ticks;
+
+        let init = &processes[0];
+        assert_eq!(init.pid, 1);
+        assert_eq!(init.ppid, 0);
+        assert_eq!(init.name, "systemd");
+        assert_eq!(init.cmdline, vec!["/sbin/init", "splash"]);
+        assert_eq!(init.state, "S");
+        assert_eq!(init.threads, 1);
+        assert_eq!(init.priority, 20);
+        assert_eq!(init.vsz, 171282432);
+        assert_eq!(init.rss, 3072 * context.page_size);
+        assert_eq!(init.user_time, 500.0 / ticks as f64);
+        assert_eq!(init.start_time, 1700000000 + 200 / ticks);
+        assert_eq!(init.cgroup.as_deref(), Some("/init.scope"));
+        assert_eq!(init.exe, None);
+
+        let tmux = &processes[1];
+        assert_eq!(tmux.name, "tmux: server (1)");
+        assert_eq!(tmux.state, "R");
+        assert_eq!(tmux.nice, 19);
+        assert_eq!((tmux.uid, tmux.euid, tmux.gid), (1000, 1001, 100));
+        assert!(tmux.cmdline.is_empty());
+        assert_eq!(tmux.cgroup.as_deref(), Some("/user.slice/session-1.scope"));
+
+        let tree = build_process_tree(&processes);
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].children[0].process.pid, 250);
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_processes() -> Result<()> {
+        let processes = get_processes()?;
+        let own = processes
+            .iter()
+            .find(|process| process.pid == std::process::id())
+            .expect("own process listed");
+        assert!(!own.cmdline.is_empty());
+        assert!(own.exe.is_some());
+        assert!(own.threads >= 1);
+        assert!(own.namespaces.contains_key("pid"));
+
+        fn count(nodes: &[ProcessNode]) -> usize {
+            nodes.iter().map(|node| 1 + count(&node.children)).sum()
+        }
+        assert_eq!(count(&build_process_tree(&processes)), processes.len());
+
+        assert!(read_process(u32::MAX).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_helpers() {
+        assert_eq!(parse_namespace_inode("net:[4026531840]"), Some(4026531840));
+        assert_eq!(parse_namespace_inode("garbage"), None);
+        assert_eq!(parse_cgroup_path("0::/\n"), Some("/".to_string()));
+        assert_eq!(parse_cgroup_path(""), None);
+        assert_eq!(parse_cmdline(b"prog\0\0x\0"), vec!["prog", "", "x"]);
+        assert_eq!(parse_cmdline(b"prog\0"), vec!["prog"]);
+        assert_eq!(
+            parse_cmdline(b"nginx: worker process\0\0\0\0"),
+            vec!["nginx: worker process"]
+        );
+        assert!(parse_cmdline(b"\0\0").is_empty());
+        assert_eq!(
+            parse_cmdline(b"sshd: alice [priv]"),
+            vec!["sshd: alice [priv]"]
+        );
+        assert!(parse_cmdline(b"").is_empty());
+    }
+
+    #[test]
+    fn test_build_process_tree_cycle() {
+        let process = |pid: u32, ppid: u32| Process {
+            pid,
+            ppid,
+            ..Default::default()
+        };
+        // 300 and 400 point at each other after PID reuse; 500 hangs off 400.
+        let processes = vec![
+            process(1, 0),
+            process(10, 1),
+            process(300, 400),
+            process(400, 300),
+            process(500, 400),
+        ];
+        let tree = build_process_tree(&processes);
+        let pids: Vec<u32> = tree.iter().map(|node| node.process.pid).collect();
+        assert_eq!(pids, vec![1, 300]);
+        assert_eq!(tree[1].children[0].process.pid, 400);
+        assert_eq!(tree[1].children[0].children[0].process.pid, 500);
+    }
+}
@@ -0,0 +1,549 @@
+use anyhow::{bail, Context, Result};
+use nix::sys::socket::{
+    bind, recv, send, socket, AddressFamily, MsgFlags, NetlinkAddr, SockFlag, SockProtocol,
+    SockType,
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+const ROUTE_PATH: &str = "/proc/net/route";
+const IPV6_ROUTE_PATH: &str = "/proc/net/ipv6_route";
+
+/// Routing table IDs reserved by the kernel, see `rt_tables(5)`.
+const RT_TABLE_DEFAULT: u32 = 253;
+const RT_TABLE_MAIN: u32 = 254;
+const RT_TABLE_LOCAL: u32 = 255;
+
+/// Route flags from `<linux/route.h>` and `<linux/ipv6_route.h>`.
+const ROUTE_FLAGS: [(u32, &str); 10] = [
+    (0x0001, "up"),
+    (0x0002, "gateway"),
+    (0x0004, "host"),
+    (0x0008, "reinstate"),
+    (0x0010, "dynamic"),
+    (0x0020, "modified"),
+    (0x0200, "reject"),
+    (0x0001_0000, "default"),
+    (0x0004_0000, "addrconf"),
+    (0x8000_0000, "local"),
+];
+
+// Netlink constants from `<linux/netlink.h>`, `<linux/rtnetlink.h>` and
+// `<linux/fib_rules.h>`.
+const NLMSG_HDRLEN: usize = 16;
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const NLM_F_REQUEST: u16 = 0x01;
+const NLM_F_DUMP: u16 = 0x300;
+const RTM_NEWRULE: u16 = 32;
+const RTM_GETRULE: u16 = 34;
+const FIB_RULE_HDRLEN: usize = 12;
+const FRA_DST: u16 = 1;
+const FRA_SRC: u16 = 2;
+const FRA_IIFNAME: u16 = 3;
+const FRA_GOTO: u16 = 4;
+const FRA_PRIORITY: u16 = 6;
+const FRA_FWMARK: u16 = 10;
+const FRA_TABLE: u16 = 15;
+const FRA_FWMASK: u16 = 16;
+const FRA_OIFNAME: u16 = 17;
+const FIB_RULE_INVERT: u32 = 0x02;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RoutingInfo {
+    pub routes: Vec<Route>,
+    /// Policy routing rules (`ip rule`), empty when netlink is unavailable.
+    pub rules: Vec<RoutingRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Route {
+    pub destination: IpAddr,
+    pub prefix_len: u8,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub gateway: Option<IpAddr>,
+    pub interface: String,
+    pub metric: u32,
+    /// Routing table ID; only known for IPv4, where `/proc/net/route`
+    /// shows the main table.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub table: Option<u32>,
+    pub flags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct RoutingRule {
+    /// `inet` or `inet6`.
+    pub family: String,
+    pub priority: u32,
+    pub action: String,
+    pub table: u32,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub iif: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub oif: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fwmark: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fwmask: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub goto: Option<u32>,
+    pub invert: bool,
+}
+
+impl Route {
+    pub fn is_default(&self) -> bool {
+        self.prefix_len == 0 && self.destination.is_unspecified()
+    }
+
+    /// Whether the route is up and not a reject (unreachable) route.
+    pub fn is_usable(&self) -> bool {
+        self.flags.iter().any(|flag| flag == "up")
+            && !self.flags.iter().any(|flag| flag == "reject")
+    }
+}
+
+impl RoutingInfo {
+    pub fn new() -> Result<Self> {
+        let mut routes = read_ipv4_routes(ROUTE_PATH)?;
+        routes.extend(read_ipv6_routes(IPV6_ROUTE_PATH).unwrap_or_default());
+
+        Ok(Self {
+            routes,
+            rules: get_routing_rules().unwrap_or_default(),
+        })
+    }
+
+    /// Usable default routes, lowest metric first.
+    pub fn default_routes(&self) -> Vec<&Route> {
+        let mut defaults: Vec<&Route> = self
+            .routes
+            .iter()
+            .filter(|route| route.is_default() && route.is_usable())
+            .collect();
+        defaults.sort_by_key(|route| route.metric);
+        defaults
+    }
+
+    /// Interface carrying the preferred IPv4 default route, falling back to
+    /// IPv6 on single-stack hosts.
+    pub fn default_interface(&self) -> Option<&str> {
+        let defaults = self.default_routes();
+        defaults
+            .iter()
+            .find(|route| route.destination.is_ipv4())
+            .or_else(|| defaults.first())
+            .map(|route| route.interface.as_str())
+    }
+}
+
+pub fn read_ipv4_routes<P: AsRef<Path>>(path: P) -> Result<Vec<Route>> {
+    let path = path.as_ref();
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    content
+        .lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_ipv4_route_line)
+        .collect()
+}
+
+/// Parses a line of `/proc/net/route`, whose addresses are 32-bit values
+/// printed in host byte order.
+fn parse_ipv4_route_line(line: &str) -> Result<Route> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 8 {
+        bail!("Truncated route line: {}", line);
+    }
+
+    let parse_addr = |hex: &str| -> Result<Ipv4Addr> {
+        let value = u32::from_str_radix(hex, 16)?;
+        Ok(Ipv4Addr::from(value.to_ne_bytes()))
+    };
+    let destination = parse_addr(fields[1])?;
+    let gateway = parse_addr(fields[2])?;
+    let flags = u32::from_str_radix(fields[3], 16)?;
+    let mask = parse_addr(fields[7])?;
+
+    Ok(Route {
+        destination: IpAddr::V4(destination),
+        prefix_len: u32::from(mask).count_ones() as u8,
+        gateway: (!gateway.is_unspecified()).then_some(IpAddr::V4(gateway)),
+        interface: fields[0].to_string(),
+        metric: fields[6].parse()?,
+        table: Some(RT_TABLE_MAIN),
+        flags: route_flag_names(flags),
+    })
+}
+
+pub fn read_ipv6_routes<P: AsRef<Path>>(path: P) -> Result<Vec<Route>> {
+    let path = path.as_ref();
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_ipv6_route_line)
+        .collect()
+}
+
+/// Parses a line of `/proc/net/ipv6_route`:
+/// destination, prefix, source, source prefix, next hop, metric, refcount,
+/// use count, flags and interface.
+fn parse_ipv6_route_line(line: &str) -> Result<Route> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 10 {
+        bail!("Truncated ipv6_route line: {}", line);
+    }
+
+    let parse_addr =
+        |hex: &str| -> Result<Ipv6Addr> { Ok(Ipv6Addr::from(u128::from_str_radix(hex, 16)?)) };
+    let gateway = parse_addr(fields[4])?;
+    let flags = u32::from_str_radix(fields[8], 16)?;
+
+    Ok(Route {
+        destination: IpAddr::V6(parse_addr(fields[0])?),
+        prefix_len: u8::from_str_radix(fields[1], 16)?,
+        gateway: (!gateway.is_unspecified()).then_some(IpAddr::V6(gateway)),
+        interface: fields[9].to_string(),
+        metric: u32::from_str_radix(fields[5], 16)?,
+        table: None,
+        flags: route_flag_names(flags),
+    })
+}
+
+fn route_flag_names(flags: u32) -> Vec<String> {
+    ROUTE_FLAGS
+        .iter()
+        .filter(|(bit, _)| flags & bit != 0)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// Dumps the policy routing rules of both address families over
+/// `NETLINK_ROUTE`, like `ip rule show` and `ip -6 rule show`.
+pub fn get_routing_rules() -> Result<Vec<RoutingRule>> {
+    let mut rules = dump_rules(libc_family(AddressFamily::Inet))?;
+    rules.extend(dump_rules(libc_family(AddressFamily::Inet6))?);
+    rules.sort_by(|a, b| (&a.family, a.priority).cmp(&(&b.family, b.priority)));
+    Ok(rules)
+}
+
+fn libc_family(family: AddressFamily) -> u8 {
+    family as i32 as u8
+}
+
+fn dump_rules(family: u8) -> Result<Vec<RoutingRule>> {
+    let sock = socket(
+        AddressFamily::Netlink,
+        SockType::Raw,
+        SockFlag::SOCK_CLOEXEC,
+        SockProtocol::NetlinkRoute,
+    )?;
+    bind(sock.as_raw_fd(), &NetlinkAddr::new(0, 0))?;
+
+    let mut request = Vec::with_capacity(NLMSG_HDRLEN + FIB_RULE_HDRLEN);
+    request.extend_from_slice(&((NLMSG_HDRLEN + FIB_RULE_HDRLEN) as u32).to_ne_bytes());
+    request.extend_from_slice(&RTM_GETRULE.to_ne_bytes());
+    request.extend_from_slice(&(NLM_F_REQUEST | NLM_F_DUMP).to_ne_bytes());
+    request.extend_from_slice(&1u32.to_ne_bytes()); // sequence number
+    request.extend_from_slice(&0u32.to_ne_bytes()); // port ID, 0 = kernel
+    request.push(family);
+    request.extend_from_slice(&[0; FIB_RULE_HDRLEN - 1]);
+    send(sock.as_raw_fd(), &request, MsgFlags::empty())?;
+
+    let mut rules = Vec::new();
+    let mut buffer = vec![0u8; 32 * 1024];
+    loop {
+        let len = recv(sock.as_raw_fd(), &mut buffer, MsgFlags::empty())?;
+        if len == 0 {
+            break;
+        }
+        if !parse_netlink_messages(&buffer[..len], &mut rules)? {
+            break;
+        }
+    }
+    Ok(rules)
+}
+
+/// Parses a batch of netlink messages, returning `false` once `NLMSG_DONE`
+/// has been seen.
+fn parse_netlink_messages(mut data: &[u8], rules: &mut Vec<RoutingRule>) -> Result<bool> {
+    while data.len() >= NLMSG_HDRLEN {
+        let msg_len = read_u32(data, 0) as usize;
+        let msg_type = read_u16(data, 4);
+        if msg_len < NLMSG_HDRLEN || msg_len > data.len() {
+            bail!("Malformed netlink message");
+        }
+
+        match msg_type {
+            NLMSG_DONE => return Ok(false),
+            NLMSG_ERROR => {
+                let errno = data
+                    .get(NLMSG_HDRLEN..NLMSG_HDRLEN + 4)
+                    .map(|bytes| i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .unwrap_or(0);
+                if errno != 0 {
+                    return Err(std::io::Error::from_raw_os_error(-errno).into());
+                }
+            }
+            RTM_NEWRULE => {
+                if let Some(rule) = parse_rule(&data[NLMSG_HDRLEN..msg_len]) {
+                    rules.push(rule);
+                }
+            }
+            _ => {}
+        }
+
+        data = &data[align4(msg_len).min(data.len())..];
+    }
+    Ok(true)
+}
+
+/// Parses a `struct fib_rule_hdr` followed by its attributes.
+fn parse_rule(payload: &[u8]) -> Option<RoutingRule> {
+    if payload.len() < FIB_RULE_HDRLEN {
+        return None;
+    }
+    let family = payload[0];
+    let dst_len = payload[1];
+    let src_len = payload[2];
+    let header_table = payload[4];
+    let action = payload[7];
+    let flags = read_u32(payload, 8);
+
+    let mut rule = RoutingRule {
+        family: if family == libc_family(AddressFamily::Inet6) {
+            "inet6".to_string()
+        } else {
+            "inet".to_string()
+        },
+        action: rule_action_name(action).to_string(),
+        table: u32::from(header_table),
+        invert: flags & FIB_RULE_INVERT != 0,
+        ..Default::default()
+    };
+
+    let mut attrs = &payload[FIB_RULE_HDRLEN..];
+    while attrs.len() >= 4 {
+        let attr_len = read_u16(attrs, 0) as usize;
+        let attr_type = read_u16(attrs, 2) & 0x3fff;
+        if attr_len < 4 || attr_len > attrs.len() {
+            break;
+        }
+        let value = &attrs[4..attr_len];
+
+        match attr_type {
+            FRA_SRC => rule.from = format_prefix(value, src_len),
+            FRA_DST => rule.to = format_prefix(value, dst_len),
+            FRA_IIFNAME => rule.iif = Some(c_string(value)),
+            FRA_OIFNAME => rule.oif = Some(c_string(value)),
+            FRA_PRIORITY if value.len() >= 4 => rule.priority = read_u32(value, 0),
+            FRA_TABLE if value.len() >= 4 => rule.table = read_u32(value, 0),
+            FRA_FWMARK if value.len() >= 4 => rule.fwmark = Some(read_u32(value, 0)),
+            FRA_FWMASK if value.len() >= 4 => rule.fwmask = Some(read_u32(value, 0)),
+            FRA_GOTO if value.len() >= 4 => rule.goto = Some(read_u32(value, 0)),
+            _ => {}
+        }
+
+        attrs = &attrs[align4(attr_len).min(attrs.len())..];
+    }
+    Some(rule)
+}
+
+fn rule_action_name(action: u8) -> &'static str {
+    match action {
+        1 => "lookup",
+        2 => "goto",
+        3 => "nop",
+        6 => "blackhole",
+        7 => "unreachable",
+        8 => "prohibit",
+        _ => "unknown",
+    }
+}
+
+/// Names of the reserved routing tables, as printed by `ip rule`.
+pub fn table_name(table: u32) -> Option<&'static str> {
+    match table {
+        RT_TABLE_DEFAULT => Some("default"),
+        RT_TABLE_MAIN => Some("main"),
+        RT_TABLE_LOCAL => Some("local"),
+        _ => None,
+    }
+}
+
+fn format_prefix(value: &[u8], prefix_len: u8) -> Option<String> {
+    let addr = match value.len() {
+        4 => IpAddr::V4(Ipv4Addr::new(value[0], value[1], value[2], value[3])),
+        16 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(value);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return None,
+    };
+    Some(format!("{}/{}", addr, prefix_len))
+}
+
+fn c_string(value: &[u8]) -> String {
+    let end = value.iter().position(|&b| b == 0).unwrap_or(value.len());
+    String::from_utf8_lossy(&value[..end]).into_owned()
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_ne_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn align4(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IPV4_ROUTES: &str = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+eth0\t00000000\t010200C0\t0003\t0\t0\t100\t00000000\t0\t0\t0
+eth0\t000200C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\t0\t0\t0
+wlan0\t00000000\t0100A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0
+";
+
+    const IPV6_ROUTES: &str = "\
+fd000000000000000000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001     eth0
+00000000000000000000000000000000 00 00000000000000000000000000000000 00 fd000000000000000000000000000001 00000400 00000001 00000000 00000003     eth0
+00000000000000000000000000000000 00 00000000000000000000000000000000 00 00000000000000000000000000000000 ffffffff 00000001 00000000 00200200       lo
+";
+
+    fn parse_fixture() -> RoutingInfo {
+        let mut routes: Vec<Route> = IPV4_ROUTES
+            .lines()
+            .skip(1)
+            .map(|line| parse_ipv4_route_line(line).unwrap())
+            .collect();
+        routes.extend(
+            IPV6_ROUTES
+                .lines()
+                .map(|line| parse_ipv6_route_line(line).unwrap()),
+        );
+        RoutingInfo {
+            routes,
+            rules: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_ipv4_route_line() {
+        let routing = parse_fixture();
+        let default = &routing.routes[0];
+        assert!(default.is_default());
+        assert_eq!(default.gateway, Some("192.0.2.1".parse().unwrap()));
+        assert_eq!(default.metric, 100);
+        assert_eq!(default.flags, vec!["up", "gateway"]);
+
+        let subnet = &routing.routes[1];
+        assert_eq!(subnet.destination, "192.0.2.0".parse::<IpAddr>().unwrap());
+        assert_eq!(subnet.prefix_len, 24);
+        assert_eq!(subnet.gateway, None);
+        assert!(!subnet.is_default());
+    }
+
+    #[test]
+    fn test_parse_ipv6_route_line() {
+        let routing = parse_fixture();
+        let subnet = &routing.routes[3];
+        assert_eq!(subnet.destination, "fd00::".parse::<IpAddr>().unwrap());
+        assert_eq!(subnet.prefix_len, 64);
+        assert_eq!(subnet.metric, 256);
+
+        let default = &routing.routes[4];
+        assert!(default.is_default());
+        assert_eq!(default.gateway, Some("fd00::1".parse().unwrap()));
+
+        let unreachable = &routing.routes[5];
+        assert!(unreachable.flags.contains(&"reject".to_string()));
+        assert!(!unreachable.is_usable());
+    }
+
+    #[test]
+    fn test_default_routes() {
+        let routing = parse_fixture();
+        let defaults: Vec<(&str, u32)> = routing
+            .default_routes()
+            .iter()
+            .map(|route| (route.interface.as_str(), route.metric))
+            .collect();
+        assert_eq!(
+            defaults,
+            vec![("eth0", 100), ("wlan0", 600), ("eth0", 1024)]
+        );
+        assert_eq!(routing.default_interface(), Some("eth0"));
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        let mut payload = vec![
+            libc_family(AddressFamily::Inet),
+            0,  // dst_len
+            24, // src_len
+            0,
+            100, // table
+            0,
+            0,
+            1, // FR_ACT_TO_TBL
+        ];
+        payload.extend_from_slice(&0u32.to_ne_bytes());
+        let mut push_attr = |kind: u16, value: &[u8]| {
+            payload.extend_from_slice(&((4 + value.len()) as u16).to_ne_bytes());
+            payload.extend_from_slice(&kind.to_ne_bytes());
+            payload.extend_from_slice(value);
+            payload.resize(align4(payload.len()), 0);
+        };
+        push_attr(FRA_PRIORITY, &1000u32.to_ne_bytes());
+        push_attr(FRA_SRC, &[10, 1, 2, 0]);
+        push_attr(FRA_IIFNAME, b"eth1\0");
+        push_attr(FRA_TABLE, &100u32.to_ne_bytes());
+
+        let rule = parse_rule(&payload).unwrap();
+        assert_eq!(rule.family, "inet");
+        assert_eq!(rule.priority, 1000);
+        assert_eq!(rule.action, "lookup");
+        assert_eq!(rule.table, 100);
+        assert_eq!(rule.from.as_deref(), Some("10.1.2.0/24"));
+        assert_eq!(rule.iif.as_deref(), Some("eth1"));
+        assert!(!rule.invert);
+    }
+
+    #[test]
+    fn test_routing_info_new() -> Result<()> {
+        let routing = RoutingInfo::new()?;
+        if let Ok(rules) = get_routing_rules() {
+            // Every kernel installs a lookup of the main table.
+            assert!(rules.is_empty() || rules.iter().any(|rule| rule.table == RT_TABLE_MAIN));
+        }
+        for route in routing.default_routes() {
+            assert!(route.is_default());
+        }
+        Ok(())
+    }
+}
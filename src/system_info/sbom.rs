@@ -0,0 +1,527 @@
+use super::language_packages::{Ecosystem, LanguagePackage};
+use super::os_release::OsRelease;
+use super::packages::{Package, PackageManager};
+use super::MachineInfo;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::fs::{self, File};
+use std::io::Read;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const RANDOM_UUID_PATH: &str = "/proc/sys/kernel/random/uuid";
+const URANDOM_PATH: &str = "/dev/urandom";
+const TOOL_NAME: &str = env!("CARGO_PKG_NAME");
+const TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// An entry of the SBOM, independent of the output format.
+struct Component {
+    name: String,
+    version: String,
+    purl: Option<String>,
+    supplier: Option<String>,
+}
+
+/// Builds a CycloneDX 1.5 JSON document with the operating system as the
+/// root component, the BIOS as a firmware component, and `packages` together
+/// with the language packages of the snapshot as libraries.
+pub fn to_cyclonedx(machine_info: &MachineInfo, packages: &[Package]) -> Result<Value> {
+    let os = &machine_info.software.os;
+    let firmware = firmware_component(machine_info);
+    let libraries = library_components(machine_info, packages);
+
+    let mut components = Vec::new();
+    if let Some(firmware) = &firmware {
+        let mut component = json!({
+            "type": "firmware",
+            "bom-ref": "firmware",
+            "name": firmware.name,
+            "version": firmware.version,
+        });
+        if let Some(supplier) = &firmware.supplier {
+            component["supplier"] = json!({ "name": supplier });
+        }
+        components.push(component);
+    }
+    for (index, library) in libraries.iter().enumerate() {
+        let mut component = json!({
+            "type": "library",
+            "bom-ref": format!("component-{}", index),
+            "name": library.name,
+            "version": library.version,
+        });
+        // The schema requires `purl` to be a string when present.
+        if let Some(purl) = &library.purl {
+            component["purl"] = json!(purl);
+        }
+        components.push(component);
+    }
+
+    let depends_on: Vec<String> = firmware
+        .iter()
+        .map(|_| "firmware".to_string())
+        .chain((0..libraries.len()).map(|index| format!("component-{}", index)))
+        .collect();
+
+    Ok(json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", random_uuid()?),
+        "version": 1,
+        "metadata": {
+            "timestamp": format_timestamp(SystemTime::now()),
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": TOOL_NAME,
+                    "version": TOOL_VERSION,
+                }],
+            },
+            "component": {
+                "type": "operating-system",
+                "bom-ref": "os",
+                "name": os.id,
+                "version": os_version(os),
+                "description": os.pretty_name,
+            },
+        },
+        "components": components,
+        "dependencies": [{ "ref": "os", "dependsOn": depends_on }],
+    }))
+}
+
+/// Builds an SPDX 2.3 JSON document describing the same components as
+/// [`to_cyclonedx`].
+pub fn to_spdx(machine_info: &MachineInfo, packages: &[Package]) -> Result<Value> {
+    let os = &machine_info.software.os;
+    let hostname = &machine_info.software.uname.nodename;
+
+    let mut spdx_packages = vec![json!({
+        "SPDXID": "SPDXRef-OperatingSystem",
+        "name": os.id,
+        "versionInfo": os_version(os),
+        "description": os.pretty_name,
+        "primaryPackagePurpose": "OPERATING-SYSTEM",
+        "downloadLocation": "NOASSERTION",
+        "filesAnalyzed": false,
+    })];
+    let mut relationships = vec![json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": "SPDXRef-OperatingSystem",
+    })];
+
+    if let Some(firmware) = firmware_component(machine_info) {
+        spdx_packages.push(json!({
+            "SPDXID": "SPDXRef-Firmware",
+            "name": firmware.name,
+            "versionInfo": firmware.version,
+            "supplier": firmware
+                .supplier
+                .map(|supplier| format!("Organization: {}", supplier))
+                .unwrap_or_else(|| "NOASSERTION".to_string()),
+            "primaryPackagePurpose": "FIRMWARE",
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+        }));
+        relationships.push(json!({
+            "spdxElementId": "SPDXRef-OperatingSystem",
+            "relationshipType": "DEPENDS_ON",
+            "relatedSpdxElement": "SPDXRef-Firmware",
+        }));
+    }
+
+    for (index, library) in library_components(machine_info, packages)
+        .into_iter()
+        .enumerate()
+    {
+        let id = format!("SPDXRef-Package-{}", index);
+        let external_refs: Vec<Value> = library
+            .purl
+            .iter()
+            .map(|purl| {
+                json!({
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": purl,
+                })
+            })
+            .collect();
+        spdx_packages.push(json!({
+            "SPDXID": id,
+            "name": library.name,
+            "versionInfo": library.version,
+            "primaryPackagePurpose": "LIBRARY",
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "externalRefs": external_refs,
+        }));
+        relationships.push(json!({
+            "spdxElementId": "SPDXRef-OperatingSystem",
+            "relationshipType": "CONTAINS",
+            "relatedSpdxElement": id,
+        }));
+    }
+
+    let uuid = random_uuid()?;
+    Ok(json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": if hostname.is_empty() { "host" } else { hostname.as_str() },
+        "documentNamespace": format!("https://spdx.org/spdxdocs/{}-{}", TOOL_NAME, uuid),
+        "creationInfo": {
+            "created": format_timestamp(SystemTime::now()),
+            "creators": [format!("Tool: {}-{}", TOOL_NAME, TOOL_VERSION)],
+        },
+        "packages": spdx_packages,
+        "relationships": relationships,
+    }))
+}
+
+/// Returns the package URL of an OS package, e.g.
+/// `pkg:deb/debian/libc6@2.36-9?arch=amd64&distro=debian-12`.
+pub fn package_purl(os: &OsRelease, package: &Package) -> String {
+    let namespace = match os.id.as_str() {
+        "rhel" => "redhat",
+        id => id,
+    };
+    let distro = match &os.version_id {
+        Some(version_id) => format!("{}-{}", os.id, version_id),
+        None => os.id.clone(),
+    };
+
+    let (kind, version, epoch) = match package.manager {
+        PackageManager::Dpkg => ("deb", package.version.as_str(), None),
+        // rpm puts the epoch in a qualifier rather than the version.
+        PackageManager::Rpm => match package.version.split_once(':') {
+            Some((epoch, version)) => ("rpm", version, Some(epoch)),
+            None => ("rpm", package.version.as_str(), None),
+        },
+        PackageManager::Apk => ("apk", package.version.as_str(), None),
+        PackageManager::Other => ("generic", package.version.as_str(), None),
+    };
+
+    let mut qualifiers = Vec::new();
+    if !package.architecture.is_empty() {
+        qualifiers.push(format!("arch={}", percent_encode(&package.architecture)));
+    }
+    if let Some(epoch) = epoch {
+        qualifiers.push(format!("epoch={}", epoch));
+    }
+    qualifiers.push(format!("distro={}", percent_encode(&distro)));
+
+    format!(
+        "pkg:{}/{}/{}@{}?{}",
+        kind,
+        percent_encode(namespace),
+        percent_encode(&package.name),
+        percent_encode(version),
+        qualifiers.join("&")
+    )
+}
+
+/// Returns the package URL of a language package, e.g. `pkg:npm/%40angular/cli@17.1.0`.
+pub fn language_purl(package: &LanguagePackage) -> Option<String> {
+    let (kind, name) = match package.ecosystem {
+        // PyPI names are case-insensitive and treat `_` like `-`.
+        Ecosystem::PyPI => ("pypi", package.name.to_lowercase().replace('_', "-")),
+        Ecosystem::Npm => ("npm", package.name.clone()),
+        Ecosystem::Cargo => ("cargo", package.name.clone()),
+        Ecosystem::Other => return None,
+    };
+    let name = name
+        .split('/')
+        .map(percent_encode)
+        .collect::<Vec<_>>()
+        .join("/");
+    Some(format!(
+        "pkg:{}/{}@{}",
+        kind,
+        name,
+        percent_encode(&package.version)
+    ))
+}
+
+fn firmware_component(machine_info: &MachineInfo) -> Option<Component> {
+    let bios = &machine_info.hardware.bios_info;
+    if bios.vendor.is_empty() && bios.bios_version.is_empty() {
+        return None;
+    }
+    Some(Component {
+        name: "bios".to_string(),
+        version: bios.bios_version.clone(),
+        purl: None,
+        supplier: (!bios.vendor.is_empty()).then(|| bios.vendor.clone()),
+    })
+}
+
+fn library_components(machine_info: &MachineInfo, packages: &[Package]) -> Vec<Component> {
+    let os = &machine_info.software.os;
+    let os_packages = packages.iter().map(|package| Component {
+        name: package.name.clone(),
+        version: package.version.clone(),
+        purl: Some(package_purl(os, package)),
+        supplier: None,
+    });
+    let language_packages = machine_info
+        .software
+        .language_packages
+        .iter()
+        .map(|package| Component {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            purl: language_purl(package),
+            supplier: None,
+        });
+    os_packages.chain(language_packages).collect()
+}
+
+fn os_version(os: &OsRelease) -> String {
+    os.version_id
+        .clone()
+        .or_else(|| os.build_id.clone())
+        .unwrap_or_default()
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986.
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Returns a random (version 4) UUID. Every document needs its own, so a
+/// fixed fallback is not an option.
+fn random_uuid() -> Result<String> {
+    if let Ok(uuid) = fs::read_to_string(RANDOM_UUID_PATH) {
+        return Ok(uuid.trim().to_string());
+    }
+
+    let mut bytes = [0u8; 16];
+    File::open(URANDOM_PATH)
+        .and_then(|mut file| file.read_exact(&mut bytes))
+        .with_context(|| format!("Failed to read {}", URANDOM_PATH))?;
+    Ok(format_uuid_v4(bytes))
+}
+
+fn format_uuid_v4(mut bytes: [u8; 16]) -> String {
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp, e.g. `2024-06-10T06:13:20Z`.
+fn format_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil-from-days conversion by Howard Hinnant.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::hardware::BiosInfo;
+    use std::time::Duration;
+
+    fn machine_info() -> Result<MachineInfo> {
+        let mut machine_info: MachineInfo = serde_json::from_value(json!({
+            "hardware": {
+                "cpu_is_virtual": false,
+                "disk_serial_number": "",
+                "mac_addresses": "",
+                "bios_info": BiosInfo::default(),
+                "system_info": {
+                    "manufacturer": "", "product_name": "", "serial_number": "", "uuid": ""
+                },
+                "enclosure_info": {
+                    "manufacturer": "", "enclosure_type": "", "version": "",
+                    "serial_number": "", "asset_tag_number": ""
+                },
+            },
+            "software": { "os_release": "", "uname": "" },
+            "version": "2.0",
+        }))?;
+        machine_info.hardware.bios_info = BiosInfo {
+            vendor: "SeaBIOS".to_string(),
+            bios_version: "1.16.3".to_string(),
+            ..Default::default()
+        };
+        machine_info.software.os = OsRelease::parse("ID=debian\nVERSION_ID=\"12\"\n");
+        machine_info.software.uname.nodename = "web01".to_string();
+        machine_info.software.language_packages = vec![LanguagePackage {
+            name: "@angular/cli".to_string(),
+            version: "17.1.0".to_string(),
+            ecosystem: Ecosystem::Npm,
+            path: "/usr/lib/node_modules/@angular/cli".to_string(),
+        }];
+        Ok(machine_info)
+    }
+
+    fn libc6() -> Package {
+        Package {
+            name: "libc6".to_string(),
+            version: "2.36-9+deb12u4".to_string(),
+            architecture: "amd64".to_string(),
+            manager: PackageManager::Dpkg,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_purls() -> Result<()> {
+        let debian = OsRelease::parse("ID=debian\nVERSION_ID=12\n");
+        assert_eq!(
+            package_purl(&debian, &libc6()),
+            "pkg:deb/debian/libc6@2.36-9%2Bdeb12u4?arch=amd64&distro=debian-12"
+        );
+
+        let rhel = OsRelease::parse("ID=rhel\nVERSION_ID=9.3\n");
+        let bash = Package {
+            name: "bash".to_string(),
+            version: "1:5.1.8-6.el9".to_string(),
+            architecture: "x86_64".to_string(),
+            manager: PackageManager::Rpm,
+            ..Default::default()
+        };
+        assert_eq!(
+            package_purl(&rhel, &bash),
+            "pkg:rpm/redhat/bash@5.1.8-6.el9?arch=x86_64&epoch=1&distro=rhel-9.3"
+        );
+
+        let python = LanguagePackage {
+            name: "Typing_Extensions".to_string(),
+            version: "4.9.0".to_string(),
+            ecosystem: Ecosystem::PyPI,
+            ..Default::default()
+        };
+        assert_eq!(
+            language_purl(&python).as_deref(),
+            Some("pkg:pypi/typing-extensions@4.9.0")
+        );
+        assert_eq!(
+            language_purl(&machine_info()?.software.language_packages[0]).as_deref(),
+            Some("pkg:npm/%40angular/cli@17.1.0")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_cyclonedx() -> Result<()> {
+        let mut machine_info = machine_info()?;
+        machine_info
+            .software
+            .language_packages
+            .push(LanguagePackage {
+                name: "vendored".to_string(),
+                version: "1.0".to_string(),
+                ecosystem: Ecosystem::Other,
+                path: "/opt/vendored".to_string(),
+            });
+        let bom = to_cyclonedx(&machine_info, &[libc6()])?;
+        assert_eq!(bom["specVersion"], "1.5");
+        assert_eq!(bom["metadata"]["component"]["type"], "operating-system");
+        assert_eq!(bom["metadata"]["component"]["name"], "debian");
+        assert_eq!(bom["metadata"]["component"]["version"], "12");
+
+        let components = bom["components"].as_array().unwrap();
+        assert_eq!(components.len(), 4);
+        assert_eq!(components[0]["type"], "firmware");
+        assert_eq!(components[0]["supplier"]["name"], "SeaBIOS");
+        assert_eq!(components[1]["name"], "libc6");
+        assert_eq!(components[2]["purl"], "pkg:npm/%40angular/cli@17.1.0");
+        // No `"purl": null` for packages without a package URL.
+        assert_eq!(components[3]["name"], "vendored");
+        assert!(components[3].get("purl").is_none());
+        assert_eq!(
+            bom["dependencies"][0]["dependsOn"]
+                .as_array()
+                .unwrap()
+                .len(),
+            4
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_spdx() -> Result<()> {
+        let document = to_spdx(&machine_info()?, &[libc6()])?;
+        assert_eq!(document["spdxVersion"], "SPDX-2.3");
+        assert_eq!(document["name"], "web01");
+
+        let packages = document["packages"].as_array().unwrap();
+        assert_eq!(packages.len(), 4);
+        assert_eq!(packages[0]["primaryPackagePurpose"], "OPERATING-SYSTEM");
+        assert_eq!(packages[1]["supplier"], "Organization: SeaBIOS");
+        assert_eq!(
+            packages[2]["externalRefs"][0]["referenceLocator"],
+            "pkg:deb/debian/libc6@2.36-9%2Bdeb12u4?arch=amd64&distro=debian-12"
+        );
+
+        let relationships = document["relationships"].as_array().unwrap();
+        assert_eq!(relationships[0]["relationshipType"], "DESCRIBES");
+        assert_eq!(relationships.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_random_uuid() -> Result<()> {
+        let uuid = random_uuid()?;
+        assert_eq!(uuid.len(), 36);
+        assert_ne!(uuid, random_uuid()?);
+
+        assert_eq!(
+            format_uuid_v4([0xff; 16]),
+            "ffffffff-ffff-4fff-bfff-ffffffffffff"
+        );
+        assert_eq!(
+            format_uuid_v4([0; 16]),
+            "00000000-0000-4000-8000-000000000000"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            format_timestamp(UNIX_EPOCH + Duration::from_secs(1_709_210_096)),
+            "2024-02-29T12:34:56Z"
+        );
+    }
+}
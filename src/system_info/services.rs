@@ -0,0 +1,584 @@
+#[cfg(feature = "systemd-dbus")]
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "systemd-dbus")]
+use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "systemd-dbus")]
+use zbus::blocking::Connection;
+#[cfg(feature = "systemd-dbus")]
+use zbus::zvariant::OwnedObjectPath;
+
+/// systemd unit search path relative to the root, highest precedence first.
+const UNIT_DIRS: [&str; 4] = [
+    "etc/systemd/system",
+    "run/systemd/system",
+    "usr/lib/systemd/system",
+    "lib/systemd/system",
+];
+/// Directory holding the `*.wants`/`*.requires` links created by
+/// `systemctl enable`.
+const ENABLE_DIR: &str = "etc/systemd/system";
+
+/// Enablement state of a unit file, as reported by `systemctl is-enabled`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitFileState {
+    Enabled,
+    Disabled,
+    /// No `[Install]` section; the unit is only started as a dependency.
+    Static,
+    /// Linked to `/dev/null`.
+    Masked,
+    #[default]
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Service {
+    /// Unit name, e.g. `ssh.service` or `getty@tty1.service`.
+    pub name: String,
+    /// Unit file the service was loaded from.
+    pub path: String,
+    /// Drop-in files applied on top of `path`, in order.
+    #[serde(default)]
+    pub drop_ins: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    /// `ExecStart=` command lines, including prefixes such as `-` or `+`.
+    #[serde(default)]
+    pub exec_start: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub wanted_by: Vec<String>,
+    pub state: UnitFileState,
+    /// `ActiveState` from systemd, e.g. `active` or `failed`. Only set with
+    /// the `systemd-dbus` feature when the system bus was reachable.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub active_state: Option<String>,
+    /// `SubState` from systemd, e.g. `running` or `exited`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sub_state: Option<String>,
+}
+
+/// Settings gathered from a unit file and its drop-ins.
+#[derive(Debug, Default)]
+struct UnitFile {
+    description: Option<String>,
+    exec_start: Vec<String>,
+    user: Option<String>,
+    wanted_by: Vec<String>,
+    /// Whether the `[Install]` section has any entries.
+    installable: bool,
+}
+
+/// Lists the services of the running system. With the `systemd-dbus`
+/// feature, runtime state is added from systemd when the system bus is
+/// available.
+pub fn get_services() -> Vec<Service> {
+    #[cfg_attr(not(feature = "systemd-dbus"), allow(unused_mut))]
+    let mut services = read_services("/");
+    #[cfg(feature = "systemd-dbus")]
+    if let Ok(connection) = Connection::system() {
+        if let Ok(states) = query_unit_states(&connection) {
+            apply_unit_states(&mut services, &states);
+        }
+    }
+    services
+}
+
+/// Reads the service unit files below `root`, sorted by name. Runtime state
+/// is left unset.
+pub fn read_services<P: AsRef<Path>>(root: P) -> Vec<Service> {
+    let root = root.as_ref();
+    let enabled = read_enabled_units(&root.join(ENABLE_DIR));
+
+    let mut fragments: BTreeMap<String, PathBuf> = BTreeMap::new();
+    let mut masked = BTreeSet::new();
+    for dir in UNIT_DIRS {
+        let Ok(entries) = fs::read_dir(root.join(dir)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.ends_with(".service") || fragments.contains_key(&name) {
+                continue;
+            }
+            let path = entry.path();
+            if let Ok(target) = fs::read_link(&path) {
+                if target == Path::new("/dev/null") {
+                    masked.insert(name.clone());
+                } else if target.file_name() != Some(entry.file_name().as_os_str()) {
+                    // An alias such as `sshd.service` -> `ssh.service`.
+                    continue;
+                }
+            }
+            fragments.insert(name, path);
+        }
+    }
+
+    let mut services = Vec::new();
+    for (name, path) in &fragments {
+        let mut unit = UnitFile::default();
+        let mut drop_ins = Vec::new();
+        if !masked.contains(name) {
+            if let Ok(content) = fs::read_to_string(path) {
+                parse_unit_file(&content, &mut unit);
+            }
+            for drop_in in find_drop_ins(root, name) {
+                if let Ok(content) = fs::read_to_string(&drop_in) {
+                    parse_unit_file(&content, &mut unit);
+                }
+                drop_ins.push(drop_in.display().to_string());
+            }
+        }
+
+        let template = name.contains("@.");
+        let state = if masked.contains(name) {
+            UnitFileState::Masked
+        } else if enabled.contains(name)
+            || (template
+                && enabled
+                    .iter()
+                    .any(|unit| template_of(unit) == Some(name.clone())))
+        {
+            UnitFileState::Enabled
+        } else if unit.installable {
+            UnitFileState::Disabled
+        } else {
+            UnitFileState::Static
+        };
+
+        let service = Service {
+            name: name.clone(),
+            path: path.display().to_string(),
+            drop_ins,
+            description: unit.description,
+            exec_start: unit.exec_start,
+            user: unit.user,
+            wanted_by: unit.wanted_by,
+            state,
+            active_state: None,
+            sub_state: None,
+        };
+
+        // Enabled instances of a template are listed as services of their own.
+        if template {
+            for instance in enabled
+                .iter()
+                .filter(|unit| template_of(unit).as_ref() == Some(name))
+            {
+                let instance_name = instance_of(instance).unwrap_or_default();
+                services.push(Service {
+                    name: instance.clone(),
+                    description: service
+                        .description
+                        .as_deref()
+                        .map(|description| expand_instance(description, instance_name)),
+                    exec_start: service
+                        .exec_start
+                        .iter()
+                        .map(|command| expand_instance(command, instance_name))
+                        .collect(),
+                    ..service.clone()
+                });
+            }
+        }
+        services.push(service);
+    }
+
+    services.sort_by(|a, b| a.name.cmp(&b.name));
+    services
+}
+
+/// Collects the unit names linked from `*.wants` and `*.requires`
+/// directories. Both the link name and the name of the unit it points to are
+/// included, since a unit may be enabled under one of its aliases.
+fn read_enabled_units(dir: &Path) -> BTreeSet<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return BTreeSet::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.ends_with(".wants") || name.ends_with(".requires")
+        })
+        .filter_map(|entry| fs::read_dir(entry.path()).ok())
+        .flat_map(|links| links.flatten())
+        .flat_map(|link| {
+            let target = fs::read_link(link.path())
+                .ok()
+                .and_then(|target| target.file_name().map(|name| name.to_os_string()));
+            std::iter::once(link.file_name())
+                .chain(target)
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .collect()
+}
+
+/// Returns the template of an instance name, e.g. `getty@tty1.service` ->
+/// `getty@.service`.
+fn template_of(name: &str) -> Option<String> {
+    let (prefix, rest) = name.split_once('@')?;
+    let (instance, suffix) = rest.rsplit_once('.')?;
+    (!instance.is_empty()).then(|| format!("{}@.{}", prefix, suffix))
+}
+
+/// Returns the instance part of a unit name, e.g. `tty1` for
+/// `getty@tty1.service`.
+fn instance_of(name: &str) -> Option<&str> {
+    let (_, rest) = name.split_once('@')?;
+    let (instance, _) = rest.rsplit_once('.')?;
+    (!instance.is_empty()).then_some(instance)
+}
+
+/// Expands the `%i` (instance) and `%I` (unescaped instance) specifiers
+/// copied from a template. Other specifiers are left as they are.
+fn expand_instance(value: &str, instance: &str) -> String {
+    let mut expanded = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('i') => expanded.push_str(instance),
+            Some('I') => expanded.push_str(&unescape_instance(instance)),
+            Some(other) => {
+                expanded.push('%');
+                expanded.push(other);
+            }
+            None => expanded.push('%'),
+        }
+    }
+    expanded
+}
+
+/// Reverses `systemd-escape`: `-` stands for `/` and `\xNN` for a byte.
+fn unescape_instance(instance: &str) -> String {
+    let bytes = instance.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && bytes.get(i + 1) == Some(&b'x') {
+            if let Some(byte) = instance
+                .get(i + 2..i + 4)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                unescaped.push(byte);
+                i += 4;
+                continue;
+            }
+        }
+        unescaped.push(if bytes[i] == b'-' { b'/' } else { bytes[i] });
+        i += 1;
+    }
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+/// Finds `<name>.d/*.conf` drop-ins in all unit directories, sorted by file
+/// name. A drop-in overrides one of the same name in a lower-precedence
+/// directory.
+fn find_drop_ins(root: &Path, name: &str) -> Vec<PathBuf> {
+    let mut drop_ins: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for dir in UNIT_DIRS {
+        let Ok(entries) = fs::read_dir(root.join(dir).join(format!("{}.d", name))) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if file_name.ends_with(".conf") {
+                drop_ins.entry(file_name).or_insert_with(|| entry.path());
+            }
+        }
+    }
+    drop_ins.into_values().collect()
+}
+
+/// Applies the settings of a unit file or drop-in to `unit`. An empty
+/// assignment resets list settings, as in systemd.
+fn parse_unit_file(content: &str, unit: &mut UnitFile) {
+    let mut section = String::new();
+    let mut logical_line = String::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if logical_line.is_empty() && (line.starts_with('#') || line.starts_with(';')) {
+            continue;
+        }
+        if let Some(continued) = line.strip_suffix('\\') {
+            logical_line.push_str(continued);
+            logical_line.push(' ');
+            continue;
+        }
+        logical_line.push_str(line);
+        let line = std::mem::take(&mut logical_line);
+
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        match (section.as_str(), key) {
+            ("Unit", "Description") => unit.description = Some(value.to_string()),
+            ("Service", "User") => unit.user = Some(value.to_string()),
+            ("Service", "ExecStart") if value.is_empty() => unit.exec_start.clear(),
+            ("Service", "ExecStart") => unit.exec_start.push(value.to_string()),
+            ("Install", "WantedBy") if value.is_empty() => unit.wanted_by.clear(),
+            ("Install", "WantedBy") => {
+                unit.wanted_by
+                    .extend(value.split_whitespace().map(str::to_string));
+            }
+            _ => {}
+        }
+        if section == "Install" && matches!(key, "WantedBy" | "RequiredBy" | "Alias" | "Also") {
+            unit.installable |= !value.is_empty();
+        }
+    }
+}
+
+/// A unit as returned by `org.freedesktop.systemd1.Manager.ListUnits`.
+#[cfg(feature = "systemd-dbus")]
+type ListedUnit = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    OwnedObjectPath,
+    u32,
+    String,
+    OwnedObjectPath,
+);
+
+/// Queries systemd for the (ActiveState, SubState) of all loaded units.
+#[cfg(feature = "systemd-dbus")]
+pub fn query_unit_states(connection: &Connection) -> Result<HashMap<String, (String, String)>> {
+    let reply = connection.call_method(
+        Some("org.freedesktop.systemd1"),
+        "/org/freedesktop/systemd1",
+        Some("org.freedesktop.systemd1.Manager"),
+        "ListUnits",
+        &(),
+    )?;
+    let units: Vec<ListedUnit> = reply.body().deserialize()?;
+    Ok(units
+        .into_iter()
+        .map(|(name, _, _, active_state, sub_state, ..)| (name, (active_state, sub_state)))
+        .collect())
+}
+
+#[cfg(feature = "systemd-dbus")]
+fn apply_unit_states(services: &mut [Service], states: &HashMap<String, (String, String)>) {
+    for service in services {
+        if let Some((active_state, sub_state)) = states.get(&service.name) {
+            service.active_state = Some(active_state.clone());
+            service.sub_state = Some(sub_state.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::test_util::TempDir;
+    use anyhow::Result;
+    #[cfg(feature = "systemd-dbus")]
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::fs::symlink;
+    #[cfg(feature = "systemd-dbus")]
+    use std::process::{Command, Stdio};
+
+    #[test]
+    fn test_read_services() -> Result<()> {
+        let root = TempDir::new("services");
+        let etc = root.join("etc/systemd/system");
+        let lib = root.join("usr/lib/systemd/system");
+        fs::create_dir_all(etc.join("multi-user.target.wants"))?;
+        fs::create_dir_all(etc.join("getty.target.wants"))?;
+        fs::create_dir_all(etc.join("nginx.service.d"))?;
+        fs::create_dir_all(&lib)?;
+
+        fs::write(
+            lib.join("nginx.service"),
+            "[Unit]\nDescription=A high performance web server\n\n[Service]\n\
+             # comment\nExecStart=/usr/sbin/nginx \\\n  -g 'daemon off;'\n\n\
+             [Install]\nWantedBy=multi-user.target\n",
+        )?;
+        fs::write(
+            etc.join("nginx.service.d/override.conf"),
+            "[Service]\nUser=www-data\nExecStart=\nExecStart=/usr/local/sbin/nginx\n",
+        )?;
+        symlink(
+            lib.join("nginx.service"),
+            etc.join("multi-user.target.wants/nginx.service"),
+        )?;
+        fs::write(
+            lib.join("cups.service"),
+            "[Unit]\nDescription=CUPS\n[Service]\nExecStart=/usr/sbin/cupsd -l\n\
+             [Install]\nWantedBy=printer.target multi-user.target\n",
+        )?;
+        fs::write(
+            lib.join("systemd-journald.service"),
+            "[Unit]\nDescription=Journal Service\n[Service]\nExecStart=/lib/systemd/systemd-journald\n",
+        )?;
+        fs::write(
+            lib.join("telnet.service"),
+            "[Service]\nExecStart=/usr/sbin/telnetd\n",
+        )?;
+        symlink("/dev/null", etc.join("telnet.service"))?;
+        symlink(lib.join("nginx.service"), etc.join("www.service"))?;
+        fs::write(
+            lib.join("getty@.service"),
+            "[Unit]\nDescription=Getty on %I\n[Service]\nExecStart=-/sbin/agetty %I\n\
+             [Install]\nWantedBy=getty.target\n",
+        )?;
+        symlink(
+            lib.join("getty@.service"),
+            etc.join("getty.target.wants/getty@tty1.service"),
+        )?;
+        fs::write(
+            lib.join("ssh.service"),
+            "[Unit]\nDescription=OpenBSD Secure Shell server\n[Service]\nExecStart=/usr/sbin/sshd -D\n\
+             [Install]\nWantedBy=multi-user.target\nAlias=sshd.service\n",
+        )?;
+        // Enabled under its alias.
+        symlink(
+            lib.join("ssh.service"),
+            etc.join("multi-user.target.wants/sshd.service"),
+        )?;
+
+        let services = read_services(&root);
+        let names: Vec<&str> = services.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "cups.service",
+                "getty@.service",
+                "getty@tty1.service",
+                "nginx.service",
+                "ssh.service",
+                "systemd-journald.service",
+                "telnet.service",
+            ]
+        );
+
+        let nginx = &services[3];
+        assert_eq!(nginx.state, UnitFileState::Enabled);
+        assert_eq!(
+            nginx.description.as_deref(),
+            Some("A high performance web server")
+        );
+        assert_eq!(nginx.exec_start, vec!["/usr/local/sbin/nginx"]);
+        assert_eq!(nginx.user.as_deref(), Some("www-data"));
+        assert_eq!(nginx.wanted_by, vec!["multi-user.target"]);
+        assert_eq!(nginx.drop_ins.len(), 1);
+
+        assert_eq!(services[0].state, UnitFileState::Disabled);
+        assert_eq!(
+            services[0].wanted_by,
+            vec!["printer.target", "multi-user.target"]
+        );
+        assert_eq!(services[1].state, UnitFileState::Enabled);
+        assert_eq!(services[1].description.as_deref(), Some("Getty on %I"));
+        assert_eq!(services[2].description.as_deref(), Some("Getty on tty1"));
+        assert_eq!(services[2].exec_start, vec!["-/sbin/agetty tty1"]);
+        assert_eq!(services[4].state, UnitFileState::Enabled);
+        assert_eq!(services[5].state, UnitFileState::Static);
+        assert_eq!(services[6].state, UnitFileState::Masked);
+        assert!(services[6].exec_start.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_unit_file_continuation() {
+        let mut unit = UnitFile::default();
+        parse_unit_file(
+            "[Service]\nExecStart=/bin/app \\\n  --flag\nExecStart=/bin/other\n",
+            &mut unit,
+        );
+        assert_eq!(unit.exec_start, vec!["/bin/app  --flag", "/bin/other"]);
+        assert!(!unit.installable);
+        assert_eq!(
+            template_of("getty@tty1.service").as_deref(),
+            Some("getty@.service")
+        );
+        assert_eq!(template_of("getty@.service"), None);
+        assert_eq!(instance_of("getty@tty1.service"), Some("tty1"));
+        assert_eq!(
+            expand_instance("Mount %I (%i), 100%% %n", "mnt-my\\x2ddata"),
+            "Mount mnt/my-data (mnt-my\\x2ddata), 100%% %n"
+        );
+    }
+
+    #[cfg(feature = "systemd-dbus")]
+    struct MockManager;
+
+    #[cfg(feature = "systemd-dbus")]
+    #[zbus::interface(name = "org.freedesktop.systemd1.Manager")]
+    impl MockManager {
+        fn list_units(&self) -> Vec<ListedUnit> {
+            let path = OwnedObjectPath::try_from("/org/freedesktop/systemd1/unit/nginx_2eservice")
+                .unwrap();
+            let job = OwnedObjectPath::try_from("/").unwrap();
+            vec![(
+                "nginx.service".to_string(),
+                "nginx".to_string(),
+                "loaded".to_string(),
+                "active".to_string(),
+                "running".to_string(),
+                String::new(),
+                path,
+                0,
+                String::new(),
+                job,
+            )]
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "systemd-dbus")]
+    fn test_query_unit_states() -> Result<()> {
+        // Runs against a private bus; skipped where dbus-daemon is missing.
+        let Ok(mut daemon) = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .spawn()
+        else {
+            return Ok(());
+        };
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap()).read_line(&mut address)?;
+
+        let result = (|| -> Result<()> {
+            let _systemd = zbus::blocking::connection::Builder::address(address.trim())?
+                .name("org.freedesktop.systemd1")?
+                .serve_at("/org/freedesktop/systemd1", MockManager)?
+                .build()?;
+            let client = zbus::blocking::connection::Builder::address(address.trim())?.build()?;
+
+            let states = query_unit_states(&client)?;
+            let mut services = vec![Service {
+                name: "nginx.service".to_string(),
+                ..Default::default()
+            }];
+            apply_unit_states(&mut services, &states);
+            assert_eq!(services[0].active_state.as_deref(), Some("active"));
+            assert_eq!(services[0].sub_state.as_deref(), Some("running"));
+            Ok(())
+        })();
+
+        daemon.kill()?;
+        daemon.wait()?;
+        result
+    }
+}
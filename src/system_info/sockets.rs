@@ -0,0 +1,426 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+const PROC_PATH: &str = "/proc";
+const UNIX_PATH: &str = "/proc/net/unix";
+
+/// TCP states from `<net/tcp_states.h>`, indexed by their numeric value.
+const TCP_STATES: [&str; 13] = [
+    "UNKNOWN",
+    "ESTABLISHED",
+    "SYN_SENT",
+    "SYN_RECV",
+    "FIN_WAIT1",
+    "FIN_WAIT2",
+    "TIME_WAIT",
+    "CLOSE",
+    "CLOSE_WAIT",
+    "LAST_ACK",
+    "LISTEN",
+    "CLOSING",
+    "NEW_SYN_RECV",
+];
+const TCP_LISTEN: u8 = 0x0A;
+const TCP_CLOSE: u8 = 0x07;
+
+/// `__SO_ACCEPTCON`, set on listening unix sockets.
+const SO_ACCEPTCON: u32 = 0x0001_0000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum InetProtocol {
+    Tcp,
+    Tcp6,
+    Udp,
+    Udp6,
+}
+
+impl InetProtocol {
+    const ALL: [InetProtocol; 4] = [Self::Tcp, Self::Tcp6, Self::Udp, Self::Udp6];
+
+    fn proc_file(self) -> &'static str {
+        match self {
+            Self::Tcp => "tcp",
+            Self::Tcp6 => "tcp6",
+            Self::Udp => "udp",
+            Self::Udp6 => "udp6",
+        }
+    }
+
+    fn is_tcp(self) -> bool {
+        matches!(self, Self::Tcp | Self::Tcp6)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InetSocket {
+    pub protocol: InetProtocol,
+    pub local_address: IpAddr,
+    pub local_port: u16,
+    pub remote_address: IpAddr,
+    pub remote_port: u16,
+    pub state: String,
+    pub tx_queue: u64,
+    pub rx_queue: u64,
+    pub uid: u32,
+    pub inode: u64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub owner: Option<SocketOwner>,
+}
+
+impl InetSocket {
+    /// TCP sockets in `LISTEN` and unconnected UDP sockets bound to a port.
+    pub fn is_listening(&self) -> bool {
+        if self.protocol.is_tcp() {
+            self.state == TCP_STATES[TCP_LISTEN as usize]
+        } else {
+            self.state == TCP_STATES[TCP_CLOSE as usize] && self.remote_port == 0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UnixSocket {
+    /// Bound path; abstract sockets start with `@`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub path: Option<String>,
+    pub socket_type: String,
+    pub state: String,
+    pub listening: bool,
+    pub inode: u64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub owner: Option<SocketOwner>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SocketOwner {
+    pub pid: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ListeningPort {
+    pub protocol: InetProtocol,
+    pub address: IpAddr,
+    pub port: u16,
+    pub uid: u32,
+    pub inode: u64,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub owner: Option<SocketOwner>,
+}
+
+/// Returns the TCP and UDP sockets of the current network namespace.
+///
+/// Owners are resolved through `/proc/*/fd`; without privileges only the
+/// caller's own processes can be inspected and other sockets have no owner.
+pub fn get_inet_sockets() -> Result<Vec<InetSocket>> {
+    let owners = socket_owners(PROC_PATH);
+    let mut sockets = Vec::new();
+
+    for protocol in InetProtocol::ALL {
+        let path = Path::new(PROC_PATH).join("net").join(protocol.proc_file());
+        // IPv6 may be disabled, in which case the *6 files do not exist.
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for line in content.lines().skip(1).filter(|l| !l.trim().is_empty()) {
+            let mut socket = parse_inet_line(protocol, line)?;
+            socket.owner = owners.get(&socket.inode).cloned();
+            sockets.push(socket);
+        }
+    }
+    Ok(sockets)
+}
+
+pub fn get_unix_sockets() -> Result<Vec<UnixSocket>> {
+    let content =
+        fs::read_to_string(UNIX_PATH).with_context(|| format!("Failed to read {}", UNIX_PATH))?;
+    let owners = socket_owners(PROC_PATH);
+
+    content
+        .lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut socket = parse_unix_line(line)?;
+            socket.owner = owners.get(&socket.inode).cloned();
+            Ok(socket)
+        })
+        .collect()
+}
+
+/// Listening TCP and bound UDP ports, the equivalent of `ss -tulnp`.
+pub fn listening_ports() -> Result<Vec<ListeningPort>> {
+    let mut ports: Vec<ListeningPort> = get_inet_sockets()?
+        .into_iter()
+        .filter(InetSocket::is_listening)
+        .map(|socket| ListeningPort {
+            protocol: socket.protocol,
+            address: socket.local_address,
+            port: socket.local_port,
+            uid: socket.uid,
+            inode: socket.inode,
+            owner: socket.owner,
+        })
+        .collect();
+
+    ports.sort_by(|a, b| {
+        (a.port, a.protocol.proc_file(), a.address).cmp(&(
+            b.port,
+            b.protocol.proc_file(),
+            b.address,
+        ))
+    });
+    Ok(ports)
+}
+
+/// Parses a line of `/proc/net/{tcp,udp}{,6}`:
+///
+/// ```text
+/// sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+/// 0: 0100007F:0277 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 21452 ...
+/// ```
+fn parse_inet_line(protocol: InetProtocol, line: &str) -> Result<InetSocket> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 10 {
+        bail!("Truncated socket line: {}", line);
+    }
+
+    let (local_address, local_port) = parse_endpoint(fields[1])?;
+    let (remote_address, remote_port) = parse_endpoint(fields[2])?;
+    let state = u8::from_str_radix(fields[3], 16)?;
+    let (tx_queue, rx_queue) = fields[4]
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Invalid queue field: {}", fields[4]))?;
+
+    Ok(InetSocket {
+        protocol,
+        local_address,
+        local_port,
+        remote_address,
+        remote_port,
+        state: TCP_STATES
+            .get(state as usize)
+            .copied()
+            .unwrap_or("UNKNOWN")
+            .to_string(),
+        tx_queue: u64::from_str_radix(tx_queue, 16)?,
+        rx_queue: u64::from_str_radix(rx_queue, 16)?,
+        uid: fields[7].parse()?,
+        inode: fields[9].parse()?,
+        owner: None,
+    })
+}
+
+/// Parses `ADDR:PORT`, where the address is printed as 32-bit words in host
+/// byte order.
+fn parse_endpoint(field: &str) -> Result<(IpAddr, u16)> {
+    let (addr, port) = field
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Invalid endpoint: {}", field))?;
+    let port = u16::from_str_radix(port, 16)?;
+
+    let address = match addr.len() {
+        8 => IpAddr::V4(Ipv4Addr::from(u32::from_str_radix(addr, 16)?.to_ne_bytes())),
+        32 => {
+            let mut octets = [0u8; 16];
+            for (i, chunk) in octets.chunks_mut(4).enumerate() {
+                let word = u32::from_str_radix(&addr[i * 8..i * 8 + 8], 16)?;
+                chunk.copy_from_slice(&word.to_ne_bytes());
+            }
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => bail!("Invalid address: {}", addr),
+    };
+    Ok((address, port))
+}
+
+/// Parses a line of `/proc/net/unix`:
+///
+/// ```text
+/// Num       RefCount Protocol Flags    Type St Inode Path
+/// 0000000000000000: 00000002 00000000 00010000 0001 01 21409 /run/dbus/system_bus_socket
+/// ```
+///
+/// The path is everything after the fixed columns and may contain spaces.
+fn parse_unix_line(line: &str) -> Result<UnixSocket> {
+    let mut fields = Vec::with_capacity(7);
+    let mut rest = line;
+    while fields.len() < 7 {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            bail!("Truncated unix socket line: {}", line);
+        }
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        fields.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    // A single space separates the inode from the path.
+    let path = rest.strip_prefix(' ').unwrap_or(rest);
+
+    let flags = u32::from_str_radix(fields[3], 16)?;
+    let socket_type = match u16::from_str_radix(fields[4], 16)? {
+        1 => "stream",
+        2 => "dgram",
+        5 => "seqpacket",
+        _ => "unknown",
+    };
+    let state = match u8::from_str_radix(fields[5], 16)? {
+        1 => "UNCONNECTED",
+        2 => "CONNECTING",
+        3 => "CONNECTED",
+        4 => "DISCONNECTING",
+        _ => "UNKNOWN",
+    };
+
+    Ok(UnixSocket {
+        path: Some(path)
+            .filter(|path| !path.is_empty())
+            .map(str::to_string),
+        socket_type: socket_type.to_string(),
+        state: state.to_string(),
+        listening: flags & SO_ACCEPTCON != 0,
+        inode: fields[6].parse()?,
+        owner: None,
+    })
+}
+
+/// Maps socket inodes to the first process found holding them open.
+/// Processes that exit or deny access while being scanned are skipped.
+fn socket_owners<P: AsRef<Path>>(proc_root: P) -> HashMap<u64, SocketOwner> {
+    let mut owners = HashMap::new();
+    let Ok(entries) = fs::read_dir(proc_root.as_ref()) else {
+        return owners;
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        let mut name = None;
+
+        for fd in fds.filter_map(|fd| fd.ok()) {
+            let Some(inode) = fs::read_link(fd.path())
+                .ok()
+                .and_then(|target| parse_socket_link(&target.to_string_lossy()))
+            else {
+                continue;
+            };
+            let name = name
+                .get_or_insert_with(|| {
+                    fs::read_to_string(entry.path().join("comm"))
+                        .map(|comm| comm.trim().to_string())
+                        .unwrap_or_default()
+                })
+                .clone();
+            owners.entry(inode).or_insert(SocketOwner { pid, name });
+        }
+    }
+    owners
+}
+
+/// Extracts the inode from a `socket:[12345]` fd link.
+fn parse_socket_link(target: &str) -> Option<u64> {
+    target
+        .strip_prefix("socket:[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_inet_line() -> Result<()> {
+        let line = "   0: 0100007F:0277 00000000:0000 0A 00000000:00000002 00:00000000 00000000     0        0 21452 1 0000000000000000 100 0 0 10 0";
+        let socket = parse_inet_line(InetProtocol::Tcp, line)?;
+        assert_eq!(socket.local_address, "127.0.0.1".parse::<IpAddr>()?);
+        assert_eq!(socket.local_port, 631);
+        assert_eq!(socket.state, "LISTEN");
+        assert_eq!(socket.rx_queue, 2);
+        assert_eq!(socket.inode, 21452);
+        assert!(socket.is_listening());
+
+        let line = "   1: 0F02000A:A2D2 22D8B85D:01BB 01 00000000:00000000 02:000A7E4A 00000000  1000        0 40117 2 0000000000000000 20 4 30 10 -1";
+        let socket = parse_inet_line(InetProtocol::Tcp, line)?;
+        assert_eq!(socket.remote_address, "93.184.216.34".parse::<IpAddr>()?);
+        assert_eq!(socket.remote_port, 443);
+        assert_eq!(socket.state, "ESTABLISHED");
+        assert_eq!(socket.uid, 1000);
+        assert!(!socket.is_listening());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_inet6_line() -> Result<()> {
+        let line = "   0: 00000000000000000000000000000000:0016 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 18830 1 0000000000000000 100 0 0 10 0";
+        let socket = parse_inet_line(InetProtocol::Tcp6, line)?;
+        assert_eq!(socket.local_address, "::".parse::<IpAddr>()?);
+        assert_eq!(socket.local_port, 22);
+
+        let line = "  12: 00000000000000000000000001000000:0035 00000000000000000000000000000000:0000 07 00000000:00000000 00:00000000 00000000   101        0 17000 2 0000000000000000 0";
+        let socket = parse_inet_line(InetProtocol::Udp6, line)?;
+        assert_eq!(socket.local_address, "::1".parse::<IpAddr>()?);
+        assert_eq!(socket.local_port, 53);
+        assert!(socket.is_listening());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_unix_line() -> Result<()> {
+        let line = "0000000000000000: 00000002 00000000 00010000 0001 01 21409 /run/dbus/system_bus_socket";
+        let socket = parse_unix_line(line)?;
+        assert_eq!(socket.path.as_deref(), Some("/run/dbus/system_bus_socket"));
+        assert_eq!(socket.socket_type, "stream");
+        assert!(socket.listening);
+
+        let line = "0000000000000000: 00000002 00000000 00010000 0001 01 21410 /tmp/my app/sock  1";
+        let socket = parse_unix_line(line)?;
+        assert_eq!(socket.path.as_deref(), Some("/tmp/my app/sock  1"));
+        assert_eq!(socket.inode, 21410);
+
+        let line = "0000000000000000: 00000003 00000000 00000000 0001 03 40001";
+        let socket = parse_unix_line(line)?;
+        assert_eq!(socket.path, None);
+        assert_eq!(socket.state, "CONNECTED");
+        assert!(!socket.listening);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_socket_link() {
+        assert_eq!(parse_socket_link("socket:[12345]"), Some(12345));
+        assert_eq!(parse_socket_link("pipe:[12345]"), None);
+        assert_eq!(parse_socket_link("/dev/null"), None);
+    }
+
+    #[test]
+    fn test_listening_ports_owner() -> Result<()> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+
+        let ports = listening_ports()?;
+        let entry = ports
+            .iter()
+            .find(|p| p.protocol == InetProtocol::Tcp && p.port == port)
+            .expect("listener not found");
+        assert_eq!(
+            entry.owner.as_ref().map(|owner| owner.pid),
+            Some(std::process::id())
+        );
+        Ok(())
+    }
+}
@@ -1,78 +1,102 @@
-use anyhow::{Context, Result};
-use nix::sys::utsname::uname;
+use super::dns::NetworkIdentity;
+use super::language_packages::{get_language_packages, LanguagePackage};
+use super::os_release::{read_os_release, OsRelease};
+use super::services::{get_services, Service};
+use super::uname::{deserialize_uname, Uname};
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::path::Path;
 use std::sync::OnceLock;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SoftwareInfo {
+    /// Raw contents of the file the OS was identified from, kept for
+    /// compatibility. Prefer `os`.
     pub os_release: String,
-    pub uname: String,
+    #[serde(default)]
+    pub os: OsRelease,
+    #[serde(deserialize_with = "deserialize_uname")]
+    pub uname: Uname,
+    #[serde(default)]
+    pub network_identity: NetworkIdentity,
+    /// Python, npm and Rust packages installed outside the OS package manager.
+    /// Empty unless requested with [`SoftwareInfo::with_language_packages`],
+    /// since collecting them reads every binary in the system bin directories.
+    #[serde(default)]
+    pub language_packages: Vec<LanguagePackage>,
+    /// systemd service units with enablement and, if available, runtime state.
+    #[serde(default)]
+    pub services: Vec<Service>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub extra: Option<serde_json::Value>,
 }
 
 // Cache uname info since it rarely changes
-static UNAME_INFO: OnceLock<String> = OnceLock::new();
+static UNAME_INFO: OnceLock<Uname> = OnceLock::new();
 
 impl SoftwareInfo {
     pub fn new() -> Result<Self> {
+        let uname = get_cached_uname()?;
+        let (os_release, os) = get_os_release("/");
+
         Ok(Self {
-            os_release: get_os_release()?,
-            uname: get_cached_uname()?,
+            os_release,
+            os,
+            network_identity: NetworkIdentity::new(),
+            language_packages: Vec::new(),
+            services: get_services(),
+            uname,
             extra: None,
         })
     }
-}
 
-fn get_os_release() -> Result<String> {
-    fs::read_to_string("/etc/os-release").context("Failed to read /etc/os-release")
+    /// Fills in `language_packages` from the standard system locations.
+    pub fn with_language_packages(mut self) -> Self {
+        self.language_packages = get_language_packages();
+        self
+    }
 }
 
-fn get_cached_uname() -> Result<String> {
-    Ok(UNAME_INFO
-        .get_or_init(|| get_uname().expect("Failed to get uname info"))
-        .clone())
+/// Falls back to the os-release(5) defaults (`ID=linux`, `NAME=Linux`) when
+/// no release file is found.
+fn get_os_release<P: AsRef<Path>>(root: P) -> (String, OsRelease) {
+    read_os_release(root).unwrap_or_else(|| (String::new(), OsRelease::parse("")))
 }
 
-fn get_uname() -> Result<String> {
-    let uname = uname().context("Failed to get uname info")?;
-
-    let mut fields = Vec::with_capacity(6);
-
-    fields.push(("sysname", uname.sysname().to_string_lossy().into_owned()));
-    fields.push(("nodename", uname.nodename().to_string_lossy().into_owned()));
-    fields.push(("release", uname.release().to_string_lossy().into_owned()));
-    fields.push(("version", uname.version().to_string_lossy().into_owned()));
-    fields.push(("machine", uname.machine().to_string_lossy().into_owned()));
-    fields.push((
-        "domainname",
-        uname.domainname().to_string_lossy().into_owned(),
-    ));
-
-    let uname_info = serde_json::Map::from_iter(
-        fields
-            .into_iter()
-            .map(|(k, v)| (k.to_owned(), serde_json::Value::String(v))),
-    );
-
-    Ok(serde_json::Value::Object(uname_info).to_string())
+fn get_cached_uname() -> Result<Uname> {
+    if let Some(uname) = UNAME_INFO.get() {
+        return Ok(uname.clone());
+    }
+    let uname = Uname::new()?;
+    Ok(UNAME_INFO.get_or_init(|| uname).clone())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::system_info::test_util::TempDir;
 
     #[test]
     fn test_get_os_release() {
-        let os_release = get_os_release().unwrap();
+        let (os_release, os) = get_os_release("/");
         assert!(!os_release.is_empty());
+        assert!(!os.id.is_empty());
+
+        let empty = TempDir::new("software_os_release");
+        let (os_release, os) = get_os_release(&empty);
+        assert!(os_release.is_empty());
+        assert_eq!(os.id, "linux");
+        assert_eq!(os.name, "Linux");
+        assert_eq!(os.pretty_name, "Linux");
     }
 
     #[test]
-    fn test_get_uname() {
-        let uname = get_uname().unwrap();
-        assert!(!uname.is_empty());
+    fn test_get_cached_uname() {
+        let uname = get_cached_uname().unwrap();
+        assert_eq!(uname.sysname, "Linux");
+        assert!(!uname.nodename.is_empty());
+        assert!(uname.kernel_version.is_some());
+        assert_eq!(get_cached_uname().unwrap(), uname);
     }
 
     #[test]
@@ -93,5 +117,6 @@ mod tests {
         let deserialized: SoftwareInfo = serde_json::from_str(&serialized).unwrap();
         assert_eq!(software_info.os_release, deserialized.os_release);
         assert_eq!(software_info.uname, deserialized.uname);
+        assert!(software_info.language_packages.is_empty());
     }
 }
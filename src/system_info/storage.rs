@@ -0,0 +1,608 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+const MDSTAT_PATH: &str = "/proc/mdstat";
+const SYS_CLASS_BLOCK_PATH: &str = "/sys/class/block";
+
+/// Software RAID, device-mapper and stacking information for block devices.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct StorageTopology {
+    pub md_arrays: Vec<MdArray>,
+    pub dm_devices: Vec<DmDevice>,
+    pub block_devices: Vec<BlockDeviceLinks>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct MdArray {
+    pub name: String,
+    pub level: String,
+    /// `array_state` from sysfs (`clean`, `active`, `degraded`, ...), or the
+    /// `active`/`inactive` word of `/proc/mdstat` when sysfs is unavailable.
+    pub state: String,
+    pub members: Vec<MdMember>,
+    pub raid_disks: u32,
+    pub active_disks: u32,
+    pub degraded: bool,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sync_action: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub sync_progress_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct MdMember {
+    pub device: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub slot: Option<u32>,
+    pub faulty: bool,
+    pub spare: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DmKind {
+    Lvm,
+    Crypt,
+    Multipath,
+    Raid,
+    #[default]
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct DmDevice {
+    /// Kernel name, e.g. `dm-0`.
+    pub device: String,
+    /// Mapper name, e.g. `vg0-root` as found in `/dev/mapper`.
+    pub name: String,
+    pub uuid: String,
+    pub kind: DmKind,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub vg_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub lv_name: Option<String>,
+    pub slaves: Vec<String>,
+}
+
+/// Holder/slave edges of a block device as exposed in sysfs.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct BlockDeviceLinks {
+    pub name: String,
+    /// Whole disk this partition belongs to.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub parent: Option<String>,
+    /// Devices this one is built on top of.
+    pub slaves: Vec<String>,
+    /// Devices built on top of this one.
+    pub holders: Vec<String>,
+}
+
+/// Answer to "what backs this device": physical disks at the bottom of the
+/// stack and every MD array crossed on the way down.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct BackingDevices {
+    pub device: String,
+    pub physical_disks: Vec<String>,
+    pub md_arrays: Vec<MdArray>,
+    pub degraded: bool,
+}
+
+impl StorageTopology {
+    pub fn new() -> Result<Self> {
+        Self::from_paths(SYS_CLASS_BLOCK_PATH, MDSTAT_PATH)
+    }
+
+    pub fn from_paths<P: AsRef<Path>, Q: AsRef<Path>>(
+        sys_class_block: P,
+        mdstat: Q,
+    ) -> Result<Self> {
+        let sys_class_block = sys_class_block.as_ref();
+        let block_devices = read_block_device_links(sys_class_block)?;
+
+        let mut md_arrays = match fs::read_to_string(mdstat.as_ref()) {
+            Ok(content) => parse_mdstat(&content),
+            Err(_) => Vec::new(),
+        };
+        for array in &mut md_arrays {
+            enrich_md_array_from_sysfs(array, &sys_class_block.join(&array.name).join("md"));
+        }
+
+        let dm_devices = block_devices
+            .iter()
+            .filter(|dev| dev.name.starts_with("dm-"))
+            .filter_map(|dev| read_dm_device(&sys_class_block.join(&dev.name), dev).ok())
+            .collect();
+
+        Ok(Self {
+            md_arrays,
+            dm_devices,
+            block_devices,
+        })
+    }
+
+    /// Resolves `name` (kernel name, mapper name, `vg/lv`, or a `/dev` path)
+    /// and walks the slave links down to whole physical disks.
+    pub fn backing_devices(&self, name: &str) -> Option<BackingDevices> {
+        let device = self.resolve_device(name)?;
+        let links: HashMap<&str, &BlockDeviceLinks> = self
+            .block_devices
+            .iter()
+            .map(|dev| (dev.name.as_str(), dev))
+            .collect();
+
+        let mut physical_disks = BTreeSet::new();
+        let mut arrays = BTreeSet::new();
+        let mut stack = vec![device.clone()];
+        let mut visited = BTreeSet::new();
+
+        while let Some(current) = stack.pop() {
+            if !visited.insert(current.clone()) {
+                continue;
+            }
+            if self.md_arrays.iter().any(|md| md.name == current) {
+                arrays.insert(current.clone());
+            }
+            match links.get(current.as_str()) {
+                Some(dev) if !dev.slaves.is_empty() => stack.extend(dev.slaves.iter().cloned()),
+                // A partition of an MD or dm device continues through the
+                // slaves of the device it belongs to.
+                Some(BlockDeviceLinks {
+                    parent: Some(parent),
+                    ..
+                }) => stack.push(parent.clone()),
+                Some(dev) => {
+                    physical_disks.insert(dev.name.clone());
+                }
+                None => {
+                    physical_disks.insert(current);
+                }
+            }
+        }
+
+        let md_arrays: Vec<MdArray> = self
+            .md_arrays
+            .iter()
+            .filter(|md| arrays.contains(&md.name))
+            .cloned()
+            .collect();
+        let degraded = md_arrays.iter().any(|md| md.degraded);
+
+        Some(BackingDevices {
+            device,
+            physical_disks: physical_disks.into_iter().collect(),
+            md_arrays,
+            degraded,
+        })
+    }
+
+    fn resolve_device(&self, name: &str) -> Option<String> {
+        let name = name
+            .strip_prefix("/dev/mapper/")
+            .or_else(|| name.strip_prefix("/dev/"))
+            .unwrap_or(name);
+
+        if self.block_devices.iter().any(|dev| dev.name == name) {
+            return Some(name.to_string());
+        }
+
+        self.dm_devices
+            .iter()
+            .find(|dm| {
+                dm.name == name
+                    || matches!((&dm.vg_name, &dm.lv_name), (Some(vg), Some(lv))
+                        if name.split_once('/') == Some((vg.as_str(), lv.as_str())))
+            })
+            .map(|dm| dm.device.clone())
+    }
+}
+
+fn read_block_device_links(sys_class_block: &Path) -> Result<Vec<BlockDeviceLinks>> {
+    let entries = fs::read_dir(sys_class_block)
+        .with_context(|| format!("Failed to read {}", sys_class_block.display()))?;
+
+    let mut devices: Vec<BlockDeviceLinks> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            BlockDeviceLinks {
+                parent: partition_parent(&path),
+                slaves: list_dir_names(&path.join("slaves")),
+                holders: list_dir_names(&path.join("holders")),
+                name,
+            }
+        })
+        .collect();
+
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(devices)
+}
+
+/// Partitions live below their disk in the sysfs device tree, e.g.
+/// `.../block/sda/sda1`, and carry a `partition` attribute.
+fn partition_parent(path: &Path) -> Option<String> {
+    if !path.join("partition").exists() {
+        return None;
+    }
+    let resolved = fs::canonicalize(path).ok()?;
+    resolved
+        .parent()?
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+fn list_dir_names(path: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+fn read_sysfs_attr(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+fn read_dm_device(path: &Path, links: &BlockDeviceLinks) -> Result<DmDevice> {
+    let dm_path = path.join("dm");
+    let name = read_sysfs_attr(&dm_path.join("name"))
+        .ok_or_else(|| anyhow::anyhow!("No dm name for {}", links.name))?;
+    let uuid = read_sysfs_attr(&dm_path.join("uuid")).unwrap_or_default();
+    let kind = dm_kind_from_uuid(&uuid);
+
+    let (vg_name, lv_name) = match kind {
+        DmKind::Lvm => match split_lvm_name(&name) {
+            Some((vg, lv)) => (Some(vg), Some(lv)),
+            None => (None, None),
+        },
+        _ => (None, None),
+    };
+
+    Ok(DmDevice {
+        device: links.name.clone(),
+        name,
+        uuid,
+        kind,
+        vg_name,
+        lv_name,
+        slaves: links.slaves.clone(),
+    })
+}
+
+fn dm_kind_from_uuid(uuid: &str) -> DmKind {
+    let prefix = uuid.split('-').next().unwrap_or_default();
+    match prefix {
+        "LVM" => DmKind::Lvm,
+        "CRYPT" => DmKind::Crypt,
+        "mpath" => DmKind::Multipath,
+        "DMRAID" => DmKind::Raid,
+        _ => DmKind::Other,
+    }
+}
+
+/// Splits a mapper name into volume group and logical volume. LVM doubles
+/// dashes that are part of either name, so `my--vg-root` is `my-vg`/`root`.
+fn split_lvm_name(name: &str) -> Option<(String, String)> {
+    let bytes = name.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'-' {
+            if bytes.get(i + 1) == Some(&b'-') {
+                i += 2;
+                continue;
+            }
+            let vg = name[..i].replace("--", "-");
+            let lv = name[i + 1..].replace("--", "-");
+            return Some((vg, lv));
+        }
+        i += 1;
+    }
+    None
+}
+
+fn parse_mdstat(content: &str) -> Vec<MdArray> {
+    let mut arrays = Vec::new();
+    let mut current: Option<MdArray> = None;
+
+    for line in content.lines() {
+        if let Some((name, rest)) = line.split_once(" : ") {
+            let name = name.trim();
+            if name.starts_with("md") {
+                arrays.extend(current.take());
+                current = Some(parse_md_header(name, rest));
+            }
+            continue;
+        }
+
+        let Some(array) = current.as_mut() else {
+            continue;
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            arrays.extend(current.take());
+            continue;
+        }
+
+        // "1046528 blocks super 1.2 [2/1] [U_]"
+        if let Some(counts) = line
+            .split_whitespace()
+            .find(|word| word.starts_with('[') && word.contains('/'))
+        {
+            let counts = counts.trim_matches(|c| c == '[' || c == ']');
+            if let Some((total, active)) = counts.split_once('/') {
+                array.raid_disks = total.parse().unwrap_or_default();
+                array.active_disks = active.parse().unwrap_or_default();
+                array.degraded = array.active_disks < array.raid_disks;
+            }
+        }
+
+        // "[=>....]  resync =  8.5% (89088/1046528) finish=0.5min speed=29696K/sec"
+        if let Some((action, progress)) = line.split_once(" = ") {
+            let action = action.split_whitespace().last().unwrap_or_default();
+            array.sync_action = Some(action.to_string());
+            array.sync_progress_percent = progress
+                .split_whitespace()
+                .next()
+                .and_then(|percent| percent.trim_end_matches('%').parse().ok());
+        }
+    }
+    arrays.extend(current);
+    arrays
+}
+
+/// Parses "active raid1 sdb1[1] sda1[0](F)".
+fn parse_md_header(name: &str, rest: &str) -> MdArray {
+    let mut words = rest.split_whitespace().peekable();
+    let state = words.next().unwrap_or_default().to_string();
+
+    // An "(auto-read-only)" or "(read-only)" marker may follow the state.
+    while words.peek().is_some_and(|word| word.starts_with('(')) {
+        words.next();
+    }
+
+    let level = match words.peek() {
+        Some(word) if !word.contains('[') => words.next().unwrap_or_default().to_string(),
+        _ => String::new(),
+    };
+
+    let members = words.filter_map(parse_md_member).collect();
+
+    MdArray {
+        name: name.to_string(),
+        level,
+        state,
+        members,
+        ..Default::default()
+    }
+}
+
+fn parse_md_member(word: &str) -> Option<MdMember> {
+    let (device, rest) = word.split_once('[')?;
+    let (slot, flags) = rest.split_once(']')?;
+    Some(MdMember {
+        device: device.to_string(),
+        slot: slot.parse().ok(),
+        faulty: flags.contains("(F)"),
+        spare: flags.contains("(S)"),
+    })
+}
+
+fn enrich_md_array_from_sysfs(array: &mut MdArray, md_path: &Path) {
+    if let Some(state) = read_sysfs_attr(&md_path.join("array_state")) {
+        array.state = state;
+    }
+    if let Some(level) = read_sysfs_attr(&md_path.join("level")) {
+        array.level = level;
+    }
+    if let Some(degraded) = read_sysfs_attr(&md_path.join("degraded")) {
+        array.degraded = degraded
+            .parse::<u32>()
+            .map(|n| n > 0)
+            .unwrap_or(array.degraded);
+    }
+    if let Some(action) = read_sysfs_attr(&md_path.join("sync_action")) {
+        if action != "idle" {
+            array.sync_action = Some(action);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_info::test_util::TempDir;
+
+    const MDSTAT: &str = "\
+Personalities : [raid1] [raid6] [raid5] [raid4]
+md0 : active raid1 sdb1[1] sda1[0]
+      1046528 blocks super 1.2 [2/2] [UU]
+
+md1 : active raid5 sdf[3](S) sde[2](F) sdd[1] sdc[0]
+      2093056 blocks super 1.2 level 5, 512k chunk, algorithm 2 [3/2] [UU_]
+      [=>...................]  recovery =  8.5% (89088/1046528) finish=0.5min speed=29696K/sec
+
+unused devices: <none>
+";
+
+    #[test]
+    fn test_parse_mdstat() {
+        let arrays = parse_mdstat(MDSTAT);
+        assert_eq!(arrays.len(), 2);
+
+        let md0 = &arrays[0];
+        assert_eq!(md0.name, "md0");
+        assert_eq!(md0.level, "raid1");
+        assert_eq!(md0.state, "active");
+        assert_eq!(md0.members.len(), 2);
+        assert!(!md0.degraded);
+        assert_eq!(md0.sync_action, None);
+
+        let md1 = &arrays[1];
+        assert_eq!(md1.level, "raid5");
+        assert_eq!((md1.raid_disks, md1.active_disks), (3, 2));
+        assert!(md1.degraded);
+        assert!(md1.members.iter().any(|m| m.device == "sdf" && m.spare));
+        assert!(md1.members.iter().any(|m| m.device == "sde" && m.faulty));
+        assert_eq!(md1.sync_action.as_deref(), Some("recovery"));
+        assert_eq!(md1.sync_progress_percent, Some(8.5));
+    }
+
+    #[test]
+    fn test_split_lvm_name() {
+        assert_eq!(
+            split_lvm_name("vg0-root"),
+            Some(("vg0".to_string(), "root".to_string()))
+        );
+        assert_eq!(
+            split_lvm_name("my--vg-data--lv"),
+            Some(("my-vg".to_string(), "data-lv".to_string()))
+        );
+        assert_eq!(split_lvm_name("nodash"), None);
+    }
+
+    #[test]
+    fn test_dm_kind_from_uuid() {
+        assert_eq!(dm_kind_from_uuid("LVM-abcdef"), DmKind::Lvm);
+        assert_eq!(dm_kind_from_uuid("CRYPT-LUKS2-1234-luks"), DmKind::Crypt);
+        assert_eq!(dm_kind_from_uuid("mpath-3600"), DmKind::Multipath);
+        assert_eq!(dm_kind_from_uuid(""), DmKind::Other);
+    }
+
+    #[test]
+    fn test_backing_devices() {
+        let link = |name: &str, parent: Option<&str>, slaves: &[&str]| BlockDeviceLinks {
+            name: name.to_string(),
+            parent: parent.map(str::to_string),
+            slaves: slaves.iter().map(|s| s.to_string()).collect(),
+            holders: Vec::new(),
+        };
+        let mut md0 = parse_mdstat(MDSTAT).remove(0);
+        md0.degraded = true;
+
+        let topology = StorageTopology {
+            md_arrays: vec![md0],
+            dm_devices: vec![DmDevice {
+                device: "dm-0".to_string(),
+                name: "vg0-root".to_string(),
+                uuid: "LVM-x".to_string(),
+                kind: DmKind::Lvm,
+                vg_name: Some("vg0".to_string()),
+                lv_name: Some("root".to_string()),
+                slaves: vec!["md0".to_string()],
+            }],
+            block_devices: vec![
+                link("dm-0", None, &["md0"]),
+                link("md0", None, &["sda1", "sdb1"]),
+                link("sda", None, &[]),
+                link("sda1", Some("sda"), &[]),
+                link("sdb", None, &[]),
+                link("sdb1", Some("sdb"), &[]),
+            ],
+        };
+
+        for name in ["dm-0", "vg0-root", "vg0/root", "/dev/mapper/vg0-root"] {
+            let backing = topology.backing_devices(name).unwrap();
+            assert_eq!(backing.device, "dm-0");
+            assert_eq!(backing.physical_disks, vec!["sda", "sdb"]);
+            assert_eq!(backing.md_arrays.len(), 1);
+            assert!(backing.degraded);
+        }
+        assert!(topology.backing_devices("nonexistent").is_none());
+    }
+
+    #[test]
+    fn test_storage_topology_new() -> Result<()> {
+        match StorageTopology::new() {
+            Ok(topology) => {
+                for dev in &topology.block_devices {
+                    let backing = topology.backing_devices(&dev.name).unwrap();
+                    assert!(!backing.physical_disks.is_empty());
+                    // Partitions always resolve to the disk they live on.
+                    for disk in &backing.physical_disks {
+                        assert!(topology
+                            .block_devices
+                            .iter()
+                            .all(|other| &other.name != disk || other.parent.is_none()));
+                    }
+                }
+                Ok(())
+            }
+            Err(_) => Ok(()),
+        }
+    }
+
+    #[test]
+    fn test_backing_devices_partitioned_md() -> Result<()> {
+        let root = TempDir::new("storage");
+        let devices = root.join("devices");
+        let class = root.join("class");
+        fs::create_dir_all(&class)?;
+
+        // sda/sda1 and sdb/sdb1 form md0, which is partitioned; dm-0 (LVM)
+        // sits on md0p1.
+        for (disk, partition) in [
+            ("sda", None),
+            ("sda", Some("sda1")),
+            ("sdb", None),
+            ("sdb", Some("sdb1")),
+            ("md0", None),
+            ("md0", Some("md0p1")),
+            ("dm-0", None),
+        ] {
+            let name = partition.unwrap_or(disk);
+            let dir = match partition {
+                Some(partition) => devices.join(disk).join(partition),
+                None => devices.join(disk),
+            };
+            fs::create_dir_all(dir.join("slaves"))?;
+            fs::create_dir_all(dir.join("holders"))?;
+            if partition.is_some() {
+                fs::write(dir.join("partition"), "1\n")?;
+            }
+            std::os::unix::fs::symlink(&dir, class.join(name))?;
+        }
+        for (device, slave) in [("md0", "sda1"), ("md0", "sdb1"), ("dm-0", "md0p1")] {
+            fs::create_dir(class.join(device).join("slaves").join(slave))?;
+            fs::create_dir(class.join(slave).join("holders").join(device))?;
+        }
+        fs::create_dir_all(class.join("md0/md"))?;
+        fs::write(class.join("md0/md/array_state"), "clean\n")?;
+        fs::write(class.join("md0/md/degraded"), "1\n")?;
+        fs::create_dir_all(class.join("dm-0/dm"))?;
+        fs::write(class.join("dm-0/dm/name"), "vg0-root\n")?;
+        fs::write(class.join("dm-0/dm/uuid"), "LVM-abc\n")?;
+        fs::write(root.join("mdstat"), MDSTAT)?;
+
+        let topology = StorageTopology::from_paths(&class, root.join("mdstat"))?;
+        let md0 = topology
+            .block_devices
+            .iter()
+            .find(|dev| dev.name == "md0p1");
+        assert_eq!(md0.and_then(|dev| dev.parent.as_deref()), Some("md0"));
+        assert_eq!(topology.md_arrays[0].state, "clean");
+        assert_eq!(topology.dm_devices[0].slaves, vec!["md0p1"]);
+        assert_eq!(topology.dm_devices[0].vg_name.as_deref(), Some("vg0"));
+
+        let backing = topology.backing_devices("vg0/root").unwrap();
+        assert_eq!(backing.device, "dm-0");
+        assert_eq!(backing.physical_disks, vec!["sda", "sdb"]);
+        assert_eq!(backing.md_arrays.len(), 1);
+        assert_eq!(backing.md_arrays[0].name, "md0");
+        assert!(backing.degraded);
+
+        let backing = topology.backing_devices("/dev/sda1").unwrap();
+        assert_eq!(backing.physical_disks, vec!["sda"]);
+        assert!(backing.md_arrays.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cmp::Ordering;
+
+/// Kernel and host identification as returned by `uname(2)`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Uname {
+    pub sysname: String,
+    pub nodename: String,
+    pub release: String,
+    pub version: String,
+    pub machine: String,
+    #[serde(default)]
+    pub domainname: String,
+    /// `release` split into its numeric components, if it could be parsed.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub kernel_version: Option<KernelVersion>,
+    /// Schema 1.0 `uname` string that is not a JSON document, kept verbatim
+    /// so that stored data is not lost.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub raw: Option<String>,
+}
+
+/// A kernel release such as `5.15.0-91-generic`, split into
+/// `major.minor.patch-extra-flavour`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct KernelVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    /// Distribution ABI or build number, e.g. `91` or `513.5.1.el8_9.x86_64`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub extra: Option<String>,
+    /// Kernel flavour, e.g. `generic`, `aws` or `cloudlinux`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub flavour: Option<String>,
+}
+
+impl Uname {
+    pub fn new() -> Result<Self> {
+        let uname = nix::sys::utsname::uname().context("Failed to get uname info")?;
+        let release = uname.release().to_string_lossy().into_owned();
+
+        Ok(Self {
+            sysname: uname.sysname().to_string_lossy().into_owned(),
+            nodename: uname.nodename().to_string_lossy().into_owned(),
+            kernel_version: KernelVersion::parse(&release),
+            release,
+            version: uname.version().to_string_lossy().into_owned(),
+            machine: uname.machine().to_string_lossy().into_owned(),
+            domainname: uname.domainname().to_string_lossy().into_owned(),
+            raw: None,
+        })
+    }
+}
+
+impl KernelVersion {
+    /// Parses a kernel release string. Missing minor and patch levels are
+    /// taken as zero, so `6.8-rc1` parses as 6.8.0 with flavour `rc1`.
+    pub fn parse(release: &str) -> Option<Self> {
+        let (numbers, suffix) = match release.find(|c: char| !c.is_ascii_digit() && c != '.') {
+            Some(index) => (&release[..index], &release[index..]),
+            None => (release, ""),
+        };
+        let mut components = numbers.split('.').map(str::parse::<u32>);
+        let major = components.next()?.ok()?;
+        let minor = components.next().and_then(|c| c.ok()).unwrap_or(0);
+        let patch = components.next().and_then(|c| c.ok()).unwrap_or(0);
+
+        let suffix = suffix.trim_start_matches(['-', '+', '_']);
+        let (extra, flavour) = if suffix.starts_with(|c: char| c.is_ascii_digit()) {
+            match suffix.split_once('-') {
+                Some((extra, flavour)) => (Some(extra), Some(flavour)),
+                None => (Some(suffix), None),
+            }
+        } else {
+            (None, Some(suffix))
+        };
+        let non_empty = |s: Option<&str>| s.filter(|s| !s.is_empty()).map(str::to_string);
+
+        Some(Self {
+            major,
+            minor,
+            patch,
+            extra: non_empty(extra),
+            flavour: non_empty(flavour),
+        })
+    }
+
+    /// Compares the numeric `major.minor.patch` part, ignoring extra and
+    /// flavour.
+    pub fn compare(&self, other: &KernelVersion) -> Ordering {
+        (self.major, self.minor, self.patch).cmp(&(other.major, other.minor, other.patch))
+    }
+
+    /// Returns true if this kernel is at least `major.minor.patch`.
+    pub fn at_least(&self, major: u32, minor: u32, patch: u32) -> bool {
+        (self.major, self.minor, self.patch) >= (major, minor, patch)
+    }
+}
+
+/// Accepts both the nested object and the schema 1.0 form, in which the
+/// uname fields were stored as a JSON document inside a string. A string
+/// that does not hold such a document ends up in [`Uname::raw`].
+pub(crate) fn deserialize_uname<'de, D>(deserializer: D) -> Result<Uname, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum UnameRepr {
+        Typed(Box<Uname>),
+        Legacy(String),
+    }
+
+    let mut uname = match UnameRepr::deserialize(deserializer)? {
+        UnameRepr::Typed(uname) => *uname,
+        UnameRepr::Legacy(json) => serde_json::from_str(&json).unwrap_or_else(|_| Uname {
+            raw: Some(json),
+            ..Default::default()
+        }),
+    };
+    if uname.kernel_version.is_none() {
+        uname.kernel_version = KernelVersion::parse(&uname.release);
+    }
+    Ok(uname)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_kernel_version() {
+        let ubuntu = KernelVersion::parse("5.15.0-91-generic").unwrap();
+        assert_eq!((ubuntu.major, ubuntu.minor, ubuntu.patch), (5, 15, 0));
+        assert_eq!(ubuntu.extra.as_deref(), Some("91"));
+        assert_eq!(ubuntu.flavour.as_deref(), Some("generic"));
+
+        let rhel = KernelVersion::parse("4.18.0-513.5.1.el8_9.x86_64").unwrap();
+        assert_eq!(rhel.extra.as_deref(), Some("513.5.1.el8_9.x86_64"));
+        assert_eq!(rhel.flavour, None);
+
+        let custom = KernelVersion::parse("6.6.31-cloudlinux").unwrap();
+        assert_eq!(custom.extra, None);
+        assert_eq!(custom.flavour.as_deref(), Some("cloudlinux"));
+
+        let rc = KernelVersion::parse("6.8-rc1").unwrap();
+        assert_eq!((rc.major, rc.minor, rc.patch), (6, 8, 0));
+        assert_eq!(rc.flavour.as_deref(), Some("rc1"));
+
+        assert_eq!(KernelVersion::parse(""), None);
+        assert_eq!(KernelVersion::parse("unknown"), None);
+    }
+
+    #[test]
+    fn test_kernel_version_comparison() {
+        let version = KernelVersion::parse("5.15.0-91-generic").unwrap();
+        assert!(version.at_least(5, 15, 0));
+        assert!(version.at_least(4, 19, 200));
+        assert!(!version.at_least(5, 15, 1));
+        assert_eq!(
+            version.compare(&KernelVersion::parse("5.15.0-1050-aws").unwrap()),
+            Ordering::Equal
+        );
+        assert_eq!(
+            version.compare(&KernelVersion::parse("6.1.0").unwrap()),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn test_deserialize_legacy_uname() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            #[serde(deserialize_with = "deserialize_uname")]
+            uname: Uname,
+        }
+
+        let legacy = r#"{"uname": "{\"machine\":\"x86_64\",\"nodename\":\"host\",\"release\":\"6.6.31-cloudlinux\",\"sysname\":\"Linux\",\"version\":\"1 SMP\"}"}"#;
+        let uname = serde_json::from_str::<Wrapper>(legacy).unwrap().uname;
+        assert_eq!(uname.machine, "x86_64");
+        assert_eq!(uname.domainname, "");
+        assert_eq!(uname.kernel_version.unwrap().major, 6);
+
+        let typed = serde_json::json!({ "uname": Uname::new().unwrap() });
+        let uname = serde_json::from_value::<Wrapper>(typed).unwrap().uname;
+        assert_eq!(uname, Uname::new().unwrap());
+
+        let garbage = serde_json::from_str::<Wrapper>(r#"{"uname": "Test Uname"}"#).unwrap();
+        assert_eq!(garbage.uname.raw.as_deref(), Some("Test Uname"));
+        assert_eq!(garbage.uname.release, "");
+
+        let reserialized = serde_json::json!({ "uname": garbage.uname });
+        let uname = serde_json::from_value::<Wrapper>(reserialized)
+            .unwrap()
+            .uname;
+        assert_eq!(uname.raw.as_deref(), Some("Test Uname"));
+    }
+}
@@ -0,0 +1,119 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+const PROC_STAT_PATH: &str = "/proc/stat";
+const UPTIME_PATH: &str = "/proc/uptime";
+const LOADAVG_PATH: &str = "/proc/loadavg";
+const BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+
+/// When the system booted and how busy it has been since.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct UptimeInfo {
+    /// Boot time in seconds since the Unix epoch, from `btime` in `/proc/stat`.
+    pub boot_time: u64,
+    pub uptime_seconds: f64,
+    /// Idle time summed over all CPUs, so it can exceed `uptime_seconds`.
+    pub idle_seconds: f64,
+    pub load_average: LoadAverage,
+    /// Random UUID generated by the kernel on every boot.
+    pub boot_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct LoadAverage {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+    /// Runnable scheduling entities (tasks) at the time of reading.
+    pub running_tasks: u32,
+    pub total_tasks: u32,
+    pub last_pid: u32,
+}
+
+impl UptimeInfo {
+    pub fn new() -> Result<Self> {
+        let stat = fs::read_to_string(PROC_STAT_PATH)
+            .with_context(|| format!("Failed to read {}", PROC_STAT_PATH))?;
+        let uptime = fs::read_to_string(UPTIME_PATH)
+            .with_context(|| format!("Failed to read {}", UPTIME_PATH))?;
+        let loadavg = fs::read_to_string(LOADAVG_PATH)
+            .with_context(|| format!("Failed to read {}", LOADAVG_PATH))?;
+        let (uptime_seconds, idle_seconds) =
+            parse_uptime(&uptime).with_context(|| format!("Failed to parse {}", UPTIME_PATH))?;
+
+        Ok(Self {
+            boot_time: parse_boot_time(&stat)
+                .with_context(|| format!("Failed to find btime in {}", PROC_STAT_PATH))?,
+            uptime_seconds,
+            idle_seconds,
+            load_average: parse_loadavg(&loadavg)
+                .with_context(|| format!("Failed to parse {}", LOADAVG_PATH))?,
+            boot_id: fs::read_to_string(BOOT_ID_PATH)
+                .map(|id| id.trim().to_string())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+fn parse_boot_time(stat: &str) -> Option<u64> {
+    stat.lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+fn parse_uptime(content: &str) -> Option<(f64, f64)> {
+    let mut fields = content.split_whitespace().map(str::parse::<f64>);
+    Some((fields.next()?.ok()?, fields.next()?.ok()?))
+}
+
+/// Parses `/proc/loadavg`, e.g. `0.52 0.58 0.59 2/1043 123456`.
+fn parse_loadavg(content: &str) -> Option<LoadAverage> {
+    let fields: Vec<&str> = content.split_whitespace().collect();
+    let (running, total) = fields.get(3)?.split_once('/')?;
+
+    Some(LoadAverage {
+        one: fields.first()?.parse().ok()?,
+        five: fields.get(1)?.parse().ok()?,
+        fifteen: fields.get(2)?.parse().ok()?,
+        running_tasks: running.parse().ok()?,
+        total_tasks: total.parse().ok()?,
+        last_pid: fields.get(4)?.parse().ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_files() {
+        let stat = "cpu  10 0 20 300 0 0 0 0 0 0\nintr 12345\nctxt 999\nbtime 1718000000\nprocesses 4242\n";
+        assert_eq!(parse_boot_time(stat), Some(1718000000));
+        assert_eq!(parse_boot_time("cpu 1 2 3\n"), None);
+
+        assert_eq!(
+            parse_uptime("350735.47 234388.90\n"),
+            Some((350735.47, 234388.90))
+        );
+        assert_eq!(parse_uptime("garbage"), None);
+
+        let load = parse_loadavg("0.52 0.58 0.59 2/1043 123456\n").unwrap();
+        assert_eq!(load.one, 0.52);
+        assert_eq!(load.fifteen, 0.59);
+        assert_eq!(load.running_tasks, 2);
+        assert_eq!(load.total_tasks, 1043);
+        assert_eq!(load.last_pid, 123456);
+        assert_eq!(parse_loadavg("0.52 0.58 0.59"), None);
+    }
+
+    #[test]
+    fn test_uptime_info_new() -> Result<()> {
+        let uptime = UptimeInfo::new()?;
+        assert!(uptime.boot_time > 0);
+        assert!(uptime.uptime_seconds > 0.0);
+        assert!(uptime.load_average.total_tasks >= uptime.load_average.running_tasks);
+        assert_eq!(uptime.boot_id.len(), 36);
+        Ok(())
+    }
+}
@@ -0,0 +1,497 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// Version ordering rules of the supported package ecosystems.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionScheme {
+    /// Debian `[epoch:]upstream[-revision]`, where `~` sorts before anything.
+    Dpkg,
+    /// rpm `[epoch:]version-release` compared with `rpmvercmp`.
+    Rpm,
+    /// Alpine `1.2.3a_rc1-r2`.
+    Apk,
+    /// Semantic versioning as used by npm and crates.io.
+    #[default]
+    Semver,
+    /// Python version specifiers (PEP 440).
+    Pep440,
+}
+
+/// Compares two versions according to `scheme`.
+pub fn compare_versions(scheme: VersionScheme, a: &str, b: &str) -> Ordering {
+    match scheme {
+        VersionScheme::Dpkg => dpkg_compare(a, b),
+        VersionScheme::Rpm => rpm_compare(a, b),
+        VersionScheme::Apk => apk_compare(a, b),
+        VersionScheme::Semver => semver_compare(a, b),
+        VersionScheme::Pep440 => pep440_compare(a, b),
+    }
+}
+
+/// Splits off a numeric `epoch:` prefix, defaulting to 0.
+fn split_epoch(version: &str) -> (u64, &str) {
+    match version.split_once(':') {
+        Some((epoch, rest)) if epoch.chars().all(|c| c.is_ascii_digit()) => {
+            (epoch.parse().unwrap_or(0), rest)
+        }
+        _ => (0, version),
+    }
+}
+
+fn dpkg_compare(a: &str, b: &str) -> Ordering {
+    let split = |version: &str| {
+        let (epoch, rest) = split_epoch(version);
+        match rest.rsplit_once('-') {
+            Some((upstream, revision)) => (epoch, upstream.to_string(), revision.to_string()),
+            None => (epoch, rest.to_string(), String::new()),
+        }
+    };
+    let (a_epoch, a_upstream, a_revision) = split(a);
+    let (b_epoch, b_upstream, b_revision) = split(b);
+
+    a_epoch
+        .cmp(&b_epoch)
+        .then_with(|| dpkg_verrevcmp(&a_upstream, &b_upstream))
+        .then_with(|| dpkg_verrevcmp(&a_revision, &b_revision))
+}
+
+/// Port of dpkg's `verrevcmp()`: alternating non-digit and digit runs, with
+/// `~` sorting before everything, even the end of the string.
+fn dpkg_verrevcmp(a: &str, b: &str) -> Ordering {
+    fn order(c: Option<u8>) -> i32 {
+        match c {
+            Some(b'~') => -1,
+            Some(c) if c.is_ascii_digit() => 0,
+            Some(c) if c.is_ascii_alphabetic() => c as i32,
+            Some(c) => c as i32 + 256,
+            None => 0,
+        }
+    }
+
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        while (i < a.len() && !a[i].is_ascii_digit()) || (j < b.len() && !b[j].is_ascii_digit()) {
+            let (ac, bc) = (order(a.get(i).copied()), order(b.get(j).copied()));
+            if ac != bc {
+                return ac.cmp(&bc);
+            }
+            i += 1;
+            j += 1;
+        }
+        let a_digits = take_while(a, &mut i, u8::is_ascii_digit);
+        let b_digits = take_while(b, &mut j, u8::is_ascii_digit);
+        let ordering = compare_numeric(a_digits, b_digits);
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+fn rpm_compare(a: &str, b: &str) -> Ordering {
+    let split = |version: &str| {
+        let (epoch, rest) = split_epoch(version);
+        match rest.rsplit_once('-') {
+            Some((version, release)) => (epoch, version.to_string(), Some(release.to_string())),
+            None => (epoch, rest.to_string(), None),
+        }
+    };
+    let (a_epoch, a_version, a_release) = split(a);
+    let (b_epoch, b_version, b_release) = split(b);
+
+    a_epoch
+        .cmp(&b_epoch)
+        .then_with(|| rpmvercmp(&a_version, &b_version))
+        .then_with(|| match (a_release, b_release) {
+            (Some(a), Some(b)) => rpmvercmp(&a, &b),
+            // A version without release matches any release of it.
+            _ => Ordering::Equal,
+        })
+}
+
+/// Port of rpm's `rpmvercmp()`, including `~` (pre-release) and `^`
+/// (post-release snapshot) handling.
+fn rpmvercmp(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+    let is_separator = |c: &u8| !c.is_ascii_alphanumeric() && *c != b'~' && *c != b'^';
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+
+    loop {
+        take_while(a, &mut i, is_separator);
+        take_while(b, &mut j, is_separator);
+
+        let (ac, bc) = (a.get(i), b.get(j));
+        if ac == Some(&b'~') || bc == Some(&b'~') {
+            if ac != Some(&b'~') {
+                return Ordering::Greater;
+            }
+            if bc != Some(&b'~') {
+                return Ordering::Less;
+            }
+            i += 1;
+            j += 1;
+            continue;
+        }
+        if ac == Some(&b'^') || bc == Some(&b'^') {
+            if ac.is_none() {
+                return Ordering::Less;
+            }
+            if bc.is_none() {
+                return Ordering::Greater;
+            }
+            if ac != Some(&b'^') {
+                return Ordering::Greater;
+            }
+            if bc != Some(&b'^') {
+                return Ordering::Less;
+            }
+            i += 1;
+            j += 1;
+            continue;
+        }
+        if ac.is_none() || bc.is_none() {
+            break;
+        }
+
+        let numeric = a[i].is_ascii_digit();
+        let (a_segment, b_segment) = if numeric {
+            (
+                take_while(a, &mut i, u8::is_ascii_digit),
+                take_while(b, &mut j, u8::is_ascii_digit),
+            )
+        } else {
+            (
+                take_while(a, &mut i, u8::is_ascii_alphabetic),
+                take_while(b, &mut j, u8::is_ascii_alphabetic),
+            )
+        };
+        // Segments of different types: numeric is newer than alphabetic.
+        if b_segment.is_empty() {
+            return if numeric {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            };
+        }
+        let ordering = if numeric {
+            compare_numeric(a_segment, b_segment)
+        } else {
+            a_segment.cmp(b_segment)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    (i < a.len()).cmp(&(j < b.len()))
+}
+
+/// apk suffixes in ascending order; a version without suffix sorts between
+/// `rc` and `cvs`.
+const APK_SUFFIXES: [&str; 9] = ["alpha", "beta", "pre", "rc", "cvs", "svn", "git", "hg", "p"];
+const APK_NO_SUFFIX: usize = 4;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct ApkVersion {
+    numbers: Vec<u64>,
+    letter: Option<u8>,
+    suffixes: Vec<(usize, u64)>,
+    revision: u64,
+}
+
+fn parse_apk_version(version: &str) -> ApkVersion {
+    let (main, revision) = match version.rsplit_once("-r") {
+        Some((main, revision)) if revision.chars().all(|c| c.is_ascii_digit()) => {
+            (main, revision.parse().unwrap_or(0))
+        }
+        _ => (version, 0),
+    };
+    let (main, suffix_part) = main.split_once('_').unwrap_or((main, ""));
+    let (numbers_part, letter) = match main.as_bytes().last() {
+        Some(c) if c.is_ascii_alphabetic() => (&main[..main.len() - 1], Some(*c)),
+        _ => (main, None),
+    };
+
+    let mut suffixes: Vec<(usize, u64)> = suffix_part
+        .split('_')
+        .filter(|suffix| !suffix.is_empty())
+        .map(|suffix| {
+            let name_len = suffix
+                .find(|c: char| c.is_ascii_digit())
+                .unwrap_or(suffix.len());
+            let rank = APK_SUFFIXES
+                .iter()
+                .position(|known| *known == &suffix[..name_len])
+                .unwrap_or(APK_NO_SUFFIX);
+            (rank, suffix[name_len..].parse().unwrap_or(0))
+        })
+        .collect();
+    // Pad so that "1.0" compares as "1.0_<none>" against "1.0_rc1".
+    suffixes.push((APK_NO_SUFFIX, 0));
+
+    ApkVersion {
+        numbers: numbers_part
+            .split('.')
+            .map(|number| number.parse().unwrap_or(0))
+            .collect(),
+        letter,
+        suffixes,
+        revision,
+    }
+}
+
+fn apk_compare(a: &str, b: &str) -> Ordering {
+    parse_apk_version(a).cmp(&parse_apk_version(b))
+}
+
+fn semver_compare(a: &str, b: &str) -> Ordering {
+    let parse = |version: &str| {
+        let version = version.trim().trim_start_matches('v');
+        let version = version.split_once('+').map_or(version, |(core, _)| core);
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (version, None),
+        };
+        let mut numbers = core.split('.').map(|n| n.parse::<u64>().unwrap_or(0));
+        let triple = (
+            numbers.next().unwrap_or(0),
+            numbers.next().unwrap_or(0),
+            numbers.next().unwrap_or(0),
+        );
+        (triple, pre.map(str::to_string))
+    };
+    let (a_core, a_pre) = parse(a);
+    let (b_core, b_pre) = parse(b);
+
+    a_core.cmp(&b_core).then_with(|| match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => {
+            let mut a_ids = a.split('.');
+            let mut b_ids = b.split('.');
+            loop {
+                match (a_ids.next(), b_ids.next()) {
+                    (None, None) => return Ordering::Equal,
+                    (None, Some(_)) => return Ordering::Less,
+                    (Some(_), None) => return Ordering::Greater,
+                    (Some(a), Some(b)) => {
+                        let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+                            (Ok(a), Ok(b)) => a.cmp(&b),
+                            // Numeric identifiers sort before alphanumeric ones.
+                            (Ok(_), Err(_)) => Ordering::Less,
+                            (Err(_), Ok(_)) => Ordering::Greater,
+                            (Err(_), Err(_)) => a.cmp(b),
+                        };
+                        if ordering != Ordering::Equal {
+                            return ordering;
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Sort key of a PEP 440 version: (epoch, release, pre, post, dev).
+type Pep440Key = (u64, Vec<u64>, (u8, u64), i64, u64);
+
+fn pep440_key(version: &str) -> Pep440Key {
+    let version = version.trim().to_lowercase();
+    let version = version.trim_start_matches('v');
+    let version = version
+        .split_once('+')
+        .map_or(version, |(public, _)| public);
+    let (epoch, version) = match version.split_once('!') {
+        Some((epoch, rest)) => (epoch.parse().unwrap_or(0), rest),
+        None => (0, version),
+    };
+
+    let release_end = version
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(version.len());
+    let mut release: Vec<u64> = version[..release_end]
+        .split('.')
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().unwrap_or(0))
+        .collect();
+    while release.last() == Some(&0) {
+        release.pop();
+    }
+
+    let (mut pre, mut post, mut dev) = (None, None, None);
+    let mut rest = &version[release_end..];
+    while !rest.is_empty() {
+        rest = rest.trim_start_matches(['.', '-', '_']);
+        let label_len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let (label, tail) = rest.split_at(label_len);
+        let tail = tail.trim_start_matches(['.', '-', '_']);
+        let number_len = tail
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let number = tail[..number_len].parse().unwrap_or(0);
+        match label {
+            "a" | "alpha" => pre = Some((0, number)),
+            "b" | "beta" => pre = Some((1, number)),
+            "c" | "rc" | "pre" | "preview" => pre = Some((2, number)),
+            "post" | "rev" | "r" | "" => post = Some(number),
+            "dev" => dev = Some(number),
+            _ => {}
+        }
+        if label.is_empty() && number_len == 0 {
+            break;
+        }
+        rest = &tail[number_len..];
+    }
+
+    // A dev release of a final version sorts before its pre-releases.
+    let pre_key = match (pre, post, dev) {
+        (Some((kind, number)), _, _) => (kind + 1, number),
+        (None, None, Some(_)) => (0, 0),
+        _ => (u8::MAX, 0),
+    };
+    (
+        epoch,
+        release,
+        pre_key,
+        post.map_or(-1, |post| post as i64),
+        dev.unwrap_or(u64::MAX),
+    )
+}
+
+fn pep440_compare(a: &str, b: &str) -> Ordering {
+    pep440_key(a).cmp(&pep440_key(b))
+}
+
+fn take_while<'a>(bytes: &'a [u8], index: &mut usize, predicate: impl Fn(&u8) -> bool) -> &'a [u8] {
+    let start = *index;
+    while *index < bytes.len() && predicate(&bytes[*index]) {
+        *index += 1;
+    }
+    &bytes[start..*index]
+}
+
+/// Compares digit strings of any length by value.
+fn compare_numeric(a: &[u8], b: &[u8]) -> Ordering {
+    let strip = |digits: &[u8]| -> usize { digits.iter().take_while(|d| **d == b'0').count() };
+    let (a, b) = (&a[strip(a)..], &b[strip(b)..]);
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_ascending(scheme: VersionScheme, versions: &[&str]) {
+        for pair in versions.windows(2) {
+            assert_eq!(
+                compare_versions(scheme, pair[0], pair[1]),
+                Ordering::Less,
+                "{:?}: expected {} < {}",
+                scheme,
+                pair[0],
+                pair[1]
+            );
+            assert_eq!(
+                compare_versions(scheme, pair[1], pair[0]),
+                Ordering::Greater
+            );
+        }
+    }
+
+    #[test]
+    fn test_dpkg_compare() {
+        assert_ascending(
+            VersionScheme::Dpkg,
+            &[
+                "1.0~rc1",
+                "1.0",
+                "1.0-1",
+                "1.0-1ubuntu1",
+                "1.0-1ubuntu1.1",
+                "1.0+dfsg-1",
+                "1.10",
+                "1:0.9",
+            ],
+        );
+        assert_eq!(dpkg_compare("2.36-9+deb12u4", "2.36-9"), Ordering::Greater);
+        assert_eq!(dpkg_compare("0:1.0-01", "1.0-1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_rpm_compare() {
+        assert_ascending(
+            VersionScheme::Rpm,
+            &[
+                "1.0~rc1-1",
+                "1.0-1",
+                "1.0^git1-1",
+                "1.0a-1",
+                "1.0.1-1",
+                "1.9-1",
+                "1.10-1",
+                "1:0.1-1",
+            ],
+        );
+        assert_eq!(rpm_compare("5.1.8-6.el9", "5.1.8-6.el9_1"), Ordering::Less);
+        assert_eq!(rpm_compare("1.0", "1.0-5"), Ordering::Equal);
+        assert_eq!(rpmvercmp("1.0010", "1.10"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_apk_compare() {
+        assert_ascending(
+            VersionScheme::Apk,
+            &[
+                "1.2.3_alpha1-r0",
+                "1.2.3_rc1-r0",
+                "1.2.3-r0",
+                "1.2.3-r1",
+                "1.2.3_p1-r0",
+                "1.2.3a-r0",
+                "1.2.4-r0",
+                "1.10-r0",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_semver_and_pep440_compare() {
+        assert_ascending(
+            VersionScheme::Semver,
+            &[
+                "1.0.0-alpha",
+                "1.0.0-alpha.1",
+                "1.0.0-alpha.beta",
+                "1.0.0-beta.2",
+                "1.0.0-beta.11",
+                "1.0.0",
+                "v1.2",
+                "1.10.0",
+            ],
+        );
+        assert_eq!(semver_compare("1.0.0+build.5", "1.0.0"), Ordering::Equal);
+
+        assert_ascending(
+            VersionScheme::Pep440,
+            &[
+                "1.0.dev1",
+                "1.0a1",
+                "1.0b2",
+                "1.0rc1",
+                "1.0",
+                "1.0.post1",
+                "1.1",
+                "1!0.1",
+            ],
+        );
+        assert_eq!(pep440_compare("2.0", "2.0.0"), Ordering::Equal);
+    }
+}